@@ -0,0 +1,158 @@
+//! Measures how long it takes to compile Bayesian networks into a CNF. Also
+//! compares classifying many rows with one compilation against compiling
+//! once per row, all marginals from one compilation against one `marginal`
+//! call per value, BDDs compiled under the default variable order against
+//! `var_order`, and a cached topological sort against computing it afresh.
+//!
+//! The `stats` and `bdd` modes of the `compile` example report the sizes of
+//! the CNFs and BDDs compared here.
+//!
+//! Run with `cargo bench --bench compile`.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use rand::{rngs::StdRng, SeedableRng};
+use rsdd::repr::{ddnnf::DDNNFPtr, var_label::VarLabel};
+use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+use std::collections::HashMap;
+
+/// models the collider A, B -> C
+static COLLIDER: &str = r#"{
+    "network": "toy_network",
+    "variables": ["A", "B", "C"],
+    "cpts": {
+        "A": [[0.5], [0.5]],
+        "B": [[0.25], [0.75]],
+        "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    },
+    "states": {
+        "A": ["F", "T"],
+        "B": ["F", "T"],
+        "C": ["F", "T"]
+    },
+    "parents" :{
+        "A": [],
+        "B": [],
+        "C": ["A", "B"]
+    }
+}"#;
+
+fn networks() -> Vec<(&'static str, BayesianNetwork)> {
+    vec![
+        ("collider", BayesianNetwork::from_json(COLLIDER)),
+        (
+            "sachs",
+            BayesianNetwork::from_json(include_str!("../bayesian_networks/sachs.json")),
+        ),
+        (
+            "random_200",
+            BayesianNetwork::random(200, 3, 3, &mut StdRng::seed_from_u64(0)),
+        ),
+    ]
+}
+
+fn bench_compile(c: &mut Criterion) {
+    for (name, bn) in networks() {
+        c.bench_function(&format!("compile {name}"), |b| {
+            b.iter(|| BayesianNetworkCNF::from_bayesian_network(black_box(&bn)))
+        });
+    }
+}
+
+fn bench_predict(c: &mut Criterion) {
+    let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(include_str!(
+        "../bayesian_networks/sachs.json"
+    )));
+    let observed = ["Erk", "Mek", "PKA", "Raf"];
+    let rows: Vec<HashMap<String, String>> = bn
+        .sample_from_compiled(100, &mut StdRng::seed_from_u64(0))
+        .into_iter()
+        .map(|mut row| {
+            row.retain(|var, _| observed.contains(&var.as_str()));
+            row
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("predict sachs");
+    group.bench_function("one compilation", |b| {
+        b.iter(|| bn.predict(black_box(&rows), "Akt"))
+    });
+    group.bench_function("compilation per row", |b| {
+        b.iter(|| {
+            black_box(&rows)
+                .iter()
+                .map(|row| bn.joint_marginal(&[String::from("Akt")], row))
+                .collect::<Vec<_>>()
+        })
+    });
+    group.finish();
+}
+
+fn bench_all_marginals(c: &mut Criterion) {
+    let network = BayesianNetwork::from_json(include_str!("../bayesian_networks/sachs.json"));
+    let bn = BayesianNetworkCNF::from_bayesian_network(&network);
+    let states: Vec<(String, Vec<String>)> = network
+        .variables()
+        .iter()
+        .map(|var| (var.clone(), network.all_possible_assignments(var).clone()))
+        .collect();
+
+    let mut group = c.benchmark_group("all marginals sachs");
+    group.bench_function("one compilation", |b| {
+        b.iter(|| black_box(&bn).all_marginals())
+    });
+    group.bench_function("compilation per value", |b| {
+        b.iter(|| {
+            states
+                .iter()
+                .flat_map(|(var, values)| values.iter().map(|value| bn.marginal(var, value)))
+                .collect::<Vec<_>>()
+        })
+    });
+    group.finish();
+}
+
+fn bench_var_order(c: &mut Criterion) {
+    let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(include_str!(
+        "../bayesian_networks/sachs.json"
+    )));
+    let default_order: Vec<VarLabel> = (0..bn.cnf().num_vars()).map(VarLabel::new_usize).collect();
+    let min_fill_order = bn.var_order();
+
+    let mut group = c.benchmark_group("bdd sachs");
+    for (name, order) in [
+        ("default order", &default_order),
+        ("var_order", &min_fill_order),
+    ] {
+        group.bench_function(name, |b| {
+            b.iter(|| bn.with_bdd(black_box(order), |bdd, _| bdd.count_nodes()))
+        });
+    }
+    group.finish();
+}
+
+fn bench_topological_sort(c: &mut Criterion) {
+    let bn = BayesianNetwork::random(50, 3, 3, &mut StdRng::seed_from_u64(0));
+    let json = bn.to_json();
+    bn.topological_sort();
+
+    let mut group = c.benchmark_group("topological_sort random_50");
+    group.bench_function("cached", |b| b.iter(|| black_box(&bn).topological_sort()));
+    group.bench_function("first call", |b| {
+        b.iter_batched(
+            || BayesianNetwork::from_json(&json),
+            |fresh| fresh.topological_sort(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_compile,
+    bench_predict,
+    bench_all_marginals,
+    bench_var_order,
+    bench_topological_sort
+);
+criterion_main!(benches);
@@ -32,7 +32,7 @@ struct Args {
     #[clap(short, long, value_parser)]
     file: String,
 
-    /// The compile mode ('bdd', 'sdd', 'bdd_using_builder', 'topdown', 'print')
+    /// The compile mode ('bdd', 'sdd', 'bdd_using_builder', 'topdown', 'stats', 'print')
     #[clap(short, long, value_parser)]
     mode: String,
 
@@ -69,6 +69,8 @@ fn compile_bdd(args: &Args, network: &BayesianNetwork) {
     let duration = start.elapsed();
     let sz = r.count_nodes();
     println!("Compiled\n\tTime: {:?}\n\tSize: {sz}", duration);
+    let var_order_sz = bn.with_bdd(&bn.var_order(), |bdd, _| bdd.count_nodes());
+    println!("\tSize under var_order: {var_order_sz}");
 
     match &args.query {
         Some(q) if q.eq("marginal") => {
@@ -219,9 +221,13 @@ fn main() {
         "bdd" => compile_bdd(&args, &bn),
         "bdd_using_builder" => compile_bdd_using_builder(&bn),
         "topdown" => compile_topdown(&bn),
+        "stats" => println!(
+            "CNF stats: {:?}",
+            BayesianNetworkCNF::from_bayesian_network(&bn).stats()
+        ),
         "print" => println!("printing BN: {:#?}", bn),
         _ => panic!(
-            "unrecognized mode; expected one of 'bdd', 'sdd', 'topdown', 'bdd_using_builder'"
+            "unrecognized mode; expected one of 'bdd', 'sdd', 'topdown', 'bdd_using_builder', 'stats'"
         ),
     }
     if args.dimacs {
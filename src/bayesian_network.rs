@@ -1,7 +1,19 @@
 //! A graphical representation of a Bayesian network
 
-use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashMap};
+use rand::Rng;
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fs::File,
+    io::BufReader,
+    path::Path,
+    sync::OnceLock,
+};
+
+use crate::{
+    sampling::sample_index, BnError, CycleError, Inference, ValidationError,
+    VariableEliminationEngine, Warning, Warnings,
+};
 
 /// maps each variable name to a Conditional Probability Table (CPT)
 /// - rows are indexed by the current variable's possible values
@@ -18,22 +30,126 @@ use std::collections::{BTreeMap, HashMap};
 ///          "b" -> [[0.3], [0.2], [0.5]],
 ///          "c" -> [[0.1, 0.2, 0.3, 0.4, 0.5, 0.6],
 ///                  [0.9, 0.8, 0.7, 0.6, 0.5, 0.4]]}```
-/// says Pr(c=c1 | a=a1, b=b1) = 0.1
-///      Pr(c=c1 | a=a2, b=b1) = 0.4
-///      Pr(c=c1 | a=a1, b=b3) = 0.3
-type ConditionalProbabilityTable = HashMap<String, Vec<Vec<f64>>>;
+///
+/// says Pr(c=c1 | a=a1, b=b1) = 0.1,
+/// Pr(c=c1 | a=a2, b=b1) = 0.4, and
+/// Pr(c=c1 | a=a1, b=b3) = 0.3
+pub type ConditionalProbabilityTable = HashMap<String, Vec<Vec<f64>>>;
 /// maps each variable name to a list of that variable's possible values
-type States = HashMap<String, Vec<String>>;
+pub type States = HashMap<String, Vec<String>>;
 /// maps each variable name to a list of that variable's parents
-type Parents = HashMap<String, Vec<String>>;
+pub type Parents = HashMap<String, Vec<String>>;
+/// assigns a value to each parent of some variable, selecting a CPT column
+pub type ParentConfig = HashMap<String, String>;
+
+/// One entry of a CPT: Pr(variable = value | parent_assignment) = probability
+#[derive(Debug, Clone, PartialEq)]
+pub struct CptCell {
+    pub variable: String,
+    pub value: String,
+    pub parent_assignment: ParentConfig,
+    pub probability: f64,
+}
+
+/// Free-form information about a variable that does not affect the
+/// distribution, e.g. for displaying the network in an editor
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct VariableMeta {
+    /// a human-readable description, e.g. including units
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    /// where to draw the variable, as (x, y)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<(f64, f64)>,
+    /// any other key-value annotations
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, String>,
+}
+
+/// how far a CPT column's sum may stray from 1 before `repair` rescales it
+pub const REPAIR_TOLERANCE: f64 = 1e-9;
+
+/// how far apart two CPT entries may be and still count as equal when
+/// comparing variables
+pub const EQUIVALENCE_TOLERANCE: f64 = 1e-9;
+
+/// the fraction of samples below which `approx_posterior_with_warnings`
+/// warns that too few were consistent with the evidence
+pub const MIN_ACCEPTANCE_RATE: f64 = 0.01;
+
+/// the resolution to which `content_hash` rounds CPT entries
+pub const HASH_RESOLUTION: f64 = 1e-12;
+
+/// 64-bit FNV-1a, whose output is fixed across platforms and Rust versions
+/// unlike `std`'s hashers
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Fnv1a {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+
+    /// writes the length first, so consecutive strings cannot run together
+    fn write_str(&mut self, s: &str) {
+        self.write_u64(s.len() as u64);
+        self.write(s.as_bytes());
+    }
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct BayesianNetwork {
-    network: String,
-    variables: Vec<String>,
-    cpts: ConditionalProbabilityTable,
-    states: States,
-    parents: Parents,
+    pub(crate) network: String,
+    pub(crate) variables: Vec<String>,
+    pub(crate) cpts: ConditionalProbabilityTable,
+    #[serde(deserialize_with = "deserialize_states")]
+    pub(crate) states: States,
+    pub(crate) parents: Parents,
+    /// optional metadata of some variables; absent from JSON written before
+    /// it existed, and omitted when empty
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub(crate) metadata: HashMap<String, VariableMeta>,
+    /// the result of `try_topological_sort`, computed on first use; anything
+    /// that edits `variables` or `parents` in place must reset it
+    #[serde(skip)]
+    pub(crate) topological_order: OnceLock<Result<Vec<String>, CycleError>>,
+}
+
+/// Reads the `states` map of a network, accepting numbers and booleans as
+/// well as strings for the states and storing each as its JSON text
+pub(crate) fn deserialize_states<'de, D, M>(deserializer: D) -> Result<M, D::Error>
+where
+    D: Deserializer<'de>,
+    M: FromIterator<(String, Vec<String>)>,
+{
+    let raw: HashMap<String, Vec<serde_json::Value>> = HashMap::deserialize(deserializer)?;
+    raw.into_iter()
+        .map(|(variable, states)| {
+            let states = states
+                .into_iter()
+                .map(|state| match state {
+                    serde_json::Value::String(s) => Ok(s),
+                    serde_json::Value::Bool(_) | serde_json::Value::Number(_) => {
+                        Ok(state.to_string())
+                    }
+                    other => Err(D::Error::custom(format!(
+                        "state {other} of variable {variable} is not a string, number, or boolean"
+                    ))),
+                })
+                .collect::<Result<Vec<String>, D::Error>>()?;
+            Ok((variable, states))
+        })
+        .collect()
 }
 
 impl BayesianNetwork {
@@ -44,6 +160,36 @@ impl BayesianNetwork {
     /// - `cpts`: { String: Number[][] }
     /// - `states`: { String: String[] }
     /// - `parents`: { String: String[] }
+    ///
+    /// States may also be numbers or booleans, which are read as their JSON
+    /// text, so `[true, false]` gives the states `"true"` and `"false"`.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    /// use std::collections::HashMap;
+    ///
+    /// static NETWORK: &str = r#"{
+    ///     "network": "scalar_states",
+    ///     "variables": ["A", "B"],
+    ///     "cpts": {
+    ///         "A": [[0.3], [0.7]],
+    ///         "B": [[0.1, 0.6], [0.2, 0.3], [0.7, 0.1]]
+    ///     },
+    ///     "states": {
+    ///         "A": [true, false],
+    ///         "B": [0, 1, 2.5]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": ["A"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetwork::from_json(NETWORK);
+    /// assert_eq!(bn.all_possible_assignments("B"), &vec!["0", "1", "2.5"]);
+    /// let a_true = HashMap::from([(String::from("A"), String::from("true"))]);
+    /// assert_eq!(bn.conditional_probability("B", "1", &a_true), 0.2);
+    /// assert_eq!(bn.conditional_probability("A", "false", &HashMap::new()), 0.7);
+    /// ```
     pub fn from_json(str: &str) -> BayesianNetwork {
         match serde_json::from_str(str) {
             Ok(bn) => bn,
@@ -51,7 +197,287 @@ impl BayesianNetwork {
         }
     }
 
-    fn state_index(&self, variable: &str, assignment: &str) -> usize {
+    /// Like `from_json`, but parses the JSON as it is read from `reader`
+    /// instead of from a string held in memory, and returns an error instead
+    /// of panicking on bad JSON, a network that fails `validate`, or a cyclic
+    /// graph. CPTs are read as they are; use `from_reader_with_warnings` to
+    /// `repair` them. Wrap unbuffered readers such as files in a `BufReader`,
+    /// or use `from_path`.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BnError};
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetwork::from_reader(NETWORK.as_bytes()).unwrap();
+    /// assert_eq!(bn.parents("C"), &vec!["A", "B"]);
+    ///
+    /// let truncated = &NETWORK.as_bytes()[..100];
+    /// assert!(matches!(BayesianNetwork::from_reader(truncated), Err(BnError::Json(_))));
+    /// ```
+    pub fn from_reader<R: std::io::Read>(reader: R) -> Result<BayesianNetwork, BnError> {
+        Self::checked(serde_json::from_reader(reader)?)
+    }
+
+    /// Like `from_reader`, but also runs `repair`, pushing what it changed
+    /// onto `warnings`, as `from_json_with_warnings` does
+    pub fn from_reader_with_warnings<R: std::io::Read>(
+        reader: R,
+        warnings: &mut Warnings,
+    ) -> Result<BayesianNetwork, BnError> {
+        let mut bn = Self::checked(serde_json::from_reader(reader)?)?;
+        bn.repair(warnings);
+        Ok(bn)
+    }
+
+    /// Returns a freshly parsed network if it passes `validate` and is
+    /// acyclic
+    fn checked(bn: BayesianNetwork) -> Result<BayesianNetwork, BnError> {
+        bn.validate().map_err(BnError::Invalid)?;
+        bn.try_topological_sort()?;
+        Ok(bn)
+    }
+
+    /// Reads a network from the JSON file at `path` with `from_reader`,
+    /// through a buffered reader
+    /// ```
+    /// use rsgm::{BayesianNetwork, BnError};
+    ///
+    /// let sachs = BayesianNetwork::from_path("bayesian_networks/sachs.json").unwrap();
+    /// assert_eq!(sachs.variables().len(), 11);
+    ///
+    /// assert!(matches!(
+    ///     BayesianNetwork::from_path("bayesian_networks/missing.json"),
+    ///     Err(BnError::Io(_))
+    /// ));
+    /// ```
+    pub fn from_path(path: impl AsRef<Path>) -> Result<BayesianNetwork, BnError> {
+        let file = File::open(path)?;
+        Self::from_reader(BufReader::new(file))
+    }
+
+    /// Writes the network as JSON in the layout read by `from_json`.
+    ///
+    /// The order of the keys in `cpts`, `states`, and `parents` is
+    /// unspecified; use `serialize_v1` for byte-for-byte reproducible output.
+    /// ```
+    /// use rsgm::{serialize_v1, BayesianNetwork};
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetwork::from_json(NETWORK);
+    /// let compact = BayesianNetwork::from_json(&bn.to_json());
+    /// let pretty = BayesianNetwork::from_json(&bn.to_json_pretty());
+    /// assert_eq!(serialize_v1(&compact), serialize_v1(&bn));
+    /// assert_eq!(serialize_v1(&pretty), serialize_v1(&bn));
+    /// ```
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("a Bayesian network is always serializable")
+    }
+
+    /// Like `to_json`, but indented for reading
+    pub fn to_json_pretty(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a Bayesian network is always serializable")
+    }
+
+    /// Like `from_json`, but returns an error instead of panicking on bad
+    /// JSON, a network that fails `validate`, or a cyclic graph, and runs
+    /// `repair` on the result, recording every correction in `warnings`.
+    /// ```
+    /// use rsgm::{BayesianNetwork, Warnings};
+    ///
+    /// // models the collider A, B -> C, with a column of C that sums to 0.98
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.28, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let mut warnings = Warnings::new();
+    /// let bn = BayesianNetwork::from_json_with_warnings(NETWORK, &mut warnings).unwrap();
+    ///
+    /// assert_eq!(warnings.len(), 1);
+    /// assert_eq!(
+    ///     warnings.iter().next().unwrap().to_string(),
+    ///     "column for C|A=T,B=F summed to 0.98, rescaled"
+    /// );
+    /// ```
+    pub fn from_json_with_warnings(
+        str: &str,
+        warnings: &mut Warnings,
+    ) -> Result<BayesianNetwork, BnError> {
+        let mut bn = Self::checked(serde_json::from_str(str)?)?;
+        bn.repair(warnings);
+        Ok(bn)
+    }
+
+    /// Checks that the network is well-formed, returning every problem
+    /// found:
+    /// - every variable has an entry in `cpts`, `states`, and `parents`
+    /// - every parent is one of `variables`
+    /// - every CPT has one row per state of its variable, and each row one
+    ///   column per assignment to the variable's parents
+    ///
+    /// Other methods assume these hold and panic, often much later, when
+    /// they do not; `from_json_with_warnings` runs this check on load.
+    /// ```
+    /// use rsgm::{BayesianNetwork, ValidationError};
+    ///
+    /// // models the collider A, B -> C, with a row of C too short
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetwork::from_json(NETWORK);
+    /// assert_eq!(
+    ///     bn.validate(),
+    ///     Err(vec![ValidationError::RowLength {
+    ///         variable: String::from("C"),
+    ///         row: 1,
+    ///         expected: 4,
+    ///         actual: 3,
+    ///     }])
+    /// );
+    /// ```
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors: Vec<ValidationError> = Vec::new();
+        for variable in self.variables.iter() {
+            let missing = |field| ValidationError::MissingEntry {
+                variable: variable.clone(),
+                field,
+            };
+            let (Some(cpt), Some(states), Some(parents)) = (
+                self.cpts.get(variable),
+                self.states.get(variable),
+                self.parents.get(variable),
+            ) else {
+                for (field, present) in [
+                    ("cpts", self.cpts.contains_key(variable)),
+                    ("states", self.states.contains_key(variable)),
+                    ("parents", self.parents.contains_key(variable)),
+                ] {
+                    if !present {
+                        errors.push(missing(field));
+                    }
+                }
+                continue;
+            };
+
+            if states.is_empty() {
+                errors.push(ValidationError::NoStates {
+                    variable: variable.clone(),
+                });
+            }
+            let mut num_columns = 1;
+            for parent in parents.iter() {
+                if !self.variables.contains(parent) {
+                    errors.push(ValidationError::UnknownParent {
+                        variable: variable.clone(),
+                        parent: parent.clone(),
+                    });
+                } else if let Some(parent_states) = self.states.get(parent) {
+                    num_columns *= parent_states.len();
+                }
+            }
+            if cpt.len() != states.len() {
+                errors.push(ValidationError::RowCount {
+                    variable: variable.clone(),
+                    expected: states.len(),
+                    actual: cpt.len(),
+                });
+            }
+            // rows can only be measured once every parent's states are known
+            let parents_known = parents
+                .iter()
+                .all(|p| self.variables.contains(p) && self.states.contains_key(p));
+            if parents_known {
+                for (row, entries) in cpt.iter().enumerate() {
+                    if entries.len() != num_columns {
+                        errors.push(ValidationError::RowLength {
+                            variable: variable.clone(),
+                            row,
+                            expected: num_columns,
+                            actual: entries.len(),
+                        });
+                    }
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub(crate) fn state_index(&self, variable: &str, assignment: &str) -> usize {
         let cur_s = self
             .states
             .get(variable)
@@ -64,7 +490,7 @@ impl BayesianNetwork {
             })
     }
 
-    fn num_states(&self, variable: &str) -> usize {
+    pub(crate) fn num_states(&self, variable: &str) -> usize {
         let cur_s = self
             .states
             .get(variable)
@@ -106,34 +532,14 @@ impl BayesianNetwork {
     /// assert!(bayesian_network.parents("C").iter().any(|s| s == "B"));
     /// ```
     pub fn parents(&self, variable: &str) -> &Vec<String> {
-        &self.parents[variable]
-    }
-
-    fn parent_h(&self, mut cur_parents: Vec<String>) -> Vec<HashMap<String, String>> {
-        if cur_parents.is_empty() {
-            return vec![HashMap::new()];
-        }
-
-        let mut r: Vec<HashMap<String, String>> = Vec::new();
-        let p = cur_parents.pop().unwrap();
-        let cur_values = self.all_possible_assignments(&p);
-        let sub = self.parent_h(cur_parents);
-
-        // add each assignment onto
-        for v in cur_values {
-            for sub_i in sub.iter() {
-                let mut new_s = sub_i.clone();
-                new_s.insert(p.clone(), v.clone());
-                r.push(new_s);
-            }
-        }
-        r
+        self.try_parents(variable)
+            .unwrap_or_else(|| panic!("could not find variable {variable}"))
     }
 
-    /// get a vector of all possible assignments to the parents of this variable
+    /// get a list of all parents for `variable`, or `None` if it is not in
+    /// the network
     /// ```
     /// use rsgm::BayesianNetwork;
-    /// use std::collections::HashMap;
     ///
     /// // models the collider A, B -> C
     /// static NETWORK: &str = r#"{
@@ -158,19 +564,15 @@ impl BayesianNetwork {
     ///
     /// let bayesian_network = BayesianNetwork::from_json(NETWORK);
     ///
-    /// assert_eq!(bayesian_network.parent_assignments("A"), vec![HashMap::new()]);
-    /// assert_eq!(bayesian_network.parent_assignments("B"), vec![HashMap::new()]);
-    /// assert_eq!(bayesian_network.parent_assignments("C").len(), 4);
-    /// assert!(bayesian_network.parent_assignments("C").iter().any(|s| s["A"] == "F" && s["B"] == "F"));
-    /// assert!(bayesian_network.parent_assignments("C").iter().any(|s| s["A"] == "F" && s["B"] == "T"));
-    /// assert!(bayesian_network.parent_assignments("C").iter().any(|s| s["A"] == "T" && s["B"] == "F"));
-    /// assert!(bayesian_network.parent_assignments("C").iter().any(|s| s["A"] == "T" && s["B"] == "T"));
+    /// assert_eq!(bayesian_network.try_parents("C").map(|p| p.len()), Some(2));
+    /// assert_eq!(bayesian_network.try_parents("D"), None);
     /// ```
-    pub fn parent_assignments(&self, variable: &str) -> Vec<HashMap<String, String>> {
-        self.parent_h(self.parents(variable).clone())
+    pub fn try_parents(&self, variable: &str) -> Option<&Vec<String>> {
+        self.parents.get(variable)
     }
 
-    /// get all variables defined in this Bayesian network
+    /// get every variable that lists `variable` as a parent, in the order of
+    /// `variables`
     /// ```
     /// use rsgm::BayesianNetwork;
     ///
@@ -197,18 +599,55 @@ impl BayesianNetwork {
     ///
     /// let bayesian_network = BayesianNetwork::from_json(NETWORK);
     ///
-    /// assert_eq!(bayesian_network.variables().len(), 3);
-    /// assert!(bayesian_network.variables().iter().any(|s| s == "A"));
-    /// assert!(bayesian_network.variables().iter().any(|s| s == "B"));
-    /// assert!(bayesian_network.variables().iter().any(|s| s == "C"));
+    /// assert_eq!(bayesian_network.children("A"), vec![String::from("C")]);
+    /// assert!(bayesian_network.children("C").is_empty());
     /// ```
-    pub fn variables(&self) -> &Vec<String> {
-        &self.variables
+    pub fn children(&self, variable: &str) -> Vec<String> {
+        if !self.parents.contains_key(variable) {
+            panic!("could not find variable {variable}");
+        }
+        self.variables
+            .iter()
+            .filter(|v| self.parents[*v].iter().any(|p| p == variable))
+            .cloned()
+            .collect()
     }
 
-    /// get all possible assignments to `variable`
+    /// get every variable with a directed path to `variable`, not including
+    /// `variable` itself
+    pub fn ancestors(&self, variable: &str) -> HashSet<String> {
+        self.reachable(variable, |v| self.parents[v].clone())
+    }
+
+    /// get every variable with a directed path from `variable`, not including
+    /// `variable` itself
+    pub fn descendants(&self, variable: &str) -> HashSet<String> {
+        let children = self.child_map();
+        self.reachable(variable, |v| {
+            children[v].iter().map(|c| c.to_string()).collect()
+        })
+    }
+
+    /// get the Markov blanket of `variable`: its parents, its children, and
+    /// its children's other parents. Given its blanket, `variable` is
+    /// independent of every other variable.
+    pub fn markov_blanket(&self, variable: &str) -> HashSet<String> {
+        let mut blanket: HashSet<String> = self.parents[variable].iter().cloned().collect();
+        for child in self.children(variable) {
+            blanket.extend(self.parents[&child].iter().cloned());
+            blanket.insert(child);
+        }
+        blanket.remove(variable);
+        blanket
+    }
+
+    /// Computes the moral graph as a map from each variable to its
+    /// neighbours: every pair of parents of a variable is connected
+    /// ("married"), and then edge directions are dropped. The neighbours of
+    /// a variable in the moral graph are exactly its `markov_blanket`.
     /// ```
     /// use rsgm::BayesianNetwork;
+    /// use std::collections::HashSet;
     ///
     /// // models the collider A, B -> C
     /// static NETWORK: &str = r#"{
@@ -232,18 +671,51 @@ impl BayesianNetwork {
     /// }"#;
     ///
     /// let bayesian_network = BayesianNetwork::from_json(NETWORK);
+    /// let moral = bayesian_network.moral_graph();
     ///
-    /// assert_eq!(bayesian_network.all_possible_assignments("A").len(), 2);
-    /// assert!(bayesian_network.all_possible_assignments("A").iter().any(|s| s == "T"));
-    /// assert!(bayesian_network.all_possible_assignments("A").iter().any(|s| s == "F"));
-    pub fn all_possible_assignments(&self, variable: &str) -> &Vec<String> {
-        &self.states[variable]
+    /// // A and B are not adjacent in the DAG, but share the child C
+    /// assert!(bayesian_network.parents("B").is_empty());
+    /// assert!(moral["A"].contains("B"));
+    /// assert!(moral["B"].contains("A"));
+    /// assert_eq!(moral["C"], HashSet::from([String::from("A"), String::from("B")]));
+    /// ```
+    pub fn moral_graph(&self) -> HashMap<String, HashSet<String>> {
+        let mut graph: HashMap<String, HashSet<String>> = self
+            .variables
+            .iter()
+            .map(|v| (v.clone(), HashSet::new()))
+            .collect();
+        let mut connect = |x: &String, y: &String| {
+            graph.get_mut(x).unwrap().insert(y.clone());
+            graph.get_mut(y).unwrap().insert(x.clone());
+        };
+        for variable in self.variables.iter() {
+            let parents = &self.parents[variable];
+            for (i, parent) in parents.iter().enumerate() {
+                connect(variable, parent);
+                for other in parents[i + 1..].iter() {
+                    connect(parent, other);
+                }
+            }
+        }
+        graph
     }
 
-    /// Get the conditional probability Pr(variable = variable_value | parent_assignment)
+    /// Checks whether the variables `x` are d-separated from the variables
+    /// `y` given the observed variables `given`, i.e. whether every path
+    /// between them is blocked. d-separation implies that `x` and `y` are
+    /// independent given `given` for every choice of CPTs.
+    ///
+    /// Uses the reachability ("Bayes-Ball") algorithm: a path may pass
+    /// through an unobserved chain or fork, and through a collider only when
+    /// the collider or one of its descendants is observed. An empty `x` or
+    /// `y` is trivially d-separated.
+    ///
+    /// # Panics
+    /// Panics if a variable is unknown or appears in more than one of `x`,
+    /// `y`, and `given`.
     /// ```
     /// use rsgm::BayesianNetwork;
-    /// use std::collections::HashMap;
     ///
     /// // models the collider A, B -> C
     /// static NETWORK: &str = r#"{
@@ -266,40 +738,102 @@ impl BayesianNetwork {
     ///     }
     /// }"#;
     ///
-    /// let bayesian_network = BayesianNetwork::from_json(NETWORK);
+    /// let bn = BayesianNetwork::from_json(NETWORK);
+    /// let (a, b, c) = (vec![String::from("A")], vec![String::from("B")], vec![String::from("C")]);
     ///
-    /// assert_eq!(bayesian_network.conditional_probability("A", "T", &HashMap::from([])), 0.5);
-    /// assert_eq!(bayesian_network.conditional_probability("C", "T", &HashMap::from([
-    ///     (String::from("A"), String::from("T")),
-    ///     (String::from("B"), String::from("T"))
-    /// ])), 0.6);
+    /// assert!(bn.d_separated(&a, &b, &[]));
+    /// // observing the collider makes its parents dependent
+    /// assert!(!bn.d_separated(&a, &b, &c));
+    /// assert!(!bn.d_separated(&a, &c, &[]));
     /// ```
-    pub fn conditional_probability(
-        &self,
-        variable: &str,
-        variable_value: &str,
-        parent_assignment: &HashMap<String, String>,
-    ) -> f64 {
-        let var_idx = self.state_index(variable, variable_value);
-        let row = &self.cpts[variable][var_idx];
-        // compute the index into the row
-        let parents = self.parents.get(variable).unwrap();
-        let mut cur_stride = 1;
-        let mut idx = 0;
-        for parent in parents.iter().rev() {
-            let parent_assgn = &parent_assignment[parent];
-            let parent_asggn_idx = self.state_index(parent, parent_assgn);
-            idx += cur_stride * parent_asggn_idx;
-            let parent_sz = self.num_states(parent);
-            cur_stride *= parent_sz;
+    pub fn d_separated(&self, x: &[String], y: &[String], given: &[String]) -> bool {
+        let mut seen: HashSet<&String> = HashSet::new();
+        for v in x.iter().chain(y).chain(given) {
+            if !self.parents.contains_key(v) {
+                panic!("could not find variable {v}");
+            }
+            if !seen.insert(v) {
+                panic!("variable {v} appears in more than one of x, y, and given");
+            }
+        }
+        let observed: HashSet<&String> = given.iter().collect();
+        // colliders pass a path on when they or a descendant are observed
+        let mut observed_or_ancestor: HashSet<String> = HashSet::new();
+        for v in given.iter() {
+            observed_or_ancestor.extend(self.ancestors(v));
+            observed_or_ancestor.insert(v.clone());
+        }
+
+        let children = self.child_map();
+        // (variable, whether it was reached from a child)
+        let mut frontier: Vec<(String, bool)> = x.iter().map(|v| (v.clone(), true)).collect();
+        let mut visited: HashSet<(String, bool)> = HashSet::new();
+        while let Some((v, from_child)) = frontier.pop() {
+            if !visited.insert((v.clone(), from_child)) {
+                continue;
+            }
+            let is_observed = observed.contains(&v);
+            if !is_observed && y.contains(&v) {
+                return false;
+            }
+            if from_child && !is_observed {
+                frontier.extend(self.parents[&v].iter().map(|p| (p.clone(), true)));
+                frontier.extend(children[v.as_str()].iter().map(|c| (c.to_string(), false)));
+            } else if !from_child {
+                if !is_observed {
+                    frontier.extend(children[v.as_str()].iter().map(|c| (c.to_string(), false)));
+                }
+                if observed_or_ancestor.contains(&v) {
+                    frontier.extend(self.parents[&v].iter().map(|p| (p.clone(), true)));
+                }
+            }
         }
-        row[idx]
+        true
     }
 
-    /// Produces a list of variables in topological order;
-    /// breaks ties with the order of `variables`
+    /// maps every variable to its children, in one pass over the parent lists
+    fn child_map(&self) -> HashMap<&str, Vec<&str>> {
+        let mut children: HashMap<&str, Vec<&str>> = self
+            .variables
+            .iter()
+            .map(|v| (v.as_str(), Vec::new()))
+            .collect();
+        for child in self.variables.iter() {
+            for p in self.parents[child].iter() {
+                children.get_mut(p.as_str()).unwrap().push(child);
+            }
+        }
+        children
+    }
+
+    /// the variables reached from `variable` by repeatedly following `next`
+    fn reachable(&self, variable: &str, next: impl Fn(&str) -> Vec<String>) -> HashSet<String> {
+        if !self.parents.contains_key(variable) {
+            panic!("could not find variable {variable}");
+        }
+        let mut found: HashSet<String> = HashSet::new();
+        let mut frontier = next(variable);
+        while let Some(v) = frontier.pop() {
+            if !found.contains(&v) {
+                frontier.extend(next(&v));
+                found.insert(v);
+            }
+        }
+        found
+    }
+
+    /// get the parents of `variable` in the order that lays out the columns
+    /// of its CPT. This is the order the parents are listed in the network.
+    ///
+    /// Column `j` of the CPT corresponds to the parent assignment whose state
+    /// indices, read as a mixed-radix number with the *last* parent varying
+    /// fastest, equal `j`. For parents `[P1, ..., Pn]` with state indices
+    /// `[i1, ..., in]` and cardinalities `[k1, ..., kn]`:
+    /// `j = ((i1 * k2 + i2) * k3 + i3) ... * kn + in`.
+    /// Every CPT lookup and edit in this crate computes its column this way.
     /// ```
     /// use rsgm::BayesianNetwork;
+    /// use std::collections::HashMap;
     ///
     /// // models the collider A, B -> C
     /// static NETWORK: &str = r#"{
@@ -324,40 +858,3465 @@ impl BayesianNetwork {
     ///
     /// let bayesian_network = BayesianNetwork::from_json(NETWORK);
     ///
-    /// assert_eq!(bayesian_network.topological_sort().len(), 3);
-    /// assert_eq!(bayesian_network.topological_sort()[0], "A");
-    /// assert_eq!(bayesian_network.topological_sort()[1], "B");
-    /// assert_eq!(bayesian_network.topological_sort()[2], "C");
+    /// assert_eq!(bayesian_network.parents_in_cpt_order("C"), &[String::from("A"), String::from("B")]);
+    /// // column 1 is A = F, B = T, since B varies fastest
+    /// assert_eq!(bayesian_network.conditional_probability("C", "T", &HashMap::from([
+    ///     (String::from("A"), String::from("F")),
+    ///     (String::from("B"), String::from("T"))
+    /// ])), 0.2);
     /// ```
+    pub fn parents_in_cpt_order(&self, variable: &str) -> &[String] {
+        &self.parents[variable]
+    }
+
+    /// computes the CPT column of `variable` selected by `parent_assignment`;
+    /// see `parents_in_cpt_order`
+    fn cpt_column(&self, variable: &str, parent_assignment: &HashMap<String, String>) -> usize {
+        self.parents_in_cpt_order(variable)
+            .iter()
+            .fold(0, |idx, parent| {
+                idx * self.num_states(parent) + self.state_index(parent, &parent_assignment[parent])
+            })
+    }
+
+    /// names column `column` of `variable`'s CPT as `C|A=T,B=F`
+    fn column_label(&self, variable: &str, column: usize) -> String {
+        let assignment: Vec<String> = self
+            .column_assignment(variable, column)
+            .into_iter()
+            .map(|(parent, value)| format!("{parent}={value}"))
+            .collect();
+        if assignment.is_empty() {
+            return variable.to_string();
+        }
+        format!("{variable}|{}", assignment.join(","))
+    }
+
+    /// the parent assignment selected by column `column` of `variable`'s
+    /// CPT, in the order of `parents_in_cpt_order`; the inverse of
+    /// `cpt_column`
+    pub(crate) fn column_assignment(
+        &self,
+        variable: &str,
+        mut column: usize,
+    ) -> Vec<(String, String)> {
+        let mut assignment: Vec<(String, String)> = Vec::new();
+        for parent in self.parents_in_cpt_order(variable).iter().rev() {
+            let num_states = self.num_states(parent);
+            assignment.push((
+                parent.clone(),
+                self.states[parent][column % num_states].clone(),
+            ));
+            column /= num_states;
+        }
+        assignment.reverse();
+        assignment
+    }
+
+    /// lazily enumerates every assignment to `variables`, counting through
+    /// their state indices with the first variable varying fastest
+    fn assignments_h(
+        &self,
+        variables: Vec<String>,
+    ) -> impl Iterator<Item = HashMap<String, String>> + '_ {
+        let sizes: Vec<usize> = variables.iter().map(|v| self.num_states(v)).collect();
+        let first = if sizes.contains(&0) {
+            None
+        } else {
+            Some(vec![0; sizes.len()])
+        };
+        std::iter::successors(first, move |indices| {
+            let mut next = indices.clone();
+            for (i, size) in next.iter_mut().zip(sizes.iter()) {
+                *i += 1;
+                if *i < *size {
+                    return Some(next);
+                }
+                *i = 0;
+            }
+            None
+        })
+        .map(move |indices| {
+            variables
+                .iter()
+                .zip(indices)
+                .map(|(v, i)| (v.clone(), self.states[v][i].clone()))
+                .collect()
+        })
+    }
+
+    /// get a vector of all possible assignments to the parents of this variable
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bayesian_network = BayesianNetwork::from_json(NETWORK);
+    ///
+    /// assert_eq!(bayesian_network.parent_assignments("A"), vec![HashMap::new()]);
+    /// assert_eq!(bayesian_network.parent_assignments("B"), vec![HashMap::new()]);
+    /// assert_eq!(bayesian_network.parent_assignments("C").len(), 4);
+    /// assert!(bayesian_network.parent_assignments("C").iter().any(|s| s["A"] == "F" && s["B"] == "F"));
+    /// assert!(bayesian_network.parent_assignments("C").iter().any(|s| s["A"] == "F" && s["B"] == "T"));
+    /// assert!(bayesian_network.parent_assignments("C").iter().any(|s| s["A"] == "T" && s["B"] == "F"));
+    /// assert!(bayesian_network.parent_assignments("C").iter().any(|s| s["A"] == "T" && s["B"] == "T"));
+    /// ```
+    pub fn parent_assignments(&self, variable: &str) -> Vec<HashMap<String, String>> {
+        self.assignments_h(self.parents(variable).clone()).collect()
+    }
+
+    /// lazily iterate over every assignment to all of the variables, i.e. the
+    /// Cartesian product of their states. There are exponentially many, so
+    /// stop early or fold as you go rather than collecting them.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bayesian_network = BayesianNetwork::from_json(NETWORK);
+    ///
+    /// assert_eq!(bayesian_network.joint_assignments().count(), 8);
+    /// let total: f64 = bayesian_network
+    ///     .joint_assignments()
+    ///     .map(|a| bayesian_network.joint_probability(&a))
+    ///     .sum();
+    /// assert!((total - 1.0).abs() < 1e-12);
+    /// ```
+    pub fn joint_assignments(&self) -> impl Iterator<Item = HashMap<String, String>> + '_ {
+        self.assignments_h(self.variables.clone())
+    }
+
+    /// get the name of this Bayesian network, e.g. `sachs`
+    pub fn name(&self) -> &str {
+        &self.network
+    }
+
+    /// get all variables defined in this Bayesian network
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bayesian_network = BayesianNetwork::from_json(NETWORK);
+    ///
+    /// assert_eq!(bayesian_network.name(), "toy_network");
+    /// assert_eq!(bayesian_network.variables().len(), 3);
+    /// assert!(bayesian_network.variables().iter().any(|s| s == "A"));
+    /// assert!(bayesian_network.variables().iter().any(|s| s == "B"));
+    /// assert!(bayesian_network.variables().iter().any(|s| s == "C"));
+    /// ```
+    pub fn variables(&self) -> &Vec<String> {
+        &self.variables
+    }
+
+    /// get the number of variables in this Bayesian network
+    pub fn num_variables(&self) -> usize {
+        self.variables.len()
+    }
+
+    /// get the number of states of `variable`
+    pub fn cardinality(&self, variable: &str) -> usize {
+        self.num_states(variable)
+    }
+
+    /// get the number of states of every variable
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    ///
+    /// // models A -> B, where B has three states
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.2, 0.1], [0.3, 0.1], [0.5, 0.8]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["LOW", "AVG", "HIGH"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": ["A"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bayesian_network = BayesianNetwork::from_json(NETWORK);
+    ///
+    /// assert_eq!(bayesian_network.num_variables(), 2);
+    /// assert_eq!(bayesian_network.cardinality("B"), 3);
+    /// let cardinalities = bayesian_network.cardinalities();
+    /// assert_eq!((cardinalities["A"], cardinalities["B"]), (2, 3));
+    /// ```
+    pub fn cardinalities(&self) -> HashMap<String, usize> {
+        self.variables
+            .iter()
+            .map(|v| (v.clone(), self.num_states(v)))
+            .collect()
+    }
+
+    /// the metadata of `variable`, if any has been set
+    /// ```
+    /// use rsgm::{BayesianNetwork, VariableMeta};
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let mut bayesian_network = BayesianNetwork::from_json(NETWORK);
+    /// assert_eq!(bayesian_network.metadata("C"), None);
+    ///
+    /// let meta = VariableMeta {
+    ///     description: String::from("whether the alarm sounds"),
+    ///     position: Some((120.0, 40.0)),
+    ///     ..Default::default()
+    /// };
+    /// bayesian_network.set_metadata("C", meta.clone()).unwrap();
+    /// assert!(bayesian_network.set_metadata("D", meta.clone()).is_err());
+    ///
+    /// // metadata survives a round trip through JSON
+    /// let json = serde_json::to_string(&bayesian_network).unwrap();
+    /// assert_eq!(BayesianNetwork::from_json(&json).metadata("C"), Some(&meta));
+    /// ```
+    pub fn metadata(&self, variable: &str) -> Option<&VariableMeta> {
+        self.metadata.get(variable)
+    }
+
+    /// Replaces the metadata of `variable`; returns an error if `variable` is
+    /// not in the network
+    pub fn set_metadata(&mut self, variable: &str, meta: VariableMeta) -> Result<(), BnError> {
+        if !self.states.contains_key(variable) {
+            return Err(BnError::UnknownVariable(variable.to_string()));
+        }
+        self.metadata.insert(variable.to_string(), meta);
+        Ok(())
+    }
+
+    /// Adds the edge `parent -> child`, making `parent` the last of `child`'s
+    /// parents in CPT order. `child`'s CPT is reset to the uniform
+    /// distribution in every column, since its old entries do not say how
+    /// `child` depends on `parent`.
+    ///
+    /// Returns an error, leaving the network unchanged, if either variable is
+    /// unknown, the edge already exists, or it would create a cycle.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BnError};
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let mut bayesian_network = BayesianNetwork::from_json(NETWORK);
+    /// bayesian_network.add_edge("A", "B").unwrap();
+    ///
+    /// assert_eq!(bayesian_network.parents("B"), &vec!["A"]);
+    /// assert_eq!(bayesian_network.cpt("B"), &vec![vec![0.5, 0.5], vec![0.5, 0.5]]);
+    /// assert_eq!(bayesian_network.topological_sort(), vec!["A", "B", "C"]);
+    /// assert!(matches!(
+    ///     bayesian_network.add_edge("C", "A"),
+    ///     Err(BnError::Cycle(_))
+    /// ));
+    /// assert!(bayesian_network.parents("A").is_empty());
+    /// ```
+    pub fn add_edge(&mut self, parent: &str, child: &str) -> Result<(), BnError> {
+        for var in [parent, child] {
+            if !self.states.contains_key(var) {
+                return Err(BnError::UnknownVariable(var.to_string()));
+            }
+        }
+        if self.parents[child].iter().any(|p| p == parent) {
+            return Err(BnError::DuplicateEdge(
+                parent.to_string(),
+                child.to_string(),
+            ));
+        }
+        self.parents
+            .get_mut(child)
+            .unwrap()
+            .push(parent.to_string());
+        let previous = std::mem::take(&mut self.topological_order);
+        if let Err(err) = self.try_topological_sort() {
+            self.parents.get_mut(child).unwrap().pop();
+            self.topological_order = previous;
+            return Err(err.into());
+        }
+        self.reset_cpt(child);
+        Ok(())
+    }
+
+    /// Removes the edge `parent -> child`. Like `add_edge`, this resets
+    /// `child`'s CPT to the uniform distribution in every column.
+    ///
+    /// Returns an error, leaving the network unchanged, if either variable is
+    /// unknown or the edge does not exist.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let mut bayesian_network = BayesianNetwork::from_json(NETWORK);
+    /// bayesian_network.remove_edge("A", "C").unwrap();
+    ///
+    /// assert_eq!(bayesian_network.parents("C"), &vec!["B"]);
+    /// assert_eq!(bayesian_network.cpt("C"), &vec![vec![0.5, 0.5], vec![0.5, 0.5]]);
+    /// assert!(bayesian_network.remove_edge("A", "C").is_err());
+    /// ```
+    pub fn remove_edge(&mut self, parent: &str, child: &str) -> Result<(), BnError> {
+        for var in [parent, child] {
+            if !self.states.contains_key(var) {
+                return Err(BnError::UnknownVariable(var.to_string()));
+            }
+        }
+        let parents = self.parents.get_mut(child).unwrap();
+        let position = parents
+            .iter()
+            .position(|p| p == parent)
+            .ok_or_else(|| BnError::UnknownEdge(parent.to_string(), child.to_string()))?;
+        parents.remove(position);
+        self.reset_cpt(child);
+        self.topological_order = OnceLock::new();
+        Ok(())
+    }
+
+    /// replaces `variable`'s CPT with the uniform distribution in every
+    /// column, sized for its current parents
+    fn reset_cpt(&mut self, variable: &str) {
+        let num_columns: usize = self.parents[variable]
+            .iter()
+            .map(|p| self.num_states(p))
+            .product();
+        let num_states = self.num_states(variable);
+        self.cpts.insert(
+            variable.to_string(),
+            vec![vec![1.0 / num_states as f64; num_columns]; num_states],
+        );
+    }
+
+    /// Applies the do-operator: returns the mutilated network in which each
+    /// variable of `interventions` is forced to its value, by removing its
+    /// parents and replacing its CPT with a point mass on the value. Queries
+    /// on the result compute interventional distributions
+    /// Pr(Y | do(X = x)), which differ from the observational Pr(Y | X = x)
+    /// whenever X and Y are connected other than by a directed path from X.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bayesian_network = BayesianNetwork::from_json(NETWORK);
+    /// let c_t = HashMap::from([(String::from("C"), String::from("T"))]);
+    /// let a_t = (String::from("A"), String::from("T"));
+    ///
+    /// // observing C = T is evidence about its cause A
+    /// let observed = BayesianNetworkCNF::from_bayesian_network(&bayesian_network);
+    /// assert!((observed.posterior(&a_t, &c_t) - 0.78125).abs() < 1e-9);
+    ///
+    /// // setting C = T says nothing about A
+    /// let mutilated = bayesian_network.intervene(&c_t);
+    /// assert!(mutilated.parents("C").is_empty());
+    /// let intervened = BayesianNetworkCNF::from_bayesian_network(&mutilated);
+    /// assert!((intervened.posterior(&a_t, &HashMap::new()) - 0.5).abs() < 1e-9);
+    ///
+    /// // A has no parents, so setting it is the same as observing it
+    /// let a = HashMap::from([a_t.clone()]);
+    /// let intervened = BayesianNetworkCNF::from_bayesian_network(&bayesian_network.intervene(&a));
+    /// let c = (String::from("C"), String::from("T"));
+    /// assert!((intervened.marginal("C", "T") - observed.posterior(&c, &a)).abs() < 1e-9);
+    /// ```
+    pub fn intervene(&self, interventions: &HashMap<String, String>) -> BayesianNetwork {
+        let mut mutilated = self.clone();
+        for (var, value) in interventions.iter() {
+            let states = self
+                .states
+                .get(var)
+                .unwrap_or_else(|| panic!("could not find variable {var}"));
+            if !states.contains(value) {
+                panic!("could not find assignment {value} for variable {var}");
+            }
+            mutilated.parents.insert(var.clone(), Vec::new());
+            mutilated.cpts.insert(
+                var.clone(),
+                states
+                    .iter()
+                    .map(|s| vec![if s == value { 1.0 } else { 0.0 }])
+                    .collect(),
+            );
+        }
+        mutilated.topological_order = OnceLock::new();
+        mutilated
+    }
+
+    /// get all possible assignments to `variable`
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bayesian_network = BayesianNetwork::from_json(NETWORK);
+    ///
+    /// assert_eq!(bayesian_network.all_possible_assignments("A").len(), 2);
+    /// assert!(bayesian_network.all_possible_assignments("A").iter().any(|s| s == "T"));
+    /// assert!(bayesian_network.all_possible_assignments("A").iter().any(|s| s == "F"));
+    pub fn all_possible_assignments(&self, variable: &str) -> &Vec<String> {
+        self.try_all_possible_assignments(variable)
+            .unwrap_or_else(|| panic!("could not find variable {variable}"))
+    }
+
+    /// get all possible assignments for `variable`, or `None` if it is not in
+    /// the network
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bayesian_network = BayesianNetwork::from_json(NETWORK);
+    ///
+    /// assert_eq!(
+    ///     bayesian_network.try_all_possible_assignments("A"),
+    ///     Some(&vec![String::from("F"), String::from("T")])
+    /// );
+    /// assert_eq!(bayesian_network.try_all_possible_assignments("D"), None);
+    /// ```
+    pub fn try_all_possible_assignments(&self, variable: &str) -> Option<&Vec<String>> {
+        self.states.get(variable)
+    }
+
+    /// Get the conditional probability Pr(variable = variable_value | parent_assignment)
+    ///
+    /// Entries of `parent_assignment` for variables that are not parents of
+    /// `variable` are ignored, so a full assignment may be passed; see
+    /// `try_conditional_probability` for a strict, non-panicking lookup.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bayesian_network = BayesianNetwork::from_json(NETWORK);
+    ///
+    /// assert_eq!(bayesian_network.conditional_probability("A", "T", &HashMap::from([])), 0.5);
+    /// assert_eq!(bayesian_network.conditional_probability("C", "T", &HashMap::from([
+    ///     (String::from("A"), String::from("T")),
+    ///     (String::from("B"), String::from("T"))
+    /// ])), 0.6);
+    /// ```
+    pub fn conditional_probability(
+        &self,
+        variable: &str,
+        variable_value: &str,
+        parent_assignment: &HashMap<String, String>,
+    ) -> f64 {
+        let var_idx = self.state_index(variable, variable_value);
+        self.cpts[variable][var_idx][self.cpt_column(variable, parent_assignment)]
+    }
+
+    /// Get the conditional probability Pr(variable = variable_value | parent_assignment),
+    /// checking first that `parent_assignment` assigns exactly the parents of
+    /// `variable`. Returns an error naming the offending variable or key if
+    /// `variable` is not in the network, `variable_value` or a parent's value
+    /// is not one of its states, a parent is unassigned, or a key is not a
+    /// parent.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BnError};
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bayesian_network = BayesianNetwork::from_json(NETWORK);
+    /// let parents = HashMap::from([
+    ///     (String::from("A"), String::from("T")),
+    ///     (String::from("B"), String::from("T"))
+    /// ]);
+    ///
+    /// assert_eq!(bayesian_network.try_conditional_probability("C", "T", &parents).unwrap(), 0.6);
+    /// assert!(matches!(
+    ///     bayesian_network.try_conditional_probability("C", "maybe", &parents),
+    ///     Err(BnError::UnknownState(v, s)) if v == "C" && s == "maybe"
+    /// ));
+    /// assert!(matches!(
+    ///     bayesian_network.try_conditional_probability("D", "T", &parents),
+    ///     Err(BnError::UnknownVariable(v)) if v == "D"
+    /// ));
+    /// assert!(matches!(
+    ///     bayesian_network.try_conditional_probability("A", "T", &parents),
+    ///     Err(BnError::UnexpectedParent(v, k)) if v == "A"
+    /// ));
+    /// let partial = HashMap::from([(String::from("A"), String::from("T"))]);
+    /// assert!(matches!(
+    ///     bayesian_network.try_conditional_probability("C", "T", &partial),
+    ///     Err(BnError::MissingParent(v, p)) if v == "C" && p == "B"
+    /// ));
+    /// ```
+    pub fn try_conditional_probability(
+        &self,
+        variable: &str,
+        variable_value: &str,
+        parent_assignment: &HashMap<String, String>,
+    ) -> Result<f64, BnError> {
+        let (row, column) = self.try_cpt_cell(variable, variable_value, parent_assignment)?;
+        Ok(self.cpts[variable][row][column])
+    }
+
+    /// Sets the conditional probability Pr(variable = variable_value | parent_assignment)
+    /// to `p`, finding the CPT cell as `try_conditional_probability` does.
+    /// Other entries are left alone, so the column generally no longer sums
+    /// to 1 until its other entries are set too (or see `normalize`).
+    ///
+    /// Returns an error if `p` is not in [0, 1], or for any of the reasons
+    /// `try_conditional_probability` would; the network is then unchanged.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BnError};
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let mut bayesian_network = BayesianNetwork::from_json(NETWORK);
+    /// let parents = HashMap::from([
+    ///     (String::from("A"), String::from("T")),
+    ///     (String::from("B"), String::from("F"))
+    /// ]);
+    ///
+    /// bayesian_network.set_conditional_probability("C", "F", &parents, 0.25).unwrap();
+    /// bayesian_network.set_conditional_probability("C", "T", &parents, 0.75).unwrap();
+    /// assert_eq!(bayesian_network.conditional_probability("C", "T", &parents), 0.75);
+    /// assert_eq!(bayesian_network.cpt("C")[1], vec![0.1, 0.2, 0.75, 0.6]);
+    ///
+    /// assert!(matches!(
+    ///     bayesian_network.set_conditional_probability("C", "T", &parents, 1.5),
+    ///     Err(BnError::InvalidProbability(p)) if p == 1.5
+    /// ));
+    /// assert!(bayesian_network.set_conditional_probability("C", "T", &HashMap::new(), 0.5).is_err());
+    /// ```
+    pub fn set_conditional_probability(
+        &mut self,
+        variable: &str,
+        variable_value: &str,
+        parent_assignment: &HashMap<String, String>,
+        p: f64,
+    ) -> Result<(), BnError> {
+        let (row, column) = self.try_cpt_cell(variable, variable_value, parent_assignment)?;
+        if !(0.0..=1.0).contains(&p) {
+            return Err(BnError::InvalidProbability(p));
+        }
+        self.cpts.get_mut(variable).unwrap()[row][column] = p;
+        Ok(())
+    }
+
+    /// finds the (row, column) of the CPT cell for Pr(variable = variable_value | parent_assignment),
+    /// checking that `parent_assignment` assigns exactly the parents of `variable`
+    fn try_cpt_cell(
+        &self,
+        variable: &str,
+        variable_value: &str,
+        parent_assignment: &HashMap<String, String>,
+    ) -> Result<(usize, usize), BnError> {
+        let states = self
+            .states
+            .get(variable)
+            .ok_or_else(|| BnError::UnknownVariable(variable.to_string()))?;
+        let var_idx = states
+            .iter()
+            .position(|s| s == variable_value)
+            .ok_or_else(|| {
+                BnError::UnknownState(variable.to_string(), variable_value.to_string())
+            })?;
+        let parents = self.parents_in_cpt_order(variable);
+        if let Some(key) = parent_assignment.keys().find(|k| !parents.contains(k)) {
+            return Err(BnError::UnexpectedParent(variable.to_string(), key.clone()));
+        }
+        let mut column = 0;
+        for parent in parents.iter() {
+            let value = parent_assignment
+                .get(parent)
+                .ok_or_else(|| BnError::MissingParent(variable.to_string(), parent.clone()))?;
+            let parent_states = &self.states[parent];
+            let value_idx = parent_states
+                .iter()
+                .position(|s| s == value)
+                .ok_or_else(|| BnError::UnknownState(parent.clone(), value.clone()))?;
+            column = column * parent_states.len() + value_idx;
+        }
+        Ok((var_idx, column))
+    }
+
+    /// Computes Pr(assignment) for an assignment to every variable by the
+    /// chain rule, as the product of each variable's `conditional_probability`
+    /// given its parents' values in `assignment`.
+    ///
+    /// # Panics
+    /// Panics if `assignment` leaves a variable unassigned, or names a
+    /// variable or value that is not in the network.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bayesian_network = BayesianNetwork::from_json(NETWORK);
+    /// let p = bayesian_network.joint_probability(&HashMap::from([
+    ///     (String::from("A"), String::from("T")),
+    ///     (String::from("B"), String::from("F")),
+    ///     (String::from("C"), String::from("T")),
+    /// ]));
+    /// assert!((p - 0.5 * 0.25 * 0.7).abs() < 1e-12);
+    /// ```
+    pub fn joint_probability(&self, assignment: &HashMap<String, String>) -> f64 {
+        self.chain_rule_factors(assignment).product()
+    }
+
+    /// Computes ln Pr(assignment) for an assignment to every variable, as the
+    /// sum of the logs of each variable's `conditional_probability` given its
+    /// parents' values in `assignment`. Unlike `joint_probability`, this does
+    /// not underflow to 0 on large networks. An assignment of probability 0
+    /// gives negative infinity.
+    ///
+    /// # Panics
+    /// Panics if `assignment` leaves a variable unassigned, or names a
+    /// variable or value that is not in the network.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C, where B is never T
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[1.0], [0.0]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bayesian_network = BayesianNetwork::from_json(NETWORK);
+    /// let assignment = |b: &str| HashMap::from([
+    ///     (String::from("A"), String::from("T")),
+    ///     (String::from("B"), String::from(b)),
+    ///     (String::from("C"), String::from("T")),
+    /// ]);
+    ///
+    /// let p = bayesian_network.log_joint_probability(&assignment("F"));
+    /// assert!((p - (0.5f64 * 0.7).ln()).abs() < 1e-12);
+    /// assert_eq!(bayesian_network.log_joint_probability(&assignment("T")), f64::NEG_INFINITY);
+    /// ```
+    pub fn log_joint_probability(&self, assignment: &HashMap<String, String>) -> f64 {
+        // ln 0 is negative infinity, which no finite term can cancel
+        self.chain_rule_factors(assignment).map(f64::ln).sum()
+    }
+
+    /// Computes the log-likelihood of fully observed `data`, the sum of
+    /// `log_joint_probability` over its rows, for comparing candidate
+    /// networks on held-out data.
+    ///
+    /// Returns an error naming the row if a row leaves a variable without a
+    /// value, gives a variable a value that is not one of its states, or
+    /// names a variable that is not in the network.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BnError};
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bayesian_network = BayesianNetwork::from_json(NETWORK);
+    /// let row = |a: &str, b: &str, c: &str| HashMap::from([
+    ///     (String::from("A"), String::from(a)),
+    ///     (String::from("B"), String::from(b)),
+    ///     (String::from("C"), String::from(c)),
+    /// ]);
+    ///
+    /// let data = vec![row("T", "F", "T"), row("F", "T", "F")];
+    /// let ll = bayesian_network.log_likelihood(&data).unwrap();
+    /// assert!((ll - (0.5f64 * 0.25 * 0.7).ln() - (0.5f64 * 0.75 * 0.8).ln()).abs() < 1e-12);
+    ///
+    /// let data = vec![row("T", "F", "T"), row("F", "maybe", "F")];
+    /// assert!(matches!(
+    ///     bayesian_network.log_likelihood(&data),
+    ///     Err(BnError::InvalidValue(1, v, s)) if v == "B" && s == "maybe"
+    /// ));
+    /// ```
+    pub fn log_likelihood(&self, data: &[HashMap<String, String>]) -> Result<f64, BnError> {
+        self.check_complete_rows(data)?;
+        Ok(data.iter().map(|row| self.log_joint_probability(row)).sum())
+    }
+
+    /// Estimates the CPTs of `structure` from fully observed `data` by
+    /// maximum likelihood: each column is the frequency of each value among
+    /// the rows with that column's parent assignment. `pseudocount` is added
+    /// to every count first (Laplace smoothing), so with a positive one no
+    /// entry is 0; a column with no rows and no pseudocount is uniform. The
+    /// result has the variables, states, and parents of `structure`.
+    ///
+    /// Returns the same errors as `log_likelihood` for malformed rows.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let structure = BayesianNetwork::from_json(NETWORK);
+    /// let row = |a: &str, b: &str, c: &str| HashMap::from([
+    ///     (String::from("A"), String::from(a)),
+    ///     (String::from("B"), String::from(b)),
+    ///     (String::from("C"), String::from(c)),
+    /// ]);
+    /// let data = vec![row("T", "T", "T"), row("T", "T", "F"), row("F", "T", "T")];
+    ///
+    /// let fitted = BayesianNetwork::fit_cpts_mle(&structure, &data, 0.0).unwrap();
+    /// assert!((fitted.conditional_probability("A", "T", &HashMap::new()) - 2.0 / 3.0).abs() < 1e-12);
+    /// assert_eq!(fitted.conditional_probability("B", "F", &HashMap::new()), 0.0);
+    ///
+    /// // with a pseudocount of 1, B = F has been seen 0 + 1 times in 3 + 2
+    /// let smoothed = BayesianNetwork::fit_cpts_mle(&structure, &data, 1.0).unwrap();
+    /// assert!((smoothed.conditional_probability("B", "F", &HashMap::new()) - 0.2).abs() < 1e-12);
+    /// ```
+    pub fn fit_cpts_mle(
+        structure: &BayesianNetwork,
+        data: &[HashMap<String, String>],
+        pseudocount: f64,
+    ) -> Result<BayesianNetwork, BnError> {
+        structure.check_complete_rows(data)?;
+        let mut fitted = structure.clone();
+        for variable in structure.variables.iter() {
+            let num_columns: usize = structure.parents[variable]
+                .iter()
+                .map(|p| structure.num_states(p))
+                .product();
+            let mut counts = vec![vec![pseudocount; num_columns]; structure.num_states(variable)];
+            for row in data.iter() {
+                let state = structure.state_index(variable, &row[variable]);
+                counts[state][structure.cpt_column(variable, row)] += 1.0;
+            }
+            let num_states = counts.len() as f64;
+            for column in 0..num_columns {
+                let total: f64 = counts.iter().map(|row| row[column]).sum();
+                for row in counts.iter_mut() {
+                    row[column] = if total == 0.0 {
+                        1.0 / num_states
+                    } else {
+                        row[column] / total
+                    };
+                }
+            }
+            fitted.cpts.insert(variable.clone(), counts);
+        }
+        Ok(fitted)
+    }
+
+    /// checks that every row of `data` assigns every variable one of its
+    /// states, and nothing else
+    pub(crate) fn check_complete_rows(
+        &self,
+        data: &[HashMap<String, String>],
+    ) -> Result<(), BnError> {
+        for (i, row) in data.iter().enumerate() {
+            if let Some(var) = row.keys().find(|var| !self.states.contains_key(*var)) {
+                return Err(BnError::UnknownVariable(var.clone()));
+            }
+            for var in self.variables.iter() {
+                let value = row
+                    .get(var)
+                    .ok_or_else(|| BnError::MissingValue(i, var.clone()))?;
+                if !self.states[var].contains(value) {
+                    return Err(BnError::InvalidValue(i, var.clone(), value.clone()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// the conditional probability of each variable's value in `assignment`
+    /// given its parents' values, in the order of `variables`
+    fn chain_rule_factors<'a>(
+        &'a self,
+        assignment: &'a HashMap<String, String>,
+    ) -> impl Iterator<Item = f64> + 'a {
+        for var in assignment.keys() {
+            if !self.states.contains_key(var) {
+                panic!("could not find variable {var}");
+            }
+        }
+        self.variables.iter().map(move |var| {
+            let value = assignment
+                .get(var)
+                .unwrap_or_else(|| panic!("no value assigned to variable {var}"));
+            self.conditional_probability(var, value, assignment)
+        })
+    }
+
+    /// Computes Pr(var = value | evidence) by brute force: sums
+    /// `joint_probability` over every completion of `evidence` and
+    /// normalizes. This takes time exponential in the number of unobserved
+    /// variables, so it is only a slow but obviously correct reference for
+    /// validating the other inference methods on small networks. Returns NaN
+    /// if the evidence has probability 0.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bayesian_network = BayesianNetwork::from_json(NETWORK);
+    /// let c_t = HashMap::from([(String::from("C"), String::from("T"))]);
+    ///
+    /// // Pr(A = T | C = T) = 0.5 * (0.25 * 0.7 + 0.75 * 0.6) / 0.4
+    /// let p = bayesian_network.marginal_by_enumeration("A", "T", &c_t);
+    /// assert!((p - 0.78125).abs() < 1e-9);
+    /// ```
+    pub fn marginal_by_enumeration(
+        &self,
+        var: &str,
+        value: &str,
+        evidence: &HashMap<String, String>,
+    ) -> f64 {
+        self.state_index(var, value);
+        for (observed, observed_value) in evidence.iter() {
+            self.state_index(observed, observed_value);
+        }
+        let hidden: Vec<String> = self
+            .variables
+            .iter()
+            .filter(|v| !evidence.contains_key(*v))
+            .cloned()
+            .collect();
+
+        let (mut numerator, mut denominator) = (0.0, 0.0);
+        for mut assignment in self.assignments_h(hidden) {
+            assignment.extend(evidence.iter().map(|(v, x)| (v.clone(), x.clone())));
+            let p = self.joint_probability(&assignment);
+            denominator += p;
+            if assignment[var] == value {
+                numerator += p;
+            }
+        }
+        if denominator == 0.0 {
+            return f64::NAN;
+        }
+        numerator / denominator
+    }
+
+    /// Produces a list of variables in topological order;
+    /// breaks ties with the order of `variables`. The order is computed on
+    /// first use and cached, so later calls only copy it.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bayesian_network = BayesianNetwork::from_json(NETWORK);
+    ///
+    /// assert_eq!(bayesian_network.topological_sort().len(), 3);
+    /// assert_eq!(bayesian_network.topological_sort()[0], "A");
+    /// assert_eq!(bayesian_network.topological_sort()[1], "B");
+    /// assert_eq!(bayesian_network.topological_sort()[2], "C");
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if the graph has a cycle; see `try_topological_sort`.
     pub fn topological_sort(&self) -> Vec<String> {
+        self.try_topological_sort()
+            .unwrap_or_else(|err| panic!("graph not topologically sortable: {err}"))
+    }
+
+    /// Like `topological_sort`, but returns a [`CycleError`] listing the
+    /// variables left over when no parent-free variable remains, i.e. those
+    /// on or below a cycle, instead of panicking.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    ///
+    /// // A -> B -> C -> B
+    /// static NETWORK: &str = r#"{
+    ///     "network": "cyclic",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]],
+    ///         "C": [[0.9, 0.2], [0.1, 0.8]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": ["A", "C"],
+    ///         "C": ["B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let err = BayesianNetwork::from_json(NETWORK).try_topological_sort().unwrap_err();
+    /// assert_eq!(err.variables, vec!["B", "C"]);
+    /// ```
+    pub fn try_topological_sort(&self) -> Result<Vec<String>, CycleError> {
+        self.topological_order
+            .get_or_init(|| self.compute_topological_sort())
+            .clone()
+    }
+
+    /// the uncached `try_topological_sort`
+    fn compute_topological_sort(&self) -> Result<Vec<String>, CycleError> {
         // super naive toposort
         let mut result: Vec<String> = Vec::new();
         let mut cur_vars: BTreeMap<String, Vec<String>> = self
             .variables
             .iter()
-            .map(|v| (v.clone(), self.parents[v].clone()))
+            .map(|v| (v.clone(), self.parents[v].clone()))
+            .collect();
+
+        while !cur_vars.is_empty() {
+            // find a variable with no parents, remove it, add it to the result
+            // list, and remove it as a parent from all other nodes
+            let topvar: String = match cur_vars.iter().find(|(_, value)| value.is_empty()) {
+                Some((topvar, _)) => topvar.clone(),
+                None => {
+                    return Err(CycleError {
+                        variables: cur_vars.into_keys().collect(),
+                    })
+                }
+            };
+            result.push(topvar.clone());
+            cur_vars.remove(&topvar);
+            for (_, value) in cur_vars.iter_mut() {
+                value.retain(|v| *v != topvar);
+            }
+        }
+        Ok(result)
+    }
+
+    /// checks that `cpt` has one row per state of `variable` and one column
+    /// per joint assignment to `parents`
+    fn cpt_has_shape(&self, variable: &str, parents: &[String], cpt: &[Vec<f64>]) -> bool {
+        let columns: usize = parents.iter().map(|p| self.num_states(p)).product();
+        cpt.len() == self.num_states(variable) && cpt.iter().all(|row| row.len() == columns)
+    }
+
+    /// Joins two networks over disjoint variable sets into a single network,
+    /// adding each `(parent, child)` pair in `connecting_edges` as a new edge.
+    ///
+    /// Every child that gains parents must be given a replacement CPT in
+    /// `new_cpts`. Its columns are laid out over the child's original parents
+    /// followed by its new parents, in the order they appear in
+    /// `connecting_edges`. The joined network is named `"{self}+{other}"`.
+    ///
+    /// Fails if the networks share a variable, an edge refers to an unknown
+    /// variable or is repeated, a CPT is missing, unexpected, or misshapen, or
+    /// the new edges introduce a cycle.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    /// use std::collections::HashMap;
+    ///
+    /// static RAIN: &str = r#"{
+    ///     "network": "rain",
+    ///     "variables": ["Rain"],
+    ///     "cpts": { "Rain": [[0.8], [0.2]] },
+    ///     "states": { "Rain": ["F", "T"] },
+    ///     "parents": { "Rain": [] }
+    /// }"#;
+    /// static TRAFFIC: &str = r#"{
+    ///     "network": "traffic",
+    ///     "variables": ["Jam"],
+    ///     "cpts": { "Jam": [[0.9], [0.1]] },
+    ///     "states": { "Jam": ["F", "T"] },
+    ///     "parents": { "Jam": [] }
+    /// }"#;
+    ///
+    /// let rain = BayesianNetwork::from_json(RAIN);
+    /// let traffic = BayesianNetwork::from_json(TRAFFIC);
+    /// let joined = rain
+    ///     .join(
+    ///         &traffic,
+    ///         &[(String::from("Rain"), String::from("Jam"))],
+    ///         HashMap::from([(String::from("Jam"), vec![vec![0.9, 0.4], vec![0.1, 0.6]])]),
+    ///     )
+    ///     .unwrap();
+    ///
+    /// assert_eq!(joined.parents("Jam"), &vec![String::from("Rain")]);
+    /// assert_eq!(joined.conditional_probability("Jam", "T", &HashMap::from([
+    ///     (String::from("Rain"), String::from("T"))
+    /// ])), 0.6);
+    /// ```
+    pub fn join(
+        &self,
+        other: &BayesianNetwork,
+        connecting_edges: &[(String, String)],
+        mut new_cpts: ConditionalProbabilityTable,
+    ) -> Result<BayesianNetwork, BnError> {
+        if let Some(shared) = other
+            .variables
+            .iter()
+            .find(|v| self.states.contains_key(*v))
+        {
+            return Err(BnError::VariableCollision(shared.clone()));
+        }
+
+        let mut joined = self.clone();
+        joined.topological_order = OnceLock::new();
+        joined.network = format!("{}+{}", self.network, other.network);
+        joined.variables.extend(other.variables.iter().cloned());
+        joined.cpts.extend(other.cpts.clone());
+        joined.states.extend(other.states.clone());
+        joined.parents.extend(other.parents.clone());
+        joined.metadata.extend(other.metadata.clone());
+
+        for (parent, child) in connecting_edges {
+            for v in [parent, child] {
+                if !joined.states.contains_key(v) {
+                    return Err(BnError::UnknownVariable(v.clone()));
+                }
+            }
+            let child_parents = joined.parents.get_mut(child).unwrap();
+            if child_parents.contains(parent) {
+                return Err(BnError::DuplicateEdge(parent.clone(), child.clone()));
+            }
+            child_parents.push(parent.clone());
+        }
+
+        let mut children: Vec<&String> = connecting_edges.iter().map(|(_, c)| c).collect();
+        children.sort();
+        children.dedup();
+        for child in children {
+            let cpt = new_cpts
+                .remove(child)
+                .ok_or_else(|| BnError::MissingCpt(child.clone()))?;
+            let parents = joined.parents_in_cpt_order(child);
+            if !joined.cpt_has_shape(child, parents, &cpt) {
+                return Err(BnError::CptShape {
+                    variable: child.clone(),
+                    expected: (
+                        joined.num_states(child),
+                        parents.iter().map(|p| joined.num_states(p)).product(),
+                    ),
+                });
+            }
+            joined.cpts.insert(child.clone(), cpt);
+        }
+        if let Some(unexpected) = new_cpts.into_keys().next() {
+            return Err(BnError::UnexpectedCpt(unexpected));
+        }
+
+        joined.try_topological_sort()?;
+        Ok(joined)
+    }
+
+    /// Coarsens `variable` by collapsing each group of its states into a
+    /// single new state. `groups` is a list of `(new_state, old_states)`;
+    /// states that are not mentioned in any group are kept as they are. A
+    /// merged state takes the position of its first member in `states`.
+    ///
+    /// - In `variable`'s own CPT the rows of a group are summed.
+    /// - In the CPT of each child of `variable` the columns of a group are
+    ///   averaged, each weighted by the marginal Pr(variable = state) of its
+    ///   state (uniformly if every state of the group has probability 0).
+    ///   This is exact when the child has no other parents, or when they are
+    ///   independent of `variable`; otherwise the weights should be
+    ///   conditioned on the other parents, and the merged network only
+    ///   approximates the original.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    /// use std::collections::HashMap;
+    ///
+    /// static NETWORK: &str = r#"{
+    ///     "network": "weather",
+    ///     "variables": ["Sky", "Umbrella"],
+    ///     "cpts": {
+    ///         "Sky": [[0.5], [0.3], [0.2]],
+    ///         "Umbrella": [[0.9, 0.2, 0.4], [0.1, 0.8, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "Sky": ["sun", "rain", "snow"],
+    ///         "Umbrella": ["F", "T"]
+    ///     },
+    ///     "parents": {
+    ///         "Sky": [],
+    ///         "Umbrella": ["Sky"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetwork::from_json(NETWORK);
+    /// let merged = bn
+    ///     .merge_states("Sky", &[(String::from("wet"), vec![String::from("rain"), String::from("snow")])])
+    ///     .unwrap();
+    ///
+    /// assert_eq!(merged.all_possible_assignments("Sky"), &vec![String::from("sun"), String::from("wet")]);
+    /// assert_eq!(merged.conditional_probability("Sky", "wet", &HashMap::new()), 0.5);
+    /// let wet = HashMap::from([(String::from("Sky"), String::from("wet"))]);
+    /// // Pr(Umbrella = T | wet) = (0.3 * 0.8 + 0.2 * 0.6) / 0.5
+    /// assert!((merged.conditional_probability("Umbrella", "T", &wet) - 0.72).abs() < 1e-12);
+    /// ```
+    pub fn merge_states(
+        &self,
+        variable: &str,
+        groups: &[(String, Vec<String>)],
+    ) -> Result<BayesianNetwork, BnError> {
+        let old_states = self
+            .states
+            .get(variable)
+            .ok_or_else(|| BnError::UnknownVariable(variable.to_string()))?;
+
+        // maps each old state to the name of the state it becomes
+        let mut renamed: HashMap<&String, &String> = HashMap::new();
+        for (new_state, members) in groups {
+            if members.is_empty() {
+                return Err(BnError::EmptyStateGroup(
+                    variable.to_string(),
+                    new_state.clone(),
+                ));
+            }
+            for member in members {
+                if !old_states.contains(member) {
+                    return Err(BnError::UnknownState(variable.to_string(), member.clone()));
+                }
+                if renamed.insert(member, new_state).is_some() {
+                    return Err(BnError::DuplicateState(
+                        variable.to_string(),
+                        member.clone(),
+                    ));
+                }
+            }
+        }
+
+        // a group name may not reuse the name of another group or of a kept state
+        for (i, (new_state, _)) in groups.iter().enumerate() {
+            let reused = groups[..i].iter().any(|(prev, _)| prev == new_state)
+                || (old_states.contains(new_state) && !renamed.contains_key(new_state));
+            if reused {
+                return Err(BnError::DuplicateState(
+                    variable.to_string(),
+                    new_state.clone(),
+                ));
+            }
+        }
+
+        // lay out the new states and map each old state index to its new index
+        let mut new_states: Vec<String> = Vec::new();
+        let mut new_index: Vec<usize> = Vec::new();
+        for old_state in old_states {
+            let name = renamed.get(old_state).copied().unwrap_or(old_state);
+            let idx = match new_states.iter().position(|s| s == name) {
+                Some(idx) => idx,
+                None => {
+                    new_states.push(name.clone());
+                    new_states.len() - 1
+                }
+            };
+            new_index.push(idx);
+        }
+
+        let mut merged = self.clone();
+
+        // sum the rows of the variable's own CPT
+        let old_cpt = &self.cpts[variable];
+        let columns = old_cpt.first().map_or(0, |row| row.len());
+        let mut new_cpt = vec![vec![0.0; columns]; new_states.len()];
+        for (old_idx, row) in old_cpt.iter().enumerate() {
+            for (acc, p) in new_cpt[new_index[old_idx]].iter_mut().zip(row) {
+                *acc += p;
+            }
+        }
+        merged.cpts.insert(variable.to_string(), new_cpt);
+        merged
+            .states
+            .insert(variable.to_string(), new_states.clone());
+
+        // average the columns of every child's CPT, weighted by the marginal
+        // of each merged state
+        let engine = VariableEliminationEngine::new(self);
+        let prior: Vec<f64> = old_states
+            .iter()
+            .map(|s| engine.marginal(variable, s))
+            .collect();
+        for child in self.variables.iter() {
+            let parents = self.parents_in_cpt_order(child);
+            let pos = match parents.iter().position(|p| p == variable) {
+                Some(pos) => pos,
+                None => continue,
+            };
+            let old_cards: Vec<usize> = parents.iter().map(|p| self.num_states(p)).collect();
+            let mut new_cards = old_cards.clone();
+            new_cards[pos] = new_states.len();
+            let new_columns: usize = new_cards.iter().product();
+
+            let new_cpt: Vec<Vec<f64>> = self.cpts[child]
+                .iter()
+                .map(|row| {
+                    (0..new_columns)
+                        .map(|col| {
+                            // decode `col` into per-parent state indices; the
+                            // last parent varies fastest
+                            let mut digits = vec![0; parents.len()];
+                            let mut rest = col;
+                            for i in (0..parents.len()).rev() {
+                                digits[i] = rest % new_cards[i];
+                                rest /= new_cards[i];
+                            }
+                            let members: Vec<usize> = (0..old_cards[pos])
+                                .filter(|old_idx| new_index[*old_idx] == digits[pos])
+                                .collect();
+                            let mass: f64 = members.iter().map(|old_idx| prior[*old_idx]).sum();
+                            let weight = |old_idx: usize| {
+                                if mass > 0.0 {
+                                    prior[old_idx] / mass
+                                } else {
+                                    1.0 / members.len() as f64
+                                }
+                            };
+                            members
+                                .iter()
+                                .map(|old_idx| {
+                                    digits[pos] = *old_idx;
+                                    let old_col = digits
+                                        .iter()
+                                        .zip(old_cards.iter())
+                                        .fold(0, |acc, (d, card)| acc * card + d);
+                                    weight(*old_idx) * row[old_col]
+                                })
+                                .sum()
+                        })
+                        .collect()
+                })
+                .collect();
+            merged.cpts.insert(child.clone(), new_cpt);
+        }
+        Ok(merged)
+    }
+
+    /// Generates a random Bayesian network, e.g. for benchmarking.
+    ///
+    /// Variables are named `X0`, `X1`, ... in topological order and each has
+    /// `num_states` states named `s0`, `s1`, .... Each variable draws up to
+    /// `max_parents` distinct parents uniformly from the variables before it,
+    /// and every CPT column is a uniformly random normalized distribution.
+    /// The result is fully determined by the state of `rng`, so seeding it
+    /// (e.g. with `StdRng::seed_from_u64`) reproduces the same network.
+    /// ```
+    /// use rand::{rngs::StdRng, SeedableRng};
+    /// use rsgm::BayesianNetwork;
+    ///
+    /// let bn = BayesianNetwork::random(20, 3, 2, &mut StdRng::seed_from_u64(0));
+    /// let same = BayesianNetwork::random(20, 3, 2, &mut StdRng::seed_from_u64(0));
+    ///
+    /// assert_eq!(bn.variables().len(), 20);
+    /// assert!(bn.variables().iter().all(|v| bn.parents(v).len() <= 3));
+    /// assert_eq!(bn.topological_sort(), same.topological_sort());
+    /// ```
+    pub fn random<R: Rng>(
+        num_variables: usize,
+        max_parents: usize,
+        num_states: usize,
+        rng: &mut R,
+    ) -> BayesianNetwork {
+        assert!(num_states > 0, "variables must have at least one state");
+        let variables: Vec<String> = (0..num_variables).map(|i| format!("X{i}")).collect();
+        let state_names: Vec<String> = (0..num_states).map(|i| format!("s{i}")).collect();
+
+        let mut cpts = HashMap::new();
+        let mut states = HashMap::new();
+        let mut parents = HashMap::new();
+        for (i, variable) in variables.iter().enumerate() {
+            let num_parents = rng.gen_range(0..=max_parents.min(i));
+            let mut candidates: Vec<usize> = (0..i).collect();
+            let mut cur_parents: Vec<String> = Vec::new();
+            for _ in 0..num_parents {
+                let chosen = candidates.swap_remove(rng.gen_range(0..candidates.len()));
+                cur_parents.push(variables[chosen].clone());
+            }
+
+            let columns = num_states.pow(cur_parents.len() as u32);
+            let mut cpt = vec![vec![0.0; columns]; num_states];
+            for col in 0..columns {
+                let weights: Vec<f64> = (0..num_states).map(|_| rng.gen::<f64>() + 1e-3).collect();
+                let total: f64 = weights.iter().sum();
+                for (row, w) in cpt.iter_mut().zip(weights) {
+                    row[col] = w / total;
+                }
+            }
+
+            cpts.insert(variable.clone(), cpt);
+            states.insert(variable.clone(), state_names.clone());
+            parents.insert(variable.clone(), cur_parents);
+        }
+
+        BayesianNetwork {
+            network: format!("random_{num_variables}"),
+            variables,
+            cpts,
+            states,
+            parents,
+            metadata: HashMap::new(),
+            topological_order: OnceLock::new(),
+        }
+    }
+
+    /// Computes the weakly-connected components of the network, i.e. the
+    /// connected components of its undirected skeleton.
+    ///
+    /// Components are ordered by their first variable in `variables`, and the
+    /// variables within a component keep their order from `variables`. Most
+    /// networks form a single component; more than one usually indicates a
+    /// modeling mistake, but since the components are independent of each
+    /// other they can also be compiled and queried separately.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    ///
+    /// // models A -> B alongside an unrelated C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "disconnected",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.9, 0.2], [0.1, 0.8]],
+    ///         "C": [[0.3], [0.7]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents": {
+    ///         "A": [],
+    ///         "B": ["A"],
+    ///         "C": []
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetwork::from_json(NETWORK);
+    /// assert_eq!(
+    ///     bn.connected_components(),
+    ///     vec![vec![String::from("A"), String::from("B")], vec![String::from("C")]]
+    /// );
+    /// ```
+    pub fn connected_components(&self) -> Vec<Vec<String>> {
+        // maps each variable to the representative of its component
+        let mut component: HashMap<&String, usize> = HashMap::new();
+        let mut neighbors: HashMap<&String, Vec<&String>> = HashMap::new();
+        for v in self.variables.iter() {
+            for p in self.parents[v].iter() {
+                neighbors.entry(v).or_default().push(p);
+                neighbors.entry(p).or_default().push(v);
+            }
+        }
+
+        let mut components: Vec<Vec<String>> = Vec::new();
+        for v in self.variables.iter() {
+            if component.contains_key(v) {
+                continue;
+            }
+            let id = components.len();
+            let mut frontier = vec![v];
+            component.insert(v, id);
+            while let Some(cur) = frontier.pop() {
+                for n in neighbors.get(cur).into_iter().flatten() {
+                    if !component.contains_key(n) {
+                        component.insert(n, id);
+                        frontier.push(n);
+                    }
+                }
+            }
+            components.push(Vec::new());
+        }
+        for v in self.variables.iter() {
+            components[component[v]].push(v.clone());
+        }
+        components
+    }
+
+    /// Fixes CPT entries that are not valid probabilities, pushing a
+    /// [`Warning`] onto `warnings` for each correction:
+    /// - NaN entries are set to 0
+    /// - entries outside [0, 1] are clamped into it
+    /// - columns whose sum differs from 1 by more than `REPAIR_TOLERANCE`
+    ///   are rescaled to sum to 1, except columns summing to 0, which are
+    ///   left unchanged
+    pub fn repair(&mut self, warnings: &mut Warnings) {
+        for variable in self.variables.clone() {
+            let states = self.states[&variable].clone();
+            let num_columns = self.cpts[&variable].first().map_or(0, |row| row.len());
+            for column in 0..num_columns {
+                let label = self.column_label(&variable, column);
+                let cpt = self.cpts.get_mut(&variable).unwrap();
+                for (state, row) in states.iter().zip(cpt.iter_mut()) {
+                    let value = row[column];
+                    if value.is_nan() {
+                        row[column] = 0.0;
+                        warnings.push(Warning::NanProbability {
+                            column: label.clone(),
+                            state: state.clone(),
+                        });
+                    } else if !(0.0..=1.0).contains(&value) {
+                        row[column] = value.clamp(0.0, 1.0);
+                        warnings.push(Warning::ProbabilityClamped {
+                            column: label.clone(),
+                            state: state.clone(),
+                            value,
+                        });
+                    }
+                }
+                let sum: f64 = cpt.iter().map(|row| row[column]).sum();
+                if sum == 0.0 {
+                    warnings.push(Warning::ZeroColumn { column: label });
+                } else if (sum - 1.0).abs() > REPAIR_TOLERANCE {
+                    for row in cpt.iter_mut() {
+                        row[column] /= sum;
+                    }
+                    warnings.push(Warning::ColumnRescaled { column: label, sum });
+                }
+            }
+        }
+    }
+
+    /// Rescales every CPT column to sum to 1 by dividing each entry by the
+    /// column's sum. Columns summing to 0 are left unchanged, so they still
+    /// show up in `check_normalized`.
+    ///
+    /// Unlike `repair` this rescales even columns that are already within
+    /// `REPAIR_TOLERANCE` of 1, and neither clamps entries nor records
+    /// warnings; it is meant as the last step after building CPTs from
+    /// counts or other accumulated weights.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C, with CPTs given as counts and one
+    /// // column of C never observed
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[5.0], [5.0]],
+    ///         "B": [[1.0], [3.0]],
+    ///         "C": [[9.0, 8.0, 0.0, 4.0], [1.0, 2.0, 0.0, 6.0]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let mut bn = BayesianNetwork::from_json(NETWORK);
+    /// bn.normalize();
+    ///
+    /// let errors = bn.check_normalized(1e-9).unwrap_err();
+    /// assert_eq!(errors, vec![(String::from("C"), 2, 0.0)]);
+    /// let b_t = bn.conditional_probability("B", "T", &HashMap::new());
+    /// assert!((b_t - 0.75).abs() < 1e-9);
+    /// ```
+    pub fn normalize(&mut self) {
+        for cpt in self.cpts.values_mut() {
+            let num_columns = cpt.first().map_or(0, |row| row.len());
+            for column in 0..num_columns {
+                let sum: f64 = cpt.iter().map(|row| row[column]).sum();
+                if sum != 0.0 {
+                    for row in cpt.iter_mut() {
+                        row[column] /= sum;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Finds the CPT columns whose entries do not sum to 1 within
+    /// `tolerance`, as `(variable, column, sum)` in the order of `variables`
+    /// and then of columns (see `parents_in_cpt_order`).
+    ///
+    /// Unlike `validate` this is not run on load, since unnormalized
+    /// potentials are sometimes intended; `repair` rescales such columns.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    ///
+    /// // models the collider A, B -> C, with the columns of B and of C given
+    /// // A = T, B = F off by a little
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.74]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.72, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetwork::from_json(NETWORK);
+    /// let errors = bn.check_normalized(1e-9).unwrap_err();
+    /// assert_eq!(errors.len(), 2);
+    /// assert_eq!((errors[0].0.as_str(), errors[0].1), ("B", 0));
+    /// assert!((errors[0].2 - 0.99).abs() < 1e-9);
+    /// assert_eq!((errors[1].0.as_str(), errors[1].1), ("C", 2));
+    /// assert!((errors[1].2 - 1.02).abs() < 1e-9);
+    ///
+    /// assert!(bn.check_normalized(0.05).is_ok());
+    /// ```
+    pub fn check_normalized(&self, tolerance: f64) -> Result<(), Vec<(String, usize, f64)>> {
+        let mut errors: Vec<(String, usize, f64)> = Vec::new();
+        for variable in self.variables.iter() {
+            let cpt = &self.cpts[variable];
+            let num_columns = cpt.first().map_or(0, |row| row.len());
+            for column in 0..num_columns {
+                let sum: f64 = cpt.iter().map(|row| row[column]).sum();
+                if (sum - 1.0).abs() > tolerance {
+                    errors.push((variable.clone(), column, sum));
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Checks whether `self` and `other` are the same model: they have the
+    /// same variables, each with the same states and parents in the same
+    /// order, and CPT entries that agree within `tolerance`. The order of
+    /// `variables` and the names of the networks do not matter.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetwork::from_json(NETWORK);
+    /// let learned = BayesianNetwork::from_json(
+    ///     &NETWORK
+    ///         .replace(r#"["A", "B", "C"]"#, r#"["B", "A", "C"]"#)
+    ///         .replace("[[0.25], [0.75]]", "[[0.26], [0.74]]"),
+    /// );
+    /// assert!(bn.approx_eq(&learned, 0.02));
+    /// assert!(!bn.approx_eq(&learned, 0.001));
+    ///
+    /// // the order of C's parents lays out its CPT, so it must match too
+    /// let swapped = BayesianNetwork::from_json(&NETWORK.replace(r#"["A", "B"]"#, r#"["B", "A"]"#));
+    /// assert!(!bn.approx_eq(&swapped, 0.02));
+    /// ```
+    pub fn approx_eq(&self, other: &BayesianNetwork, tolerance: f64) -> bool {
+        self.variables.len() == other.variables.len()
+            && self.variables.iter().all(|variable| {
+                other.states.contains_key(variable)
+                    && self.variable_approx_eq(variable, other, variable, tolerance)
+            })
+    }
+
+    /// checks that `variable` of `self` and `other_variable` of `other` have
+    /// the same states and parents, in the same order, and CPTs whose entries
+    /// agree within `tolerance`
+    fn variable_approx_eq(
+        &self,
+        variable: &str,
+        other: &BayesianNetwork,
+        other_variable: &str,
+        tolerance: f64,
+    ) -> bool {
+        self.states[variable] == other.states[other_variable]
+            && self.parents[variable] == other.parents[other_variable]
+            && self.cpts[variable].len() == other.cpts[other_variable].len()
+            && self.cpts[variable]
+                .iter()
+                .zip(other.cpts[other_variable].iter())
+                .all(|(row, other_row)| {
+                    row.len() == other_row.len()
+                        && row
+                            .iter()
+                            .zip(other_row)
+                            .all(|(p, q)| (p - q).abs() <= tolerance)
+                })
+    }
+
+    /// Finds pairs of distinct variables that are interchangeable: they have
+    /// the same parents and states (in the same order) and CPTs that agree
+    /// within `EQUIVALENCE_TOLERANCE`. Such pairs are usually redundant copies
+    /// left behind by joining or importing networks.
+    ///
+    /// Each pair lists the variable that comes first in `variables` first.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    ///
+    /// // A and B are both fair coins, and C and D copy the same parents
+    /// static NETWORK: &str = r#"{
+    ///     "network": "copies",
+    ///     "variables": ["A", "B", "C", "D"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.5], [0.5]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]],
+    ///         "D": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"],
+    ///         "D": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"],
+    ///         "D": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetwork::from_json(NETWORK);
+    ///
+    /// assert_eq!(
+    ///     bn.find_equivalent_variables(),
+    ///     vec![
+    ///         (String::from("A"), String::from("B")),
+    ///         (String::from("C"), String::from("D")),
+    ///     ]
+    /// );
+    /// ```
+    pub fn find_equivalent_variables(&self) -> Vec<(String, String)> {
+        let mut pairs: Vec<(String, String)> = Vec::new();
+        for (i, a) in self.variables.iter().enumerate() {
+            for b in self.variables[i + 1..].iter() {
+                if self.variable_approx_eq(a, self, b, EQUIVALENCE_TOLERANCE) {
+                    pairs.push((a.clone(), b.clone()));
+                }
+            }
+        }
+        pairs
+    }
+
+    /// Iterates over every CPT entry of the network. Variables are visited
+    /// in the order of `variables`; within a variable, columns are visited in
+    /// CPT order (see `parents_in_cpt_order`), and within a column, values
+    /// in the order of `all_possible_assignments`.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bayesian_network = BayesianNetwork::from_json(NETWORK);
+    /// let cells: Vec<(String, String, String, f64)> = bayesian_network
+    ///     .iter_cpt_cells()
+    ///     .filter(|cell| cell.variable == "C")
+    ///     .map(|cell| {
+    ///         let a = cell.parent_assignment["A"].clone();
+    ///         let b = cell.parent_assignment["B"].clone();
+    ///         (a, b, cell.value, cell.probability)
+    ///     })
+    ///     .collect();
+    ///
+    /// let cell = |a: &str, b: &str, c: &str, p| (a.to_string(), b.to_string(), c.to_string(), p);
+    /// assert_eq!(cells, vec![
+    ///     cell("F", "F", "F", 0.9), cell("F", "F", "T", 0.1),
+    ///     cell("F", "T", "F", 0.8), cell("F", "T", "T", 0.2),
+    ///     cell("T", "F", "F", 0.3), cell("T", "F", "T", 0.7),
+    ///     cell("T", "T", "F", 0.4), cell("T", "T", "T", 0.6),
+    /// ]);
+    /// assert_eq!(bayesian_network.iter_cpt_cells().count(), 12);
+    /// ```
+    pub fn iter_cpt_cells(&self) -> impl Iterator<Item = CptCell> + '_ {
+        self.variables
+            .iter()
+            .flat_map(move |variable| self.cpt_cells(variable))
+    }
+
+    /// the cells of `variable`'s CPT, in the order of `iter_cpt_cells`
+    fn cpt_cells<'a>(&'a self, variable: &'a String) -> impl Iterator<Item = CptCell> + 'a {
+        let cpt = &self.cpts[variable];
+        let num_columns = cpt.first().map_or(0, |row| row.len());
+        (0..num_columns).flat_map(move |column| {
+            let parent_assignment: HashMap<String, String> = self
+                .column_assignment(variable, column)
+                .into_iter()
+                .collect();
+            self.states[variable]
+                .iter()
+                .zip(cpt.iter())
+                .map(move |(value, row)| CptCell {
+                    variable: variable.clone(),
+                    value: value.clone(),
+                    parent_assignment: parent_assignment.clone(),
+                    probability: row[column],
+                })
+        })
+    }
+
+    /// get the raw CPT of `variable`: one row per state, in the order of
+    /// `all_possible_assignments`, and one column per parent assignment, in
+    /// the order described by `parents_in_cpt_order`. See `cpt_entries` for
+    /// the same numbers paired with what they condition on.
+    ///
+    /// # Panics
+    /// Panics if `variable` is not in the network.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bayesian_network = BayesianNetwork::from_json(NETWORK);
+    ///
+    /// assert_eq!(bayesian_network.cpt("B"), &vec![vec![0.25], vec![0.75]]);
+    /// assert_eq!(bayesian_network.cpt("C")[1], vec![0.1, 0.2, 0.7, 0.6]);
+    /// ```
+    pub fn cpt(&self, variable: &str) -> &Vec<Vec<f64>> {
+        self.cpts
+            .get(variable)
+            .unwrap_or_else(|| panic!("could not find variable {variable}"))
+    }
+
+    /// get every entry of `variable`'s CPT as `(parent assignment, value,
+    /// probability)`, meaning Pr(variable = value | parent assignment) =
+    /// probability, ordered by parent assignment in CPT column order and then
+    /// by value. This is `iter_cpt_cells` restricted to one variable.
+    ///
+    /// # Panics
+    /// Panics if `variable` is not in the network.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bayesian_network = BayesianNetwork::from_json(NETWORK);
+    /// let entries = bayesian_network.cpt_entries("C");
+    ///
+    /// assert_eq!(entries.len(), 8);
+    /// for (parent_assignment, value, p) in entries.iter() {
+    ///     assert_eq!(bayesian_network.conditional_probability("C", value, parent_assignment), *p);
+    /// }
+    /// let a_t_b_f = HashMap::from([
+    ///     (String::from("A"), String::from("T")),
+    ///     (String::from("B"), String::from("F")),
+    /// ]);
+    /// assert_eq!(entries[5], (a_t_b_f, String::from("T"), 0.7));
+    /// ```
+    pub fn cpt_entries(&self, variable: &str) -> Vec<(ParentConfig, String, f64)> {
+        let variable = self
+            .variables
+            .iter()
+            .find(|v| *v == variable)
+            .unwrap_or_else(|| panic!("could not find variable {variable}"));
+        self.cpt_cells(variable)
+            .map(|cell| (cell.parent_assignment, cell.value, cell.probability))
+            .collect()
+    }
+
+    /// Formats the CPT of `variable` as CSV, with a header row, one column per
+    /// parent (in CPT order, see `parents_in_cpt_order`) followed by one
+    /// column per state of `variable`, and one row per parent configuration
+    /// in CPT column order. Fields containing commas, quotes, or newlines
+    /// are quoted.
+    ///
+    /// # Panics
+    /// Panics if `variable` is not in the network.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bayesian_network = BayesianNetwork::from_json(NETWORK);
+    ///
+    /// assert_eq!(bayesian_network.cpt_to_csv("C"), "\
+    /// A,B,F,T
+    /// F,F,0.9,0.1
+    /// F,T,0.8,0.2
+    /// T,F,0.3,0.7
+    /// T,T,0.4,0.6
+    /// ");
+    /// assert_eq!(bayesian_network.cpt_to_csv("A"), "F,T\n0.5,0.5\n");
+    /// ```
+    pub fn cpt_to_csv(&self, variable: &str) -> String {
+        fn field(text: &str) -> String {
+            if text.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", text.replace('"', "\"\""))
+            } else {
+                text.to_string()
+            }
+        }
+        let cpt = self
+            .cpts
+            .get(variable)
+            .unwrap_or_else(|| panic!("could not find variable {variable}"));
+        let header: Vec<String> = self
+            .parents_in_cpt_order(variable)
+            .iter()
+            .chain(self.states[variable].iter())
+            .map(|name| field(name))
+            .collect();
+        let mut csv = header.join(",") + "\n";
+        let num_columns = cpt.first().map_or(0, |row| row.len());
+        for column in 0..num_columns {
+            let row: Vec<String> = self
+                .column_assignment(variable, column)
+                .iter()
+                .map(|(_, value)| field(value))
+                .chain(cpt.iter().map(|row| row[column].to_string()))
+                .collect();
+            csv += &row.join(",");
+            csv += "\n";
+        }
+        csv
+    }
+
+    /// Lists every CPT entry that is exactly 0 or 1 as
+    /// `(variable, value, parent_config)`, in the order of `iter_cpt_cells`.
+    /// These entries are hard constraints rather than probabilities: a 0 can
+    /// make evidence impossible, and a 1 makes the variable a function of its
+    /// parents in that column.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C, where C is the AND of A and B
+    /// static NETWORK: &str = r#"{
+    ///     "network": "and_gate",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[1.0, 1.0, 1.0, 0.1], [0.0, 0.0, 0.0, 0.9]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bayesian_network = BayesianNetwork::from_json(NETWORK);
+    /// let entries = bayesian_network.deterministic_entries();
+    ///
+    /// assert_eq!(entries.len(), 6);
+    /// assert!(entries.iter().all(|(variable, _, _)| variable == "C"));
+    /// assert_eq!(entries[0], (
+    ///     String::from("C"),
+    ///     String::from("F"),
+    ///     HashMap::from([
+    ///         (String::from("A"), String::from("F")),
+    ///         (String::from("B"), String::from("F")),
+    ///     ]),
+    /// ));
+    /// ```
+    pub fn deterministic_entries(&self) -> Vec<(String, String, ParentConfig)> {
+        self.iter_cpt_cells()
+            .filter(|cell| cell.probability == 0.0 || cell.probability == 1.0)
+            .map(|cell| (cell.variable, cell.value, cell.parent_assignment))
+            .collect()
+    }
+
+    /// Checks whether the network is a polytree, i.e. its undirected skeleton
+    /// has no cycles (so there is at most one path between any two
+    /// variables). Belief propagation is exact and cheap on polytrees.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// assert!(BayesianNetwork::from_json(NETWORK).is_polytree());
+    ///
+    /// // adding A -> B closes the loop A - B - C in the skeleton
+    /// let diamond = NETWORK
+    ///     .replace(r#""B": [[0.25], [0.75]]"#, r#""B": [[0.25, 0.5], [0.75, 0.5]]"#)
+    ///     .replace(r#""B": [],"#, r#""B": ["A"],"#);
+    /// assert!(!BayesianNetwork::from_json(&diamond).is_polytree());
+    /// ```
+    pub fn is_polytree(&self) -> bool {
+        // a graph is a forest exactly when it has one edge fewer than
+        // variables per connected component
+        let num_edges: usize = self.variables.iter().map(|v| self.parents[v].len()).sum();
+        num_edges + self.connected_components().len() == self.variables.len()
+    }
+
+    /// Ranks the parents of `variable` by how strongly each one drives it,
+    /// most influential first, as `(parent, influence)` pairs.
+    ///
+    /// For each parent, the other parents are averaged out under their prior
+    /// marginals (treated as independent), giving one distribution of
+    /// `variable` per state of the parent. The parent's influence is the
+    /// largest total-variation distance between two of these distributions:
+    /// 0 when the parent makes no difference on average and 1 when switching
+    /// it can flip `variable` deterministically. Ties keep CPT order.
+    ///
+    /// # Panics
+    /// Panics if `variable` is not in the network.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let influence = BayesianNetwork::from_json(NETWORK).parent_influence("C");
+    ///
+    /// assert_eq!(influence[0].0, "A");
+    /// // Pr(C = T | A = T) - Pr(C = T | A = F) = 0.625 - 0.175
+    /// assert!((influence[0].1 - 0.45).abs() < 1e-9);
+    /// // with A averaged out, Pr(C = T | B) is 0.4 either way
+    /// assert_eq!(influence[1].0, "B");
+    /// assert!(influence[1].1.abs() < 1e-9);
+    /// ```
+    pub fn parent_influence(&self, variable: &str) -> Vec<(String, f64)> {
+        let cpt = self
+            .cpts
+            .get(variable)
+            .unwrap_or_else(|| panic!("could not find variable {variable}"));
+        let parents = self.parents_in_cpt_order(variable);
+        let engine = VariableEliminationEngine::new(self);
+        let priors: HashMap<&String, Vec<f64>> = parents
+            .iter()
+            .map(|p| {
+                let prior = self.states[p]
+                    .iter()
+                    .map(|s| engine.marginal(p, s))
+                    .collect();
+                (p, prior)
+            })
+            .collect();
+        let num_columns = cpt.first().map_or(0, |row| row.len());
+        let columns: Vec<Vec<usize>> = (0..num_columns)
+            .map(|column| {
+                self.column_assignment(variable, column)
+                    .iter()
+                    .map(|(parent, value)| self.state_index(parent, value))
+                    .collect()
+            })
+            .collect();
+
+        let mut influence: Vec<(String, f64)> = parents
+            .iter()
+            .enumerate()
+            .map(|(i, parent)| {
+                // distributions[s]: Pr(variable | parent = s), others averaged out
+                let mut distributions = vec![vec![0.0; cpt.len()]; self.num_states(parent)];
+                for (column, states) in columns.iter().enumerate() {
+                    let weight: f64 = parents
+                        .iter()
+                        .zip(states)
+                        .enumerate()
+                        .filter(|(j, _)| *j != i)
+                        .map(|(_, (other, s))| priors[other][*s])
+                        .product();
+                    for (k, row) in cpt.iter().enumerate() {
+                        distributions[states[i]][k] += weight * row[column];
+                    }
+                }
+                let mut max_distance: f64 = 0.0;
+                for (s, d) in distributions.iter().enumerate() {
+                    for other in distributions[s + 1..].iter() {
+                        let distance: f64 =
+                            d.iter().zip(other).map(|(x, y)| (x - y).abs()).sum::<f64>() / 2.0;
+                        max_distance = max_distance.max(distance);
+                    }
+                }
+                (parent.clone(), max_distance)
+            })
+            .collect();
+        influence.sort_by(|a, b| b.1.total_cmp(&a.1));
+        influence
+    }
+
+    /// Draws one sample forward through the network, visiting variables in
+    /// `order` (which must be topological), as a map from each variable to
+    /// the index of its sampled state
+    fn forward_sample<'a, R: Rng>(
+        &self,
+        order: &'a [String],
+        rng: &mut R,
+    ) -> HashMap<&'a str, usize> {
+        self.forward_sample_clamped(order, &HashMap::new(), rng)
+    }
+
+    /// Like `forward_sample`, but variables in `clamped` take their given
+    /// state instead of a sampled one
+    pub(crate) fn forward_sample_clamped<'a, R: Rng>(
+        &self,
+        order: &'a [String],
+        clamped: &HashMap<&str, usize>,
+        rng: &mut R,
+    ) -> HashMap<&'a str, usize> {
+        let mut sample: HashMap<&str, usize> = HashMap::new();
+        for var in order.iter() {
+            if let Some(state) = clamped.get(var.as_str()) {
+                sample.insert(var.as_str(), *state);
+                continue;
+            }
+            let column = self.sampled_column(var, &sample);
+            let weights: Vec<f64> = self.cpts[var].iter().map(|row| row[column]).collect();
+            sample.insert(var.as_str(), sample_index(&weights, rng));
+        }
+        sample
+    }
+
+    /// the column of `var`'s CPT selected by its parents' states in `sample`
+    pub(crate) fn sampled_column(&self, var: &str, sample: &HashMap<&str, usize>) -> usize {
+        self.parents_in_cpt_order(var)
+            .iter()
+            .fold(0, |idx, parent| {
+                idx * self.num_states(parent) + sample[parent.as_str()]
+            })
+    }
+
+    /// Draws one full assignment from the network by ancestral sampling:
+    /// each variable, in topological order, takes a value drawn from its CPT
+    /// column for the values already drawn for its parents. See `sample_n`.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> HashMap<String, String> {
+        self.sample_n(1, rng).pop().unwrap()
+    }
+
+    /// Draws `n` independent full assignments from the network by ancestral
+    /// sampling; see `sample`.
+    /// ```
+    /// use rand::{rngs::StdRng, SeedableRng};
+    /// use rsgm::BayesianNetwork;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetwork::from_json(NETWORK);
+    /// let samples = bn.sample_n(20_000, &mut StdRng::seed_from_u64(0));
+    ///
+    /// let frequency = |f: &dyn Fn(&std::collections::HashMap<String, String>) -> bool| {
+    ///     samples.iter().filter(|s| f(s)).count() as f64 / samples.len() as f64
+    /// };
+    /// // Pr(B = T) = 0.75 and Pr(C = T) = 0.4
+    /// assert!((frequency(&|s| s["B"] == "T") - 0.75).abs() < 0.02);
+    /// assert!((frequency(&|s| s["C"] == "T") - 0.4).abs() < 0.02);
+    /// // Pr(C = T | A = T, B = F) = 0.7
+    /// let a_t_b_f = frequency(&|s| s["A"] == "T" && s["B"] == "F");
+    /// let c_t_a_t_b_f = frequency(&|s| s["A"] == "T" && s["B"] == "F" && s["C"] == "T");
+    /// assert!((c_t_a_t_b_f / a_t_b_f - 0.7).abs() < 0.03);
+    /// ```
+    pub fn sample_n<R: Rng>(&self, n: usize, rng: &mut R) -> Vec<HashMap<String, String>> {
+        let order = self.topological_sort();
+        (0..n)
+            .map(|_| {
+                self.forward_sample(&order, rng)
+                    .into_iter()
+                    .map(|(var, state)| (var.to_string(), self.states[var][state].clone()))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Estimates Pr(query | evidence) for `query` given as `(variable, value)`
+    /// by rejection sampling: draws `n` samples forward through the network,
+    /// discards those that disagree with `evidence`, and returns the fraction
+    /// of the rest in which the query holds. Returns NaN if no sample agrees
+    /// with the evidence. See `approx_posterior_with_warnings` to learn when
+    /// too few samples were kept for the estimate to be trusted.
+    /// ```
+    /// use rand::{rngs::StdRng, SeedableRng};
+    /// use rsgm::BayesianNetwork;
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetwork::from_json(NETWORK);
+    /// let query = (String::from("A"), String::from("T"));
+    /// let evidence = HashMap::from([(String::from("C"), String::from("T"))]);
+    ///
+    /// // Pr(A = T | C = T) = 0.5 * (0.25 * 0.7 + 0.75 * 0.6) / 0.4
+    /// let p = bn.approx_posterior(&query, &evidence, 20_000, &mut StdRng::seed_from_u64(0));
+    /// assert!((p - 0.78125).abs() < 0.02);
+    /// ```
+    pub fn approx_posterior<R: Rng>(
+        &self,
+        query: &(String, String),
+        evidence: &HashMap<String, String>,
+        n: usize,
+        rng: &mut R,
+    ) -> f64 {
+        self.approx_posterior_with_warnings(query, evidence, n, rng, &mut Warnings::new())
+    }
+
+    /// Like `approx_posterior`, but pushes a [`Warning::LowAcceptance`] onto
+    /// `warnings` if fewer than `MIN_ACCEPTANCE_RATE` of the samples agreed
+    /// with the evidence, in which case the estimate rests on few samples
+    /// and may be far off
+    /// ```
+    /// use rand::{rngs::StdRng, SeedableRng};
+    /// use rsgm::{BayesianNetwork, Warning, Warnings};
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C, where C = T is rare
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.999, 0.999, 0.999, 0.999], [0.001, 0.001, 0.001, 0.001]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetwork::from_json(NETWORK);
+    /// let query = (String::from("A"), String::from("T"));
+    /// let evidence = HashMap::from([(String::from("C"), String::from("T"))]);
+    ///
+    /// let mut warnings = Warnings::new();
+    /// bn.approx_posterior_with_warnings(
+    ///     &query,
+    ///     &evidence,
+    ///     1000,
+    ///     &mut StdRng::seed_from_u64(0),
+    ///     &mut warnings,
+    /// );
+    /// assert!(matches!(
+    ///     warnings.iter().next(),
+    ///     Some(Warning::LowAcceptance { drawn: 1000, .. })
+    /// ));
+    /// ```
+    pub fn approx_posterior_with_warnings<R: Rng>(
+        &self,
+        query: &(String, String),
+        evidence: &HashMap<String, String>,
+        n: usize,
+        rng: &mut R,
+        warnings: &mut Warnings,
+    ) -> f64 {
+        let (var, value) = query;
+        let target = self.state_index(var, value);
+        let observed: Vec<(&str, usize)> = evidence
+            .iter()
+            .map(|(v, x)| (v.as_str(), self.state_index(v, x)))
+            .collect();
+        let order = self.topological_sort();
+        // the query variable's state in each sample that agrees with the
+        // evidence
+        let kept: Vec<usize> = (0..n)
+            .map(|_| self.forward_sample(&order, rng))
+            .filter(|sample| observed.iter().all(|(v, state)| sample[v] == *state))
+            .map(|sample| sample[var.as_str()])
+            .collect();
+        let accepted = kept.len();
+        let hits = kept.iter().filter(|state| **state == target).count();
+        if (accepted as f64) < MIN_ACCEPTANCE_RATE * n as f64 {
+            warnings.push(Warning::LowAcceptance { accepted, drawn: n });
+        }
+        hits as f64 / accepted as f64
+    }
+
+    /// Estimates Pr(query | evidence) for `query` given as `(variable, value)`
+    /// by Gibbs sampling. Starting from a forward sample with the evidence
+    /// clamped, each sweep resamples every unobserved variable, in
+    /// topological order, from its distribution given its Markov blanket:
+    /// proportional to its own CPT entry times the CPT entries of its
+    /// children. The first `burn_in` sweeps are discarded and the estimate is
+    /// the fraction of the next `samples` sweeps in which the query holds,
+    /// or NaN if `samples` is 0.
+    ///
+    /// Unlike `approx_posterior`, no sample is wasted on unlikely evidence.
+    /// The chain may fail to mix if CPTs contain zeros that make some
+    /// assignments unreachable one variable at a time.
+    /// ```
+    /// use rand::{rngs::StdRng, SeedableRng};
+    /// use rsgm::BayesianNetwork;
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C, where C = T is rare
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.999, 0.998, 0.997, 0.996], [0.001, 0.002, 0.003, 0.004]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetwork::from_json(NETWORK);
+    /// let query = (String::from("A"), String::from("T"));
+    /// let evidence = HashMap::from([(String::from("C"), String::from("T"))]);
+    ///
+    /// // Pr(A = T | C = T) = 0.5 * (0.25 * 0.003 + 0.75 * 0.004) / 0.00275
+    /// let exact = bn.marginal_by_enumeration("A", "T", &evidence);
+    /// assert!((exact - 0.001875 / 0.00275).abs() < 1e-9);
+    /// let p = bn.gibbs_posterior(&query, &evidence, 100, 20_000, &mut StdRng::seed_from_u64(0));
+    /// assert!((p - exact).abs() < 0.03);
+    /// ```
+    pub fn gibbs_posterior<R: Rng>(
+        &self,
+        query: &(String, String),
+        evidence: &HashMap<String, String>,
+        burn_in: usize,
+        samples: usize,
+        rng: &mut R,
+    ) -> f64 {
+        let (var, value) = query;
+        let target = self.state_index(var, value);
+        let clamped: HashMap<&str, usize> = evidence
+            .iter()
+            .map(|(v, x)| (v.as_str(), self.state_index(v, x)))
+            .collect();
+        let order = self.topological_sort();
+        let unobserved: Vec<(&String, Vec<String>)> = order
+            .iter()
+            .filter(|v| !clamped.contains_key(v.as_str()))
+            .map(|v| (v, self.children(v)))
+            .collect();
+
+        let mut state = self.forward_sample_clamped(&order, &clamped, rng);
+        let mut hits = 0;
+        for sweep in 0..burn_in + samples {
+            for (v, children) in unobserved.iter() {
+                let current = state[v.as_str()];
+                let weights: Vec<f64> = (0..self.num_states(v))
+                    .map(|s| {
+                        state.insert(v.as_str(), s);
+                        let own = self.cpts[*v][s][self.sampled_column(v, &state)];
+                        children.iter().fold(own, |p, child| {
+                            p * self.cpts[child][state[child.as_str()]]
+                                [self.sampled_column(child, &state)]
+                        })
+                    })
+                    .collect();
+                // a state inconsistent with the evidence stays put rather
+                // than divide by 0
+                let next = if weights.iter().sum::<f64>() > 0.0 {
+                    sample_index(&weights, rng)
+                } else {
+                    current
+                };
+                state.insert(v.as_str(), next);
+            }
+            if sweep >= burn_in && state[var.as_str()] == target {
+                hits += 1;
+            }
+        }
+        hits as f64 / samples as f64
+    }
+
+    /// Estimates the conditional mutual information I(x; y | z), in bits,
+    /// from `n` samples drawn forward through the network, by plugging the
+    /// empirical joint distribution of x, y, and z into the definition.
+    ///
+    /// The result is near 0 exactly when x and y are independent given z
+    /// under the network's parameters (and not merely its structure), up to
+    /// a small positive bias from sampling that shrinks as `n` grows. An
+    /// empty `z` estimates the mutual information I(x; y).
+    ///
+    /// # Panics
+    /// Panics if `x`, `y`, or a variable of `z` is not in the network.
+    /// ```
+    /// use rand::{rngs::StdRng, SeedableRng};
+    /// use rsgm::BayesianNetwork;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetwork::from_json(NETWORK);
+    /// let mut rng = StdRng::seed_from_u64(0);
+    ///
+    /// // A and B are independent...
+    /// assert!(bn.test_independence_from_samples("A", "B", &[], 20_000, &mut rng) < 0.001);
+    /// // ...but observing their common effect C couples them (exactly 0.0081 bits)
+    /// let c = [String::from("C")];
+    /// assert!(bn.test_independence_from_samples("A", "B", &c, 20_000, &mut rng) > 0.005);
+    /// ```
+    pub fn test_independence_from_samples<R: Rng>(
+        &self,
+        x: &str,
+        y: &str,
+        z: &[String],
+        n: usize,
+        rng: &mut R,
+    ) -> f64 {
+        for var in [x, y].into_iter().chain(z.iter().map(|v| v.as_str())) {
+            if !self.states.contains_key(var) {
+                panic!("could not find variable {var}");
+            }
+        }
+        let order = self.topological_sort();
+        // counts of (x, y, z), (x, z), (y, z), and z
+        let mut xyz: HashMap<(usize, usize, Vec<usize>), usize> = HashMap::new();
+        let mut xz: HashMap<(usize, Vec<usize>), usize> = HashMap::new();
+        let mut yz: HashMap<(usize, Vec<usize>), usize> = HashMap::new();
+        let mut zs: HashMap<Vec<usize>, usize> = HashMap::new();
+        for _ in 0..n {
+            let sample = self.forward_sample(&order, rng);
+            let zv: Vec<usize> = z.iter().map(|v| sample[v.as_str()]).collect();
+            let (xv, yv) = (sample[x], sample[y]);
+            *xyz.entry((xv, yv, zv.clone())).or_default() += 1;
+            *xz.entry((xv, zv.clone())).or_default() += 1;
+            *yz.entry((yv, zv.clone())).or_default() += 1;
+            *zs.entry(zv).or_default() += 1;
+        }
+        // sum of p(x,y,z) log p(z) p(x,y,z) / (p(x,z) p(y,z)); the sample
+        // size cancels inside the logarithm
+        xyz.iter()
+            .map(|((xv, yv, zv), count)| {
+                let count = *count as f64;
+                let ratio = zs[zv] as f64 * count
+                    / (xz[&(*xv, zv.clone())] as f64 * yz[&(*yv, zv.clone())] as f64);
+                count / n as f64 * ratio.log2()
+            })
+            .sum()
+    }
+
+    /// Restricts the network to `vars`, keeping only the edges between them.
+    /// A variable that loses parents gets the CPT Pr(variable | remaining
+    /// parents) of the original network, with the removed parents (and
+    /// everything else) marginalized out by variable elimination; other
+    /// variables keep their CPTs. Variables keep their order in `variables`.
+    ///
+    /// When `vars` is closed under taking ancestors, the subnetwork's joint
+    /// distribution is exactly the original's marginal over `vars`;
+    /// otherwise each CPT is still exact, but dependencies that ran through
+    /// the removed variables may be lost.
+    ///
+    /// Returns an error if `vars` names an unknown variable or names one
+    /// twice, or if a new CPT would condition on remaining parent values that
+    /// have probability 0, so that its column is undefined.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BnError};
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetwork::from_json(NETWORK);
+    /// let sub = bn.subnetwork(&[String::from("C"), String::from("B")]).unwrap();
+    ///
+    /// assert_eq!(sub.variables(), &vec!["B", "C"]);
+    /// assert_eq!(sub.parents("C"), &vec!["B"]);
+    /// // Pr(C = T | B = T) = 0.5 * 0.2 + 0.5 * 0.6
+    /// let b_t = HashMap::from([(String::from("B"), String::from("T"))]);
+    /// assert!((sub.conditional_probability("C", "T", &b_t) - 0.4).abs() < 1e-9);
+    ///
+    /// // if B is never T, Pr(C | B = T) is undefined
+    /// let never = BayesianNetwork::from_json(&NETWORK.replace("[[0.25], [0.75]]", "[[1.0], [0.0]]"));
+    /// assert!(matches!(
+    ///     never.subnetwork(&[String::from("B"), String::from("C")]),
+    ///     Err(BnError::ImpossibleColumn(column)) if column == "C|B=T"
+    /// ));
+    /// ```
+    pub fn subnetwork(&self, vars: &[String]) -> Result<BayesianNetwork, BnError> {
+        for (i, var) in vars.iter().enumerate() {
+            if !self.states.contains_key(var) {
+                return Err(BnError::UnknownVariable(var.clone()));
+            }
+            if vars[..i].contains(var) {
+                return Err(BnError::VariableCollision(var.clone()));
+            }
+        }
+        let variables: Vec<String> = self
+            .variables
+            .iter()
+            .filter(|v| vars.contains(v))
+            .cloned()
+            .collect();
+        let mut sub = BayesianNetwork {
+            network: self.network.clone(),
+            variables: variables.clone(),
+            cpts: HashMap::new(),
+            states: variables
+                .iter()
+                .map(|v| (v.clone(), self.states[v].clone()))
+                .collect(),
+            metadata: self
+                .metadata
+                .iter()
+                .filter(|(v, _)| vars.contains(v))
+                .map(|(v, meta)| (v.clone(), meta.clone()))
+                .collect(),
+            topological_order: OnceLock::new(),
+            parents: variables
+                .iter()
+                .map(|v| {
+                    let parents = self.parents[v].iter().filter(|p| vars.contains(p));
+                    (v.clone(), parents.cloned().collect())
+                })
+                .collect(),
+        };
+        let engine = VariableEliminationEngine::new(self);
+        for var in variables.iter() {
+            if sub.parents[var].len() == self.parents[var].len() {
+                sub.cpts.insert(var.clone(), self.cpts[var].clone());
+                continue;
+            }
+            let num_columns: usize = sub.parents[var].iter().map(|p| sub.num_states(p)).product();
+            let mut cpt: Vec<Vec<f64>> = vec![Vec::with_capacity(num_columns); sub.num_states(var)];
+            for column in 0..num_columns {
+                let evidence: HashMap<String, String> =
+                    sub.column_assignment(var, column).into_iter().collect();
+                for (row, value) in cpt.iter_mut().zip(self.states[var].iter()) {
+                    let p = engine.conditional(var, value, &evidence);
+                    if p.is_nan() {
+                        return Err(BnError::ImpossibleColumn(sub.column_label(var, column)));
+                    }
+                    row.push(p);
+                }
+            }
+            sub.cpts.insert(var.clone(), cpt);
+        }
+        Ok(sub)
+    }
+
+    /// Combines this network with `other`, whose variables must all be new:
+    /// the result has this network's variables followed by `other`'s, each
+    /// with its states, parents, CPT, and metadata, and keeps this network's
+    /// name. The two parts are independent until they are connected with
+    /// `add_edge`.
+    ///
+    /// Returns an error naming the first variable of `other` that this
+    /// network already has.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BnError};
+    /// use std::collections::HashMap;
+    ///
+    /// let weather = BayesianNetwork::from_json(r#"{
+    ///     "network": "weather",
+    ///     "variables": ["Rain"],
+    ///     "cpts": { "Rain": [[0.8], [0.2]] },
+    ///     "states": { "Rain": ["F", "T"] },
+    ///     "parents": { "Rain": [] }
+    /// }"#);
+    /// let traffic = BayesianNetwork::from_json(r#"{
+    ///     "network": "traffic",
+    ///     "variables": ["Jam"],
+    ///     "cpts": { "Jam": [[0.7], [0.3]] },
+    ///     "states": { "Jam": ["F", "T"] },
+    ///     "parents": { "Jam": [] }
+    /// }"#);
+    ///
+    /// let mut city = weather.union(&traffic).unwrap();
+    /// assert_eq!(city.variables(), &vec!["Rain", "Jam"]);
+    ///
+    /// // rain makes jams likelier
+    /// city.add_edge("Rain", "Jam").unwrap();
+    /// let rain_t = HashMap::from([(String::from("Rain"), String::from("T"))]);
+    /// city.set_conditional_probability("Jam", "T", &rain_t, 0.6).unwrap();
+    /// city.set_conditional_probability("Jam", "F", &rain_t, 0.4).unwrap();
+    /// assert!(city.validate().is_ok());
+    ///
+    /// assert!(matches!(
+    ///     city.union(&traffic),
+    ///     Err(BnError::VariableCollision(v)) if v == "Jam"
+    /// ));
+    /// ```
+    pub fn union(&self, other: &BayesianNetwork) -> Result<BayesianNetwork, BnError> {
+        let mut combined = self.join(other, &[], HashMap::new())?;
+        combined.network = self.network.clone();
+        Ok(combined)
+    }
+
+    /// Shrinks the network to the variables needed to compute the posterior
+    /// of `query` given values for `evidence`: first every barren variable
+    /// (one that is neither an ancestor of nor in `query` or `evidence`) is
+    /// removed, and then every remaining variable d-separated from `query`
+    /// given `evidence`. The result is the `subnetwork` of what is left.
+    ///
+    /// Pr(query | evidence) is the same in the pruned network as in this one
+    /// for every value of the query and evidence variables, so compiling the
+    /// pruned network answers the query with a smaller CNF. Other
+    /// probabilities, such as Pr(evidence), may differ.
+    ///
+    /// Returns the same errors as `subnetwork`.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetwork::from_json(NETWORK);
+    /// let a = [String::from("A")];
+    ///
+    /// // C is barren, and then B is independent of A
+    /// assert_eq!(bn.prune_for_query(&a, &[]).unwrap().variables(), &vec!["A"]);
+    /// // observing C couples A and B
+    /// let c = [String::from("C")];
+    /// assert_eq!(bn.prune_for_query(&a, &c).unwrap().num_variables(), 3);
+    /// ```
+    pub fn prune_for_query(
+        &self,
+        query: &[String],
+        evidence: &[String],
+    ) -> Result<BayesianNetwork, BnError> {
+        let mut relevant: HashSet<String> = HashSet::new();
+        for var in query.iter().chain(evidence.iter()) {
+            if !self.states.contains_key(var) {
+                return Err(BnError::UnknownVariable(var.clone()));
+            }
+            relevant.extend(self.ancestors(var));
+            relevant.insert(var.clone());
+        }
+        let unobserved: Vec<String> = query
+            .iter()
+            .filter(|v| !evidence.contains(v))
+            .cloned()
+            .collect();
+        let keep: Vec<String> = self
+            .variables
+            .iter()
+            .filter(|v| relevant.contains(*v))
+            .filter(|v| {
+                query.contains(v)
+                    || evidence.contains(v)
+                    || !self.d_separated(&[(*v).clone()], &unobserved, evidence)
+            })
+            .cloned()
+            .collect();
+        self.subnetwork(&keep)
+    }
+
+    /// Bounds the treewidth of the network's moral graph from above by the
+    /// width of a greedy min-degree elimination order (ties broken by name);
+    /// exact inference is exponential in the treewidth
+    fn treewidth_upper_bound(&self) -> usize {
+        greedy_elimination(&self.moral_graph(), |neighbors, _| neighbors.len()).1
+    }
+
+    /// Suggests an elimination order for variable elimination with the
+    /// min-fill heuristic: repeatedly eliminate the variable of the moral
+    /// graph whose neighbours need the fewest new edges to become a clique
+    /// (ties broken by name), then connect its neighbours. Returns the order
+    /// and its induced width, the size of the largest clique it creates
+    /// minus one, which bounds the treewidth from above.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bayesian_network = BayesianNetwork::from_json(NETWORK);
+    /// let (order, width) = bayesian_network.min_fill_ordering();
+    ///
+    /// // marrying A and B makes the moral graph a triangle
+    /// assert_eq!(order, vec![String::from("A"), String::from("B"), String::from("C")]);
+    /// assert_eq!(width, 2);
+    /// ```
+    pub fn min_fill_ordering(&self) -> (Vec<String>, usize) {
+        min_fill_order(&self.moral_graph())
+    }
+
+    /// Draws the network's graph in Graphviz DOT: one node per variable,
+    /// labeled with its name and states, and an edge from each parent to
+    /// each child. Variables whose metadata has a position are pinned there
+    /// with `pos`, which `neato -n` honors. Nodes follow `variables` and
+    /// edges follow each child's parents in CPT order.
+    /// ```
+    /// use rsgm::{BayesianNetwork, VariableMeta};
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetwork::from_json(NETWORK);
+    /// assert_eq!(
+    ///     bn.to_dot(),
+    ///     r#"digraph "toy_network" {
+    ///     "A" [label="A\n{F, T}"];
+    ///     "B" [label="B\n{F, T}"];
+    ///     "C" [label="C\n{F, T}"];
+    ///     "A" -> "C";
+    ///     "B" -> "C";
+    /// }
+    /// "#
+    /// );
+    ///
+    /// let mut bn = bn;
+    /// let meta = VariableMeta {
+    ///     position: Some((120.0, 40.0)),
+    ///     ..Default::default()
+    /// };
+    /// bn.set_metadata("C", meta).unwrap();
+    /// assert!(bn.to_dot().contains(r#""C" [label="C\n{F, T}", pos="120,40!"];"#));
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let mut dot = format!("digraph {} {{\n", dot_quote(&self.network));
+        for variable in self.variables.iter() {
+            // the `\n` is a DOT line break, so only the parts are escaped
+            let label = format!(
+                "\"{}\\n{{{}}}\"",
+                dot_escape(variable),
+                dot_escape(&self.states[variable].join(", "))
+            );
+            let position = match self.metadata.get(variable).and_then(|m| m.position) {
+                Some((x, y)) => format!(", pos=\"{x},{y}!\""),
+                None => String::new(),
+            };
+            dot += &format!("    {} [label={label}{position}];\n", dot_quote(variable));
+        }
+        for variable in self.variables.iter() {
+            for parent in self.parents[variable].iter() {
+                dot += &format!("    {} -> {};\n", dot_quote(parent), dot_quote(variable));
+            }
+        }
+        dot += "}\n";
+        dot
+    }
+
+    /// Summarizes the whole network as text: its name, each variable with
+    /// its states, the edges, the roots and leaves, an upper bound on the
+    /// treewidth, and every CPT as CSV (see `cpt_to_csv`). The report lists
+    /// variables in the order of `variables`, so the same network always
+    /// gives the same report and two versions of a network diff cleanly.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// assert_eq!(BayesianNetwork::from_json(NETWORK).report(), "\
+    /// network: toy_network
+    ///
+    /// variables (3):
+    ///   A: 2 states (F, T)
+    ///   B: 2 states (F, T)
+    ///   C: 2 states (F, T)
+    ///
+    /// edges (2):
+    ///   A -> C
+    ///   B -> C
+    ///
+    /// roots: A, B
+    /// leaves: C
+    /// treewidth upper bound: 2
+    ///
+    /// CPT of A:
+    /// F,T
+    /// 0.5,0.5
+    ///
+    /// CPT of B:
+    /// F,T
+    /// 0.25,0.75
+    ///
+    /// CPT of C:
+    /// A,B,F,T
+    /// F,F,0.9,0.1
+    /// F,T,0.8,0.2
+    /// T,F,0.3,0.7
+    /// T,T,0.4,0.6
+    /// ");
+    /// ```
+    pub fn report(&self) -> String {
+        let mut report = format!("network: {}\n\n", self.network);
+        report += &format!("variables ({}):\n", self.variables.len());
+        for variable in self.variables.iter() {
+            let states = &self.states[variable];
+            report += &format!(
+                "  {variable}: {} states ({})\n",
+                states.len(),
+                states.join(", ")
+            );
+        }
+        let edges: Vec<(&String, &String)> = self
+            .variables
+            .iter()
+            .flat_map(|child| {
+                self.parents_in_cpt_order(child)
+                    .iter()
+                    .map(move |parent| (parent, child))
+            })
+            .collect();
+        report += &format!("\nedges ({}):\n", edges.len());
+        for (parent, child) in edges.iter() {
+            report += &format!("  {parent} -> {child}\n");
+        }
+        let roots: Vec<&str> = self
+            .variables
+            .iter()
+            .filter(|v| self.parents[*v].is_empty())
+            .map(|v| v.as_str())
+            .collect();
+        let leaves: Vec<&str> = self
+            .variables
+            .iter()
+            .filter(|v| self.children(v).is_empty())
+            .map(|v| v.as_str())
             .collect();
+        report += &format!("\nroots: {}\n", roots.join(", "));
+        report += &format!("leaves: {}\n", leaves.join(", "));
+        report += &format!("treewidth upper bound: {}\n", self.treewidth_upper_bound());
+        for variable in self.variables.iter() {
+            report += &format!("\nCPT of {variable}:\n{}", self.cpt_to_csv(variable));
+        }
+        report
+    }
+
+    /// Computes a hash of the network's content that is stable across
+    /// process runs, platforms, and Rust versions, e.g. to key a cache of
+    /// compiled artifacts (see `BayesianNetworkCNF::source_hash`).
+    ///
+    /// The hash covers each variable's states, parents (in CPT order), and
+    /// CPT entries rounded to `HASH_RESOLUTION`, visiting variables sorted
+    /// by name. It ignores the network's name and the order of `variables`,
+    /// so reformatting or reordering a file does not change it.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let hash = BayesianNetwork::from_json(NETWORK).content_hash();
+    /// let reordered = NETWORK.replace(r#"["A", "B", "C"]"#, r#"["C", "B", "A"]"#);
+    /// let edited = NETWORK.replace("0.25", "0.3").replace("0.75", "0.7");
+    ///
+    /// assert_eq!(BayesianNetwork::from_json(&reordered).content_hash(), hash);
+    /// assert_ne!(BayesianNetwork::from_json(&edited).content_hash(), hash);
+    /// ```
+    pub fn content_hash(&self) -> u64 {
+        let mut variables: Vec<&String> = self.variables.iter().collect();
+        variables.sort();
+        let mut hasher = Fnv1a::new();
+        hasher.write_u64(variables.len() as u64);
+        for variable in variables {
+            self.hash_variable(variable, &mut hasher);
+        }
+        hasher.0
+    }
 
-        while !cur_vars.is_empty() {
-            // find a variable with no parents, remove it, add it to the result
-            // list, and remove it as a parent from all other nodes
-            let topvar: String = {
-                let (topvar, _) = cur_vars
-                    .iter()
-                    .find(|(_, value)| value.is_empty())
-                    .unwrap_or_else(|| panic!("graph not topologically sortable"));
-                topvar.clone()
-            };
-            result.push(topvar.clone());
-            cur_vars.remove(&topvar);
-            for (_, value) in cur_vars.iter_mut() {
-                value.retain(|v| *v != topvar);
+    /// Computes a hash of everything the encoding of `variable` in a CNF
+    /// depends on: its own part of `content_hash`, plus the states of each
+    /// of its parents
+    pub(crate) fn local_hash(&self, variable: &str) -> u64 {
+        let mut hasher = Fnv1a::new();
+        self.hash_variable(variable, &mut hasher);
+        for parent in self.parents_in_cpt_order(variable) {
+            let states = &self.states[parent];
+            hasher.write_u64(states.len() as u64);
+            for state in states.iter() {
+                hasher.write_str(state);
+            }
+        }
+        hasher.0
+    }
+
+    /// feeds `variable`'s states, parents, and CPT to `hasher`
+    fn hash_variable(&self, variable: &str, hasher: &mut Fnv1a) {
+        hasher.write_str(variable);
+        let states = &self.states[variable];
+        hasher.write_u64(states.len() as u64);
+        for state in states.iter() {
+            hasher.write_str(state);
+        }
+        let parents = self.parents_in_cpt_order(variable);
+        hasher.write_u64(parents.len() as u64);
+        for parent in parents.iter() {
+            hasher.write_str(parent);
+        }
+        let cpt = &self.cpts[variable];
+        hasher.write_u64(cpt.len() as u64);
+        for row in cpt.iter() {
+            hasher.write_u64(row.len() as u64);
+            for p in row.iter() {
+                hasher.write_u64((p / HASH_RESOLUTION).round() as i64 as u64);
             }
         }
-        result
     }
 }
 
+/// Eliminates every variable of the moral graph `moral`, each time choosing
+/// the one whose neighbours minimize `cost` (ties broken by name) and
+/// connecting its neighbours; returns the elimination order and its induced
+/// width
+fn greedy_elimination(
+    moral: &HashMap<String, HashSet<String>>,
+    cost: impl Fn(&BTreeSet<&str>, &BTreeMap<&str, BTreeSet<&str>>) -> usize,
+) -> (Vec<String>, usize) {
+    let mut graph: BTreeMap<&str, BTreeSet<&str>> = moral
+        .iter()
+        .map(|(v, n)| (v.as_str(), n.iter().map(|x| x.as_str()).collect()))
+        .collect();
+    let mut order: Vec<String> = Vec::new();
+    let mut width = 0;
+    while let Some(next) = graph
+        .iter()
+        .min_by_key(|(_, n)| cost(n, &graph))
+        .map(|(v, _)| *v)
+    {
+        let clique = graph.remove(next).unwrap();
+        width = width.max(clique.len());
+        for a in clique.iter() {
+            let n = graph.get_mut(a).unwrap();
+            n.remove(next);
+            n.extend(clique.iter().filter(|b| *b != a));
+        }
+        order.push(next.to_string());
+    }
+    (order, width)
+}
+
+/// `BayesianNetwork::min_fill_ordering` of the moral graph `moral`
+pub(crate) fn min_fill_order(moral: &HashMap<String, HashSet<String>>) -> (Vec<String>, usize) {
+    greedy_elimination(moral, |neighbors, graph| {
+        neighbors
+            .iter()
+            .map(|a| {
+                neighbors
+                    .range(..*a)
+                    .filter(|b| !graph[a].contains(*b))
+                    .count()
+            })
+            .sum()
+    })
+}
+
+/// escapes embedded backslashes and quotes for use inside a DOT string
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// quotes `s` as a DOT identifier, escaping embedded backslashes and quotes
+fn dot_quote(s: &str) -> String {
+    format!("\"{}\"", dot_escape(s))
+}
+
 #[test]
 fn test_conditional() {
     let sachs = include_str!("../bayesian_networks/sachs.json");
@@ -471,3 +4430,453 @@ fn test_public_api_e2e() {
     assert_eq!(bayesian_network.topological_sort()[1], "B");
     assert_eq!(bayesian_network.topological_sort()[2], "C");
 }
+
+#[test]
+fn test_repair() {
+    static NETWORK: &str = r#"{
+        "network": "toy_network",
+        "variables": ["A", "B", "C"],
+        "cpts": {
+            "A": [[1.5], [0.5]],
+            "B": [[0.0], [0.0]],
+            "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+        },
+        "states": {
+            "A": ["F", "T"],
+            "B": ["F", "T"],
+            "C": ["F", "T"]
+        },
+        "parents" :{
+            "A": [],
+            "B": [],
+            "C": ["A", "B"]
+        }
+    }"#;
+
+    let mut warnings = Warnings::new();
+    let bn = BayesianNetwork::from_json_with_warnings(NETWORK, &mut warnings).unwrap();
+    let warnings: Vec<Warning> = warnings.into_iter().collect();
+    assert_eq!(
+        warnings,
+        vec![
+            Warning::ProbabilityClamped {
+                column: String::from("A"),
+                state: String::from("F"),
+                value: 1.5
+            },
+            Warning::ColumnRescaled {
+                column: String::from("A"),
+                sum: 1.5
+            },
+            Warning::ZeroColumn {
+                column: String::from("B")
+            },
+        ]
+    );
+    assert!((bn.conditional_probability("A", "F", &HashMap::new()) - 2.0 / 3.0).abs() < 1e-9);
+    assert_eq!(bn.conditional_probability("B", "T", &HashMap::new()), 0.0);
+
+    // NaN is neither clamped nor summed, but zeroed before rescaling
+    let mut bn = BayesianNetwork::from_json(NETWORK);
+    bn.cpts.get_mut("C").unwrap()[0][1] = f64::NAN;
+    let mut warnings = Warnings::new();
+    bn.repair(&mut warnings);
+    assert!(warnings.iter().any(|w| *w
+        == Warning::NanProbability {
+            column: String::from("C|A=F,B=T"),
+            state: String::from("F")
+        }));
+    assert_eq!(bn.cpts["C"][0][1], 0.0);
+    assert_eq!(bn.cpts["C"][1][1], 1.0);
+
+    assert!(matches!(
+        BayesianNetwork::from_json_with_warnings("{", &mut Warnings::new()),
+        Err(BnError::Json(_))
+    ));
+}
+
+#[test]
+fn test_validate() {
+    // C has a missing row and names an unknown parent D; B has no states
+    static NETWORK: &str = r#"{
+        "network": "toy_network",
+        "variables": ["A", "B", "C"],
+        "cpts": {
+            "A": [[0.5], [0.5]],
+            "B": [[0.25], [0.75]],
+            "C": [[0.9, 0.3]]
+        },
+        "states": {
+            "A": ["F", "T"],
+            "C": ["F", "T"]
+        },
+        "parents" :{
+            "A": [],
+            "B": [],
+            "C": ["A", "D"]
+        }
+    }"#;
+
+    let bn = BayesianNetwork::from_json(NETWORK);
+    assert_eq!(
+        bn.validate(),
+        Err(vec![
+            ValidationError::MissingEntry {
+                variable: String::from("B"),
+                field: "states"
+            },
+            ValidationError::UnknownParent {
+                variable: String::from("C"),
+                parent: String::from("D")
+            },
+            ValidationError::RowCount {
+                variable: String::from("C"),
+                expected: 2,
+                actual: 1
+            },
+        ])
+    );
+    assert!(matches!(
+        BayesianNetwork::from_json_with_warnings(NETWORK, &mut Warnings::new()),
+        Err(BnError::Invalid(errors)) if errors.len() == 3
+    ));
+
+    // a variable with a single state is fine, but one with none is not
+    static CONSTANTS: &str = r#"{
+        "network": "constants",
+        "variables": ["K", "Z"],
+        "cpts": {
+            "K": [[1.0]],
+            "Z": []
+        },
+        "states": {
+            "K": ["on"],
+            "Z": []
+        },
+        "parents" :{
+            "K": [],
+            "Z": []
+        }
+    }"#;
+    assert_eq!(
+        BayesianNetwork::from_json(CONSTANTS).validate(),
+        Err(vec![ValidationError::NoStates {
+            variable: String::from("Z")
+        }])
+    );
+}
+
+#[test]
+fn test_ancestors_descendants() {
+    let sachs = include_str!("../bayesian_networks/sachs.json");
+    let network = BayesianNetwork::from_json(sachs);
+    let set = |vars: &[&str]| -> HashSet<String> { vars.iter().map(|v| v.to_string()).collect() };
+
+    // PKC is a root
+    assert!(network.ancestors("PKC").is_empty());
+    assert_eq!(
+        network.descendants("PKC"),
+        set(&["Akt", "Erk", "Jnk", "Mek", "P38", "PKA", "Raf"])
+    );
+    assert_eq!(
+        network.ancestors("Akt"),
+        set(&["Erk", "Mek", "PKA", "PKC", "Raf"])
+    );
+    assert!(network.descendants("Akt").is_empty());
+    assert_eq!(network.ancestors("PIP2"), set(&["PIP3", "Plcg"]));
+    assert_eq!(network.descendants("Plcg"), set(&["PIP2", "PIP3"]));
+}
+
+#[test]
+fn test_markov_blanket() {
+    // the collider A, B -> C, plus A -> B so A is both parent and co-parent
+    // of B's child
+    static NETWORK: &str = r#"{
+        "network": "toy_network",
+        "variables": ["A", "B", "C"],
+        "cpts": {
+            "A": [[0.5], [0.5]],
+            "B": [[0.25, 0.5], [0.75, 0.5]],
+            "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+        },
+        "states": {
+            "A": ["F", "T"],
+            "B": ["F", "T"],
+            "C": ["F", "T"]
+        },
+        "parents" :{
+            "A": [],
+            "B": ["A"],
+            "C": ["A", "B"]
+        }
+    }"#;
+    let set = |vars: &[&str]| -> HashSet<String> { vars.iter().map(|v| v.to_string()).collect() };
+
+    let collider = BayesianNetwork::from_json(
+        &NETWORK
+            .replace(r#""B": ["A"]"#, r#""B": []"#)
+            .replace("[[0.25, 0.5], [0.75, 0.5]]", "[[0.25], [0.75]]"),
+    );
+    assert_eq!(collider.markov_blanket("A"), set(&["B", "C"]));
+    assert_eq!(collider.markov_blanket("C"), set(&["A", "B"]));
+
+    let network = BayesianNetwork::from_json(NETWORK);
+    assert_eq!(network.markov_blanket("B"), set(&["A", "C"]));
+    assert_eq!(network.markov_blanket("A"), set(&["B", "C"]));
+
+    let sachs = BayesianNetwork::from_json(include_str!("../bayesian_networks/sachs.json"));
+    for bn in [&collider, &network, &sachs] {
+        let moral = bn.moral_graph();
+        for variable in bn.variables().iter() {
+            assert_eq!(moral[variable], bn.markov_blanket(variable));
+        }
+    }
+}
+
+#[test]
+fn test_d_separated() {
+    // the collider A, B -> C, with C -> D and a fork D <- E -> F
+    static NETWORK: &str = r#"{
+        "network": "toy_network",
+        "variables": ["A", "B", "C", "D", "E", "F"],
+        "cpts": {
+            "A": [[0.5], [0.5]],
+            "B": [[0.25], [0.75]],
+            "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]],
+            "D": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]],
+            "E": [[0.5], [0.5]],
+            "F": [[0.9, 0.2], [0.1, 0.8]]
+        },
+        "states": {
+            "A": ["F", "T"],
+            "B": ["F", "T"],
+            "C": ["F", "T"],
+            "D": ["F", "T"],
+            "E": ["F", "T"],
+            "F": ["F", "T"]
+        },
+        "parents" :{
+            "A": [],
+            "B": [],
+            "C": ["A", "B"],
+            "D": ["C", "E"],
+            "E": [],
+            "F": ["E"]
+        }
+    }"#;
+    let bn = BayesianNetwork::from_json(NETWORK);
+    let vars = |vars: &[&str]| -> Vec<String> { vars.iter().map(|v| v.to_string()).collect() };
+
+    assert!(bn.d_separated(&vars(&["A"]), &vars(&["B"]), &[]));
+    // observing a descendant of the collider activates it
+    assert!(!bn.d_separated(&vars(&["A"]), &vars(&["B"]), &vars(&["D"])));
+    assert!(!bn.d_separated(&vars(&["A"]), &vars(&["B"]), &vars(&["C", "E"])));
+    // the chain A -> C -> D is blocked by observing C
+    assert!(!bn.d_separated(&vars(&["A"]), &vars(&["D"]), &[]));
+    assert!(bn.d_separated(&vars(&["A"]), &vars(&["D"]), &vars(&["C"])));
+    // D is a collider between C and E, and E a fork between D and F
+    assert!(bn.d_separated(&vars(&["A", "B"]), &vars(&["F"]), &[]));
+    assert!(!bn.d_separated(&vars(&["A", "B"]), &vars(&["F"]), &vars(&["D"])));
+    assert!(bn.d_separated(&vars(&["A", "B"]), &vars(&["F"]), &vars(&["D", "E"])));
+    assert!(bn.d_separated(&[], &vars(&["F"]), &[]));
+}
+
+#[test]
+fn test_cyclic_network_is_rejected_on_load() {
+    static NETWORK: &str = r#"{
+        "network": "cyclic",
+        "variables": ["A", "B"],
+        "cpts": {
+            "A": [[0.5, 0.5], [0.5, 0.5]],
+            "B": [[0.9, 0.2], [0.1, 0.8]]
+        },
+        "states": {
+            "A": ["F", "T"],
+            "B": ["F", "T"]
+        },
+        "parents" :{
+            "A": ["B"],
+            "B": ["A"]
+        }
+    }"#;
+
+    assert!(matches!(
+        BayesianNetwork::from_json_with_warnings(NETWORK, &mut Warnings::new()),
+        Err(BnError::Cycle(vars)) if vars == vec!["A", "B"]
+    ));
+}
+
+#[test]
+fn test_marginal_by_enumeration_agrees_with_wmc() {
+    use crate::BayesianNetworkCNF;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    static COLLIDER: &str = r#"{
+        "network": "toy_network",
+        "variables": ["A", "B", "C"],
+        "cpts": {
+            "A": [[0.5], [0.5]],
+            "B": [[0.25], [0.75]],
+            "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+        },
+        "states": {
+            "A": ["F", "T"],
+            "B": ["F", "T"],
+            "C": ["F", "T"]
+        },
+        "parents" :{
+            "A": [],
+            "B": [],
+            "C": ["A", "B"]
+        }
+    }"#;
+
+    let networks = [
+        BayesianNetwork::from_json(COLLIDER),
+        BayesianNetwork::from_bif(include_str!("../bayesian_networks/asia.bif")).unwrap(),
+        BayesianNetwork::random(6, 2, 3, &mut StdRng::seed_from_u64(0)),
+    ];
+    for bn in networks.iter() {
+        let cnf = BayesianNetworkCNF::from_bayesian_network(bn);
+        let last = bn.variables().last().unwrap();
+        let observed = HashMap::from([(last.clone(), bn.states[last][0].clone())]);
+        for evidence in [HashMap::new(), observed] {
+            for var in bn.variables().iter() {
+                for value in bn.states[var].iter() {
+                    let expected = cnf.posterior(&(var.clone(), value.clone()), &evidence);
+                    let p = bn.marginal_by_enumeration(var, value, &evidence);
+                    assert!(
+                        (p - expected).abs() < 1e-9,
+                        "{var} = {value}: {p} vs {expected}"
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_min_fill_ordering() {
+    // the tree A -> B, A -> C, B -> D, B -> E
+    static NETWORK: &str = r#"{
+        "network": "tree",
+        "variables": ["A", "B", "C", "D", "E"],
+        "cpts": {
+            "A": [[0.5], [0.5]],
+            "B": [[0.2, 0.7], [0.8, 0.3]],
+            "C": [[0.9, 0.4], [0.1, 0.6]],
+            "D": [[0.3, 0.6], [0.7, 0.4]],
+            "E": [[0.5, 0.1], [0.5, 0.9]]
+        },
+        "states": {
+            "A": ["F", "T"],
+            "B": ["F", "T"],
+            "C": ["F", "T"],
+            "D": ["F", "T"],
+            "E": ["F", "T"]
+        },
+        "parents" :{
+            "A": [],
+            "B": ["A"],
+            "C": ["A"],
+            "D": ["B"],
+            "E": ["B"]
+        }
+    }"#;
+
+    let bn = BayesianNetwork::from_json(NETWORK);
+    let (order, width) = bn.min_fill_ordering();
+    assert_eq!(width, 1);
+    let mut sorted = order.clone();
+    sorted.sort();
+    assert_eq!(sorted, bn.variables);
+    // no leaf creates fill, so the first leaf by name goes first
+    assert_eq!(order[0], "C");
+}
+
+#[test]
+fn test_fit_cpts_mle_recovers_collider() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    static NETWORK: &str = r#"{
+        "network": "toy_network",
+        "variables": ["A", "B", "C"],
+        "cpts": {
+            "A": [[0.5], [0.5]],
+            "B": [[0.25], [0.75]],
+            "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+        },
+        "states": {
+            "A": ["F", "T"],
+            "B": ["F", "T"],
+            "C": ["F", "T"]
+        },
+        "parents" :{
+            "A": [],
+            "B": [],
+            "C": ["A", "B"]
+        }
+    }"#;
+
+    let bn = BayesianNetwork::from_json(NETWORK);
+    let data = bn.sample_n(20_000, &mut StdRng::seed_from_u64(0));
+    let fitted = BayesianNetwork::fit_cpts_mle(&bn, &data, 1.0).unwrap();
+    assert!(fitted.approx_eq(&bn, 0.02));
+    assert!(fitted.check_normalized(1e-9).is_ok());
+    // unsmoothed, the estimates maximize the likelihood of the data
+    let mle = BayesianNetwork::fit_cpts_mle(&bn, &data, 0.0).unwrap();
+    assert!(mle.log_likelihood(&data).unwrap() >= bn.log_likelihood(&data).unwrap());
+}
+
+#[test]
+fn test_gibbs_posterior_converges() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let bn = BayesianNetwork::random(6, 2, 2, &mut StdRng::seed_from_u64(3));
+    let last = bn.variables().last().unwrap().clone();
+    let evidence = HashMap::from([(last, String::from("s1"))]);
+    let mut rng = StdRng::seed_from_u64(0);
+    for var in bn.variables()[..5].iter() {
+        let exact = bn.marginal_by_enumeration(var, "s0", &evidence);
+        let query = (var.clone(), String::from("s0"));
+        let p = bn.gibbs_posterior(&query, &evidence, 500, 20_000, &mut rng);
+        assert!((p - exact).abs() < 0.03, "{var}: {p} vs {exact}");
+    }
+}
+
+#[test]
+fn test_prune_for_query() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let bn = BayesianNetwork::random(10, 2, 2, &mut StdRng::seed_from_u64(1));
+    let engine = VariableEliminationEngine::new(&bn);
+    let var = |i: usize| bn.variables()[i].clone();
+    for (query, evidence) in [
+        (var(4), vec![]),
+        (var(2), vec![var(7)]),
+        (var(6), vec![var(1), var(9)]),
+        (var(9), vec![var(3), var(5)]),
+    ] {
+        let pruned = bn.prune_for_query(&[query.clone()], &evidence).unwrap();
+        assert!(pruned.num_variables() < bn.num_variables());
+        let pruned_engine = VariableEliminationEngine::new(&pruned);
+        for values in bn.assignments_h(evidence.clone()) {
+            for value in bn.states[&query].iter() {
+                let expected = engine.conditional(&query, value, &values);
+                let p = pruned_engine.conditional(&query, value, &values);
+                assert!(
+                    (p - expected).abs() < 1e-9,
+                    "{query} = {value} | {values:?}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_dot_quote() {
+    assert_eq!(dot_quote("a"), r#""a""#);
+    assert_eq!(dot_quote(r#"say "hi""#), r#""say \"hi\"""#);
+    assert_eq!(dot_quote(r"a\"), r#""a\\""#);
+    assert_eq!(dot_quote(r#"a\""#), r#""a\\\"""#);
+}
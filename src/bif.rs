@@ -0,0 +1,552 @@
+//! Import and export of Bayesian Interchange Format (`.bif`) networks
+//!
+//! Understands the discrete subset of BIF used by the standard benchmark
+//! networks: a `network` block, `variable` blocks with a `type discrete`
+//! declaration, and `probability` blocks given as a `table`, as one row per
+//! parent assignment, or as rows with a `default`. `property` statements and
+//! comments are skipped.
+
+use std::{collections::HashMap, sync::OnceLock};
+
+use crate::{BayesianNetwork, BnError};
+
+fn syntax_error(message: impl Into<String>) -> BnError {
+    BnError::Syntax {
+        format: "BIF",
+        message: message.into(),
+    }
+}
+
+/// Splits BIF source into words and the punctuation `{}()[];,|`, dropping
+/// comments and the quotes around quoted words. Inside quotes, `\"` and `\\`
+/// stand for `"` and `\`.
+fn tokenize(text: &str) -> Result<Vec<String>, BnError> {
+    let mut tokens: Vec<String> = Vec::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if rest.starts_with("//") {
+            rest = rest.find('\n').map_or("", |end| &rest[end..]);
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            let end = comment
+                .find("*/")
+                .ok_or_else(|| syntax_error("unterminated comment"))?;
+            rest = &comment[end + 2..];
+        } else if let Some(quoted) = rest.strip_prefix('"') {
+            let mut word = String::new();
+            let mut chars = quoted.char_indices().peekable();
+            let end = loop {
+                match chars.next() {
+                    None => return Err(syntax_error("unterminated string")),
+                    Some((i, '"')) => break i,
+                    Some((_, '\\')) => match chars.peek() {
+                        Some((_, escaped @ ('"' | '\\'))) => {
+                            word.push(*escaped);
+                            chars.next();
+                        }
+                        _ => word.push('\\'),
+                    },
+                    Some((_, c)) => word.push(c),
+                }
+            };
+            tokens.push(word);
+            rest = &quoted[end + 1..];
+        } else if "{}()[];,|".contains(c) {
+            tokens.push(c.to_string());
+            rest = &rest[1..];
+        } else {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || "{}()[];,|\"".contains(c))
+                .unwrap_or(rest.len());
+            tokens.push(rest[..end].to_string());
+            rest = &rest[end..];
+        }
+    }
+    Ok(tokens)
+}
+
+struct Tokens {
+    tokens: Vec<String>,
+    pos: usize,
+}
+
+impl Tokens {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|t| t.as_str())
+    }
+
+    fn next(&mut self) -> Result<String, BnError> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| syntax_error("unexpected end of input"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), BnError> {
+        let token = self.next()?;
+        if token != expected {
+            return Err(syntax_error(format!("expected {expected}, found {token}")));
+        }
+        Ok(())
+    }
+
+    /// skips past the next `;`
+    fn skip_statement(&mut self) -> Result<(), BnError> {
+        while self.next()? != ";" {}
+        Ok(())
+    }
+
+    /// reads words separated by `,` up to and including `end`
+    fn list(&mut self, end: &str) -> Result<Vec<String>, BnError> {
+        let mut items: Vec<String> = Vec::new();
+        loop {
+            let token = self.next()?;
+            if token == end {
+                return Ok(items);
+            }
+            if token != "," {
+                items.push(token);
+            }
+        }
+    }
+
+    /// reads numbers separated by `,` up to and including `;`
+    fn numbers(&mut self, variable: &str) -> Result<Vec<f64>, BnError> {
+        self.list(";")?
+            .iter()
+            .map(|p| {
+                p.parse::<f64>().map_err(|_| {
+                    syntax_error(format!("{variable} has non-numeric probability {p}"))
+                })
+            })
+            .collect()
+    }
+}
+
+/// one `probability` block, before it is laid out as a CPT
+#[derive(Default)]
+struct ProbabilityBlock {
+    parents: Vec<String>,
+    table: Option<Vec<f64>>,
+    default: Option<Vec<f64>>,
+    rows: Vec<(Vec<String>, Vec<f64>)>,
+}
+
+impl BayesianNetwork {
+    /// Reads a network in the Bayesian Interchange Format (`.bif`).
+    ///
+    /// A row `(a, b) p1, p2;` of a `probability ( X | A, B )` block gives
+    /// Pr(X | A = a, B = b) for each state of X, and becomes the CPT column
+    /// for that assignment, with the parents in the order the block lists
+    /// them (see `parents_in_cpt_order`). A `table` lists the whole CPT with
+    /// X varying slowest and the last parent fastest, i.e. row by row.
+    ///
+    /// Fails if the input is not well-formed BIF, a variable has no
+    /// `probability` block or an incomplete one, a name or state is unknown
+    /// or repeated, or the graph has a cycle.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"
+    /// network toy_network {
+    /// }
+    /// variable A {
+    ///   type discrete [ 2 ] { F, T };
+    /// }
+    /// variable B {
+    ///   type discrete [ 2 ] { F, T };
+    /// }
+    /// variable C {
+    ///   type discrete [ 2 ] { F, T };
+    /// }
+    /// probability ( A ) {
+    ///   table 0.5, 0.5;
+    /// }
+    /// probability ( B ) {
+    ///   table 0.25, 0.75;
+    /// }
+    /// probability ( C | A, B ) {
+    ///   (F, F) 0.9, 0.1;
+    ///   (F, T) 0.8, 0.2;
+    ///   (T, F) 0.3, 0.7;
+    ///   (T, T) 0.4, 0.6;
+    /// }
+    /// "#;
+    ///
+    /// let bn = BayesianNetwork::from_bif(NETWORK).unwrap();
+    ///
+    /// assert_eq!(bn.name(), "toy_network");
+    /// assert_eq!(bn.variables(), &vec!["A", "B", "C"]);
+    /// assert_eq!(bn.conditional_probability("C", "T", &HashMap::from([
+    ///     (String::from("A"), String::from("T")),
+    ///     (String::from("B"), String::from("F"))
+    /// ])), 0.7);
+    /// ```
+    pub fn from_bif(text: &str) -> Result<BayesianNetwork, BnError> {
+        let mut tokens = Tokens {
+            tokens: tokenize(text)?,
+            pos: 0,
+        };
+        let mut bn = BayesianNetwork {
+            network: String::new(),
+            variables: Vec::new(),
+            cpts: HashMap::new(),
+            states: HashMap::new(),
+            parents: HashMap::new(),
+            metadata: HashMap::new(),
+            topological_order: OnceLock::new(),
+        };
+        let mut blocks: HashMap<String, ProbabilityBlock> = HashMap::new();
+        while let Some(keyword) = tokens.peek() {
+            match keyword {
+                "network" => {
+                    tokens.next()?;
+                    bn.network = tokens.next()?;
+                    tokens.expect("{")?;
+                    while tokens.peek() != Some("}") {
+                        tokens.skip_statement()?;
+                    }
+                    tokens.expect("}")?;
+                }
+                "variable" => {
+                    tokens.next()?;
+                    let name = tokens.next()?;
+                    if bn.states.contains_key(&name) {
+                        return Err(BnError::VariableCollision(name));
+                    }
+                    tokens.expect("{")?;
+                    let mut states: Option<Vec<String>> = None;
+                    while tokens.peek() != Some("}") {
+                        if tokens.peek() != Some("type") {
+                            tokens.skip_statement()?;
+                            continue;
+                        }
+                        tokens.next()?;
+                        tokens.expect("discrete")?;
+                        tokens.expect("[")?;
+                        let count = tokens.next()?;
+                        tokens.expect("]")?;
+                        tokens.expect("{")?;
+                        let values = tokens.list("}")?;
+                        tokens.expect(";")?;
+                        if count.parse::<usize>().ok() != Some(values.len()) {
+                            return Err(syntax_error(format!(
+                                "{name} declares {count} states but lists {}",
+                                values.len()
+                            )));
+                        }
+                        for (i, value) in values.iter().enumerate() {
+                            if values[..i].contains(value) {
+                                return Err(BnError::DuplicateState(name, value.clone()));
+                            }
+                        }
+                        states = Some(values);
+                    }
+                    tokens.expect("}")?;
+                    let states = states
+                        .ok_or_else(|| syntax_error(format!("{name} has no discrete type")))?;
+                    bn.variables.push(name.clone());
+                    bn.states.insert(name.clone(), states);
+                }
+                "probability" => {
+                    tokens.next()?;
+                    tokens.expect("(")?;
+                    let variable = tokens.next()?;
+                    let mut block = ProbabilityBlock::default();
+                    if tokens.peek() == Some("|") {
+                        tokens.next()?;
+                        block.parents = tokens.list(")")?;
+                    } else {
+                        tokens.expect(")")?;
+                    }
+                    tokens.expect("{")?;
+                    while let Some(entry) = tokens.peek() {
+                        match entry {
+                            "}" => break,
+                            "table" => {
+                                tokens.next()?;
+                                block.table = Some(tokens.numbers(&variable)?);
+                            }
+                            "default" => {
+                                tokens.next()?;
+                                block.default = Some(tokens.numbers(&variable)?);
+                            }
+                            "(" => {
+                                tokens.next()?;
+                                let values = tokens.list(")")?;
+                                block.rows.push((values, tokens.numbers(&variable)?));
+                            }
+                            _ => tokens.skip_statement()?,
+                        }
+                    }
+                    tokens.expect("}")?;
+                    if blocks.insert(variable.clone(), block).is_some() {
+                        return Err(BnError::VariableCollision(variable));
+                    }
+                }
+                _ => return Err(syntax_error(format!("unexpected {keyword}"))),
+            }
+        }
+
+        if let Some(unknown) = blocks.keys().find(|v| !bn.states.contains_key(*v)) {
+            return Err(BnError::UnknownVariable(unknown.clone()));
+        }
+        for variable in bn.variables.iter() {
+            let block = blocks
+                .remove(variable)
+                .ok_or_else(|| BnError::MissingCpt(variable.clone()))?;
+            if let Some(parent) = block.parents.iter().find(|p| !bn.states.contains_key(*p)) {
+                return Err(BnError::UnknownVariable(parent.clone()));
+            }
+            let rows = bn.num_states(variable);
+            let columns: usize = block.parents.iter().map(|p| bn.num_states(p)).product();
+            let shape_error = || BnError::CptShape {
+                variable: variable.clone(),
+                expected: (rows, columns),
+            };
+
+            let mut cpt_columns: Vec<Option<Vec<f64>>> = vec![None; columns];
+            if let Some(table) = block.table {
+                if table.len() != rows * columns {
+                    return Err(shape_error());
+                }
+                for (c, column) in cpt_columns.iter_mut().enumerate() {
+                    *column = Some((0..rows).map(|s| table[s * columns + c]).collect());
+                }
+            }
+            for (values, probabilities) in block.rows {
+                if values.len() != block.parents.len() {
+                    return Err(syntax_error(format!(
+                        "row ({}) of {variable} does not assign every parent",
+                        values.join(", ")
+                    )));
+                }
+                if probabilities.len() != rows {
+                    return Err(shape_error());
+                }
+                let mut c = 0;
+                for (parent, value) in block.parents.iter().zip(values) {
+                    let index = bn.states[parent]
+                        .iter()
+                        .position(|s| *s == value)
+                        .ok_or_else(|| BnError::UnknownState(parent.clone(), value))?;
+                    c = c * bn.num_states(parent) + index;
+                }
+                cpt_columns[c] = Some(probabilities);
+            }
+            if let Some(default) = block.default {
+                if default.len() != rows {
+                    return Err(shape_error());
+                }
+                for column in cpt_columns.iter_mut().filter(|c| c.is_none()) {
+                    *column = Some(default.clone());
+                }
+            }
+            let cpt_columns = cpt_columns
+                .into_iter()
+                .collect::<Option<Vec<Vec<f64>>>>()
+                .ok_or_else(shape_error)?;
+
+            let cpt = (0..rows)
+                .map(|s| cpt_columns.iter().map(|column| column[s]).collect())
+                .collect();
+            bn.cpts.insert(variable.clone(), cpt);
+            bn.parents.insert(variable.clone(), block.parents);
+        }
+        bn.try_topological_sort()?;
+        Ok(bn)
+    }
+
+    /// Writes the network in the Bayesian Interchange Format, the inverse of
+    /// `from_bif`: each CPT becomes one row per parent assignment, in column
+    /// order, so that reading the output back gives the same network.
+    /// Probabilities are written with the fewest digits that read back
+    /// exactly.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bif = BayesianNetwork::from_json(NETWORK).to_bif();
+    /// assert!(bif.starts_with("network toy_network {\n}\nvariable A {\n"));
+    /// assert!(bif.ends_with(
+    ///     "probability ( C | A, B ) {
+    ///   (F, F) 0.9, 0.1;
+    ///   (F, T) 0.8, 0.2;
+    ///   (T, F) 0.3, 0.7;
+    ///   (T, T) 0.4, 0.6;
+    /// }
+    /// "
+    /// ));
+    /// assert_eq!(BayesianNetwork::from_bif(&bif).unwrap().to_bif(), bif);
+    /// ```
+    pub fn to_bif(&self) -> String {
+        let mut bif = format!("network {} {{\n}}\n", bif_word(&self.network));
+        for variable in self.variables.iter() {
+            let states: Vec<String> = self.states[variable].iter().map(|s| bif_word(s)).collect();
+            bif += &format!(
+                "variable {} {{\n  type discrete [ {} ] {{ {} }};\n}}\n",
+                bif_word(variable),
+                states.len(),
+                states.join(", ")
+            );
+        }
+        for variable in self.variables.iter() {
+            let parents = self.parents_in_cpt_order(variable);
+            let cpt = &self.cpts[variable];
+            let column_values = |column: usize| -> String {
+                let values: Vec<String> = cpt.iter().map(|row| row[column].to_string()).collect();
+                values.join(", ")
+            };
+            if parents.is_empty() {
+                bif += &format!(
+                    "probability ( {} ) {{\n  table {};\n}}\n",
+                    bif_word(variable),
+                    column_values(0)
+                );
+                continue;
+            }
+            let parents_list: Vec<String> = parents.iter().map(|p| bif_word(p)).collect();
+            bif += &format!(
+                "probability ( {} | {} ) {{\n",
+                bif_word(variable),
+                parents_list.join(", ")
+            );
+            let num_columns = cpt.first().map_or(0, |row| row.len());
+            for column in 0..num_columns {
+                let assignment: Vec<String> = self
+                    .column_assignment(variable, column)
+                    .iter()
+                    .map(|(_, value)| bif_word(value))
+                    .collect();
+                bif += &format!("  ({}) {};\n", assignment.join(", "), column_values(column));
+            }
+            bif += "}\n";
+        }
+        bif
+    }
+}
+
+/// quotes `word` if it would not read back as a single BIF word, escaping
+/// any `"` and `\` inside the quotes
+fn bif_word(word: &str) -> String {
+    if word.is_empty() || word.contains(|c: char| c.is_whitespace() || "{}()[];,|\"/".contains(c)) {
+        format!("\"{}\"", word.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        word.to_string()
+    }
+}
+
+#[test]
+fn test_from_bif() {
+    // the bundled BIF and JSON copies of sachs describe the same network
+    let bif = BayesianNetwork::from_bif(include_str!("../bayesian_networks/sachs.bif")).unwrap();
+    let json = BayesianNetwork::from_json(include_str!("../bayesian_networks/sachs.json"));
+    assert_eq!(bif.variables, json.variables);
+    assert_eq!(bif.states, json.states);
+    assert_eq!(bif.parents, json.parents);
+    assert_eq!(bif.cpts, json.cpts);
+    let parent_assgn = HashMap::from([
+        (String::from("Erk"), String::from("HIGH")),
+        (String::from("PKA"), String::from("AVG")),
+    ]);
+    assert_eq!(
+        bif.conditional_probability("Akt", "LOW", &parent_assgn),
+        0.177105936
+    );
+
+    let asia = BayesianNetwork::from_bif(include_str!("../bayesian_networks/asia.bif")).unwrap();
+    assert_eq!(asia.num_variables(), 8);
+
+    // writing and reading back BIF is lossless
+    for bn in [&bif, &asia] {
+        let round_trip = BayesianNetwork::from_bif(&bn.to_bif()).unwrap();
+        assert_eq!(round_trip.variables, bn.variables);
+        assert_eq!(round_trip.states, bn.states);
+        assert_eq!(round_trip.parents, bn.parents);
+        assert_eq!(round_trip.cpts, bn.cpts);
+    }
+
+    // even with quotes and backslashes in the names
+    let mut quoted = asia.clone();
+    quoted.network = String::from(r#"say "hi" \ bye"#);
+    quoted.states.get_mut("asia").unwrap()[0] = String::from(r#"a "b" c\"#);
+    let round_trip = BayesianNetwork::from_bif(&quoted.to_bif()).unwrap();
+    assert_eq!(round_trip.network, quoted.network);
+    assert_eq!(round_trip.states, quoted.states);
+
+    fn network(c_block: &str) -> String {
+        format!(
+            r#"// a comment
+            network "broken" {{ property "a property"; }}
+            variable A {{ type discrete [ 2 ] {{ F, T }}; }}
+            variable C {{ type discrete [ 3 ] {{ x, y, z }}; }}
+            probability ( A ) {{ table 0.5, 0.5; }}
+            /* another comment */
+            {c_block}"#
+        )
+    }
+
+    // a table lists C row by row; a default fills the rows not given
+    let table = BayesianNetwork::from_bif(&network(
+        "probability ( C | A ) { table 0.2, 1, 0.3, 0, 0.5, 0; }",
+    ))
+    .unwrap();
+    let expected = vec![vec![0.2, 1.0], vec![0.3, 0.0], vec![0.5, 0.0]];
+    assert_eq!(table.name(), "broken");
+    assert_eq!(table.cpts["C"], expected);
+    let default = BayesianNetwork::from_bif(&network(
+        "probability ( C | A ) { (F) 0.2, 0.3, 0.5; default 1, 0, 0; }",
+    ))
+    .unwrap();
+    assert_eq!(default.cpts["C"], expected);
+
+    assert!(matches!(
+        BayesianNetwork::from_bif(&network("probability ( C | A ) { (F) 0.2, 0.3, 0.5; }")),
+        Err(BnError::CptShape {
+            expected: (3, 2),
+            ..
+        })
+    ));
+    assert!(matches!(
+        BayesianNetwork::from_bif(&network("probability ( C | A ) { (X) 0.2, 0.3, 0.5; }")),
+        Err(BnError::UnknownState(v, s)) if v == "A" && s == "X"
+    ));
+    assert!(matches!(
+        BayesianNetwork::from_bif(&network("probability ( C | D ) { table 1, 0, 0; }")),
+        Err(BnError::UnknownVariable(v)) if v == "D"
+    ));
+    assert!(matches!(
+        BayesianNetwork::from_bif(&network("")),
+        Err(BnError::MissingCpt(v)) if v == "C"
+    ));
+    assert!(matches!(
+        BayesianNetwork::from_bif(&network("probability ( C | A ) { table 0.2, 1, 0.3")),
+        Err(BnError::Syntax { format: "BIF", .. })
+    ));
+}
@@ -0,0 +1,172 @@
+//! Constructing Bayesian networks in code rather than from JSON
+
+use std::{collections::HashMap, sync::OnceLock};
+
+use crate::{BayesianNetwork, BnError, ConditionalProbabilityTable, Parents, States};
+
+/// Builds a [`BayesianNetwork`] one variable at a time.
+///
+/// Each method takes and returns the builder so calls can be chained.
+/// Mistakes such as naming an unknown variable are remembered rather than
+/// reported immediately; `build` returns the first of them.
+/// ```
+/// use rsgm::BayesianNetworkBuilder;
+///
+/// // the collider A, B -> C
+/// let bn = BayesianNetworkBuilder::new("toy_network")
+///     .add_variable("A", &["F", "T"])
+///     .add_variable("B", &["F", "T"])
+///     .add_variable("C", &["F", "T"])
+///     .set_parents("C", &["A", "B"])
+///     .set_cpt("A", vec![vec![0.5], vec![0.5]])
+///     .set_cpt("B", vec![vec![0.25], vec![0.75]])
+///     .set_cpt("C", vec![vec![0.9, 0.8, 0.3, 0.4], vec![0.1, 0.2, 0.7, 0.6]])
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(bn.parents("C"), &vec![String::from("A"), String::from("B")]);
+/// assert_eq!(bn.cardinality("C"), 2);
+///
+/// // C's CPT needs a column for each of the 2 * 2 parent assignments
+/// let misshapen = BayesianNetworkBuilder::new("toy_network")
+///     .add_variable("A", &["F", "T"])
+///     .add_variable("B", &["F", "T"])
+///     .add_variable("C", &["F", "T"])
+///     .set_parents("C", &["A", "B"])
+///     .set_cpt("A", vec![vec![0.5], vec![0.5]])
+///     .set_cpt("B", vec![vec![0.25], vec![0.75]])
+///     .set_cpt("C", vec![vec![0.9, 0.3], vec![0.1, 0.7]])
+///     .build();
+/// assert!(misshapen.is_err());
+/// ```
+#[derive(Debug, Default)]
+pub struct BayesianNetworkBuilder {
+    network: String,
+    variables: Vec<String>,
+    cpts: ConditionalProbabilityTable,
+    states: States,
+    parents: Parents,
+    /// the first mistake made while building, returned by `build`
+    error: Option<BnError>,
+}
+
+impl BayesianNetworkBuilder {
+    /// Starts an empty network named `name`
+    pub fn new(name: &str) -> BayesianNetworkBuilder {
+        BayesianNetworkBuilder {
+            network: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Adds the variable `name` with the given states, in order, and no
+    /// parents
+    pub fn add_variable<S: AsRef<str>>(mut self, name: &str, states: &[S]) -> Self {
+        if self.states.contains_key(name) {
+            self.fail(BnError::VariableCollision(name.to_string()));
+            return self;
+        }
+        let states: Vec<String> = states.iter().map(|s| s.as_ref().to_string()).collect();
+        for (i, state) in states.iter().enumerate() {
+            if states[..i].contains(state) {
+                self.fail(BnError::DuplicateState(name.to_string(), state.clone()));
+            }
+        }
+        self.variables.push(name.to_string());
+        self.states.insert(name.to_string(), states);
+        self.parents.insert(name.to_string(), Vec::new());
+        self
+    }
+
+    /// Sets the parents of `name`, in the order that lays out the columns of
+    /// its CPT (see `BayesianNetwork::parents_in_cpt_order`)
+    pub fn set_parents<S: AsRef<str>>(mut self, name: &str, parents: &[S]) -> Self {
+        match self.parents.get_mut(name) {
+            Some(current) => {
+                *current = parents.iter().map(|p| p.as_ref().to_string()).collect();
+            }
+            None => self.fail(BnError::UnknownVariable(name.to_string())),
+        }
+        self
+    }
+
+    /// Sets the CPT of `name`, with one row per state and one column per
+    /// assignment to its parents
+    pub fn set_cpt(mut self, name: &str, cpt: Vec<Vec<f64>>) -> Self {
+        if self.states.contains_key(name) {
+            self.cpts.insert(name.to_string(), cpt);
+        } else {
+            self.fail(BnError::UnknownVariable(name.to_string()));
+        }
+        self
+    }
+
+    /// Finishes the network, failing on the first mistake made while
+    /// building, a variable without a CPT, a network that does not pass
+    /// `BayesianNetwork::validate`, or a cycle
+    pub fn build(self) -> Result<BayesianNetwork, BnError> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        if let Some(missing) = self.variables.iter().find(|v| !self.cpts.contains_key(*v)) {
+            return Err(BnError::MissingCpt(missing.clone()));
+        }
+        let bn = BayesianNetwork {
+            network: self.network,
+            variables: self.variables,
+            cpts: self.cpts,
+            states: self.states,
+            parents: self.parents,
+            metadata: HashMap::new(),
+            topological_order: OnceLock::new(),
+        };
+        bn.validate().map_err(BnError::Invalid)?;
+        bn.try_topological_sort()?;
+        Ok(bn)
+    }
+
+    fn fail(&mut self, err: BnError) {
+        self.error.get_or_insert(err);
+    }
+}
+
+#[test]
+fn test_builder_errors() {
+    let collider = || {
+        BayesianNetworkBuilder::new("toy_network")
+            .add_variable("A", &["F", "T"])
+            .add_variable("B", &["F", "T"])
+            .add_variable("C", &["F", "T"])
+            .set_parents("C", &["A", "B"])
+            .set_cpt("A", vec![vec![0.5], vec![0.5]])
+            .set_cpt("B", vec![vec![0.25], vec![0.75]])
+    };
+
+    assert!(matches!(collider().build(), Err(BnError::MissingCpt(v)) if v == "C"));
+    assert!(matches!(
+        collider().set_cpt("D", vec![vec![1.0]]).build(),
+        Err(BnError::UnknownVariable(v)) if v == "D"
+    ));
+    assert!(matches!(
+        collider().add_variable("A", &["F"]).build(),
+        Err(BnError::VariableCollision(v)) if v == "A"
+    ));
+    assert!(matches!(
+        collider().add_variable("D", &["F", "F"]).build(),
+        Err(BnError::DuplicateState(v, s)) if v == "D" && s == "F"
+    ));
+    assert!(matches!(
+        collider()
+            .set_cpt("C", vec![vec![0.9, 0.3], vec![0.1, 0.7]])
+            .build(),
+        Err(BnError::Invalid(errors)) if errors.len() == 2
+    ));
+    assert!(matches!(
+        collider()
+            .set_parents("A", &["C"])
+            .set_cpt("A", vec![vec![0.5, 0.5], vec![0.5, 0.5]])
+            .set_cpt("C", vec![vec![0.5; 4], vec![0.5; 4]])
+            .build(),
+        Err(BnError::Cycle(_))
+    ));
+}
@@ -1,90 +1,783 @@
-use std::collections::HashMap;
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap, HashSet},
+    ops::Range,
+    sync::OnceLock,
+};
 
+use rand::Rng;
 use rsdd::{
+    builder::{
+        bdd::{BddBuilder, RobddBuilder},
+        cache::all_app::AllTable,
+        BottomUpBuilder,
+    },
     repr::{
+        bdd::BddPtr,
         cnf::Cnf,
+        ddnnf::DDNNFPtr,
         var_label::{Literal, VarLabel},
+        var_order::VarOrder,
         wmc::WmcParams,
     },
     util::semirings::{RealSemiring, Semiring},
 };
 
-use crate::BayesianNetwork;
+use crate::{
+    bayesian_network::min_fill_order, sampling::sample_index, BayesianNetwork, BnError,
+    LogSemiring, MaxProductSemiring, ParentConfig,
+};
+
+/// Size statistics of a Bayesian network compiled to a CNF
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CnfStats {
+    /// number of Boolean variables in the CNF
+    pub num_vars: usize,
+    /// number of clauses in the CNF
+    pub num_clauses: usize,
+    /// number of indicator variables, one per state of each variable
+    pub num_indicators: usize,
+    /// number of parameter variables, one per CPT entry unless parameters
+    /// are shared
+    pub num_parameters: usize,
+    /// number of parameter variables that stand for more than one CPT entry
+    pub num_shared_parameters: usize,
+    /// number of bit variables added by the log encoding
+    pub num_encoding_bits: usize,
+}
+
+/// Refers to the CPT entry Pr(variable = value | parent_assignment)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamRef {
+    pub variable: String,
+    pub value: String,
+    pub parent_assignment: ParentConfig,
+}
+
+/// A parameter variable of the CNF, which stands for
+/// Pr(variable = value | parent_assignment)
+#[derive(Debug, Clone)]
+struct Parameter {
+    /// the CNF variable carrying this entry's weight
+    label: VarLabel,
+    value: String,
+    parent_assignment: HashMap<String, String>,
+    probability: f64,
+}
+
+/// the largest joint table `joint_marginal` will build
+pub const MAX_JOINT_TABLE_SIZE: usize = 1 << 16;
+
+/// How the states of each variable are encoded in the CNF. Every encoding
+/// has one indicator literal per state, so queries are unaffected by the
+/// choice; they differ in how the indicators are kept mutually exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    /// clauses that at least one indicator holds and that no two do, which
+    /// is O(k^2) clauses for a variable with k states
+    #[default]
+    OneHot,
+    /// ceil(log2 k) extra bit variables that spell out the state in binary,
+    /// with each indicator defined as one pattern of the bits and unused
+    /// patterns forbidden, which is O(k log k) clauses
+    Log,
+    /// `Log` for variables with more than `log_threshold` states and `OneHot`
+    /// for the rest
+    Auto { log_threshold: usize },
+    /// a single CNF variable for each variable with exactly two states, whose
+    /// positive literal indicates the second state and negative literal the
+    /// first, so no clauses are needed to keep them exclusive; `OneHot` for
+    /// the rest
+    Bernoulli,
+}
+
+impl Encoding {
+    /// whether a variable with `num_states` states gets the log encoding
+    fn uses_log(&self, num_states: usize) -> bool {
+        match self {
+            Encoding::OneHot => false,
+            Encoding::Log => num_states > 1,
+            Encoding::Auto { log_threshold } => num_states > (*log_threshold).max(1),
+            Encoding::Bernoulli => false,
+        }
+    }
+
+    /// whether a variable with `num_states` states gets a single literal
+    fn uses_bernoulli(&self, num_states: usize) -> bool {
+        *self == Encoding::Bernoulli && num_states == 2
+    }
+}
+
+/// Options controlling how a Bayesian network is compiled to a CNF
+#[derive(Debug, Clone, Default)]
+pub struct CompileOptions {
+    /// Compile each weakly-connected component of the network into its own
+    /// CNF. The components are probabilistically independent, so every
+    /// query compiles the components separately and multiplies their
+    /// weighted model counts; this can turn one intractable compilation into
+    /// several tractable ones. Only `with_bdd` and `to_dimacs` still use the
+    /// CNF of the whole network.
+    pub split_components: bool,
+    /// Give entries of a variable's CPT one shared parameter variable when
+    /// their parent assignments induce identical distributions over the
+    /// variable (i.e. their columns are equal), as often happens after
+    /// merging states. This shrinks the CNF without changing any weighted
+    /// model count.
+    pub share_parameters: bool,
+    /// how each variable's states are encoded
+    pub encoding: Encoding,
+}
+
+/// Evidence that grows one observation at a time over a CNF compiled once;
+/// see `BayesianNetworkCNF::with_running_evidence`
+pub struct RunningEvidence<'a> {
+    cnf: &'a BayesianNetworkCNF,
+    /// the weighted model count of the compiled CNF under given weights
+    count: &'a dyn Fn(&WmcParams<RealSemiring>) -> RealSemiring,
+    params: WmcParams<RealSemiring>,
+    evidence: HashMap<String, String>,
+}
+
+impl RunningEvidence<'_> {
+    /// Adds `var = value` to the evidence and returns the updated
+    /// Pr(evidence). Observing a variable again with a different value makes
+    /// the evidence impossible, so every later result is 0.
+    pub fn observe(&mut self, var: &str, value: &str) -> f64 {
+        let indicators = self
+            .cnf
+            .indicators
+            .get(var)
+            .unwrap_or_else(|| panic!("could not find variable {var}"));
+        if !indicators.contains_key(value) {
+            panic!("could not find assignment {value} for variable {var}");
+        }
+        for (other, literal) in indicators.iter() {
+            if other != value {
+                let label = literal.get_label();
+                let (low, high) = *self.params.get_var_weight(label);
+                if literal.get_polarity() {
+                    self.params.set_weight(label, low, RealSemiring(0.0));
+                } else {
+                    self.params.set_weight(label, RealSemiring(0.0), high);
+                }
+            }
+        }
+        self.evidence.insert(var.to_string(), value.to_string());
+        self.probability()
+    }
+
+    /// Pr(evidence) for the evidence observed so far
+    pub fn probability(&self) -> f64 {
+        (self.count)(&self.params).0
+    }
+
+    /// the evidence observed so far; a variable observed more than once maps
+    /// to its latest value
+    pub fn evidence(&self) -> &HashMap<String, String> {
+        &self.evidence
+    }
+}
 
 /// Contains a Bayesian network that was compiled to a CNF
 #[derive(Debug, Clone)]
 pub struct BayesianNetworkCNF {
     cnf: Cnf,
-    /// maps Variable Name -> (Variable Assignment -> Label)
-    indicators: HashMap<String, HashMap<String, VarLabel>>,
+    /// maps Variable Name -> (Variable Assignment -> Indicator literal)
+    indicators: HashMap<String, HashMap<String, Literal>>,
     params: WmcParams<RealSemiring>,
+    /// the network's variables, in the (topological) order they were encoded
+    order: Vec<String>,
+    /// the network's variables, each with its states, in the network's
+    /// order; positions in these lists are the indices of the index-based API
+    states: Vec<(String, Vec<String>)>,
+    /// maps Variable Name -> the parameter variables of its CPT
+    parameters: HashMap<String, Vec<Parameter>>,
+    /// the weight of every CNF variable, as (low, high)
+    weights: HashMap<VarLabel, (f64, f64)>,
+    /// when compiled with `split_components`, one CNF per weakly-connected
+    /// component; queries are answered by these instead of `cnf`
+    components: Vec<BayesianNetworkCNF>,
+    /// the number of parameter variables shared by several CPT entries
+    num_shared_parameters: usize,
+    /// the number of bit variables added by the log encoding
+    num_encoding_bits: usize,
+    /// the stable name of every CNF variable; see `export_weights`
+    weight_names: HashMap<String, VarLabel>,
+    /// the `content_hash` of the network this was compiled from
+    source_hash: u64,
+    /// the moral graph of the network this was compiled from
+    moral_graph: HashMap<String, HashSet<String>>,
+    /// the CNF variables of each variable's encoding (its indicators,
+    /// parameters, and encoding bits), which are numbered consecutively
+    label_ranges: HashMap<String, Range<usize>>,
+    /// whether clauses beyond the network's encoding were asserted (see
+    /// `with_ordinal_evidence`), so answers can no longer be read off the CPTs
+    constrained: bool,
+    /// `marginal_table[i][j]`: Pr(var = value) for the `j`th value of the
+    /// `i`th variable of `states`, counted over one compilation by the first
+    /// `marginal_by_index`; reset whenever the weights or clauses change
+    marginal_table: OnceLock<Vec<Vec<f64>>>,
 }
 
-impl BayesianNetworkCNF {
-    pub fn from_bayesian_network(network: &BayesianNetwork) -> BayesianNetworkCNF {
-        let mut clauses: Vec<Vec<Literal>> = Vec::new();
-        let mut wmc_params: HashMap<VarLabel, (RealSemiring, RealSemiring)> = HashMap::new();
-        let mut var_count = 0;
+/// The clauses and weights that one variable contributes to the CNF, kept
+/// in a label space of their own so they stay valid while other variables
+/// change: labels `0..externals.len()` stand for the parents' indicators
+/// listed in `externals`, and the rest for the fragment's own CNF variables
+/// (its indicators, parameters, and encoding bits), in order
+#[derive(Debug, Clone)]
+struct Fragment {
+    /// the `local_hash` of the variable when it was encoded
+    key: u64,
+    /// the `(parent, value)` whose indicator each external label stands for
+    externals: Vec<(String, String)>,
+    /// the indicator literal of each state
+    indicators: Vec<(String, Literal)>,
+    /// the weight of each own label, as (low, high)
+    weights: Vec<(f64, f64)>,
+    /// the stable name of each own label; see `export_weights`
+    names: Vec<String>,
+    clauses: Vec<Vec<Literal>>,
+    parameters: Vec<Parameter>,
+    num_shared_parameters: usize,
+    num_encoding_bits: usize,
+}
 
-        // create one indicator for every variable assignment
-        // maps Variable Name -> (Variable Assignment -> Label)
-        let mut indicators: HashMap<String, HashMap<String, VarLabel>> = HashMap::new();
+impl Fragment {
+    fn new(network: &BayesianNetwork, variable: &str, options: &CompileOptions) -> Fragment {
+        let mut externals: Vec<(String, String)> = Vec::new();
+        let mut parent_labels: HashMap<(&str, &str), VarLabel> = HashMap::new();
+        for parent in network.parents_in_cpt_order(variable) {
+            for value in network.all_possible_assignments(parent) {
+                parent_labels.insert((parent, value), VarLabel::new_usize(externals.len()));
+                externals.push((parent.clone(), value.clone()));
+            }
+        }
+        let mut var_count = externals.len();
+        let mut clauses: Vec<Vec<Literal>> = Vec::new();
+        let mut weights: Vec<(f64, f64)> = Vec::new();
+        let mut names: Vec<String> = Vec::new();
+        let mut num_shared_parameters = 0;
+        let mut num_encoding_bits = 0;
 
-        for variable in network.topological_sort() {
-            // create this variable's indicators and parameter clauses
-            let mut cur_indic: Vec<Literal> = Vec::new();
-            indicators.insert(variable.clone(), HashMap::new());
-            for variable_assignment in network.all_possible_assignments(&variable) {
+        // create this variable's indicators and parameter clauses
+        let mut indicators: Vec<(String, Literal)> = Vec::new();
+        let mut cur_indic: Vec<Literal> = Vec::new();
+        let num_states = network.cardinality(variable);
+        let mut cur_params: Vec<Parameter> = Vec::new();
+        let parent_assignments = network.parent_assignments(variable);
+        // groups of parent assignments that get one parameter variable per
+        // value; singletons unless sharing parameters
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        if options.share_parameters {
+            let mut columns: Vec<Vec<u64>> = Vec::new();
+            for (i, parent_assignment) in parent_assignments.iter().enumerate() {
+                let column: Vec<u64> = network
+                    .all_possible_assignments(variable)
+                    .iter()
+                    .map(|value| {
+                        network
+                            .conditional_probability(variable, value, parent_assignment)
+                            .to_bits()
+                    })
+                    .collect();
+                match columns.iter().position(|c| *c == column) {
+                    Some(group) => groups[group].push(i),
+                    None => {
+                        columns.push(column);
+                        groups.push(vec![i]);
+                    }
+                }
+            }
+        } else {
+            groups = (0..parent_assignments.len()).map(|i| vec![i]).collect();
+        }
+        let bernoulli = options.encoding.uses_bernoulli(num_states);
+        let shared = VarLabel::new_usize(var_count);
+        if bernoulli {
+            // the one CNF variable, named for the state it is true for
+            weights.push((1.0, 1.0));
+            names.push(format!(
+                "I({variable}={})",
+                network.all_possible_assignments(variable)[1]
+            ));
+            var_count += 1;
+        }
+        for (i, variable_assignment) in network
+            .all_possible_assignments(variable)
+            .iter()
+            .enumerate()
+        {
+            let new_indic = if bernoulli {
+                Literal::new(shared, i == 1)
+            } else {
                 let cur_var = VarLabel::new_usize(var_count);
-                let new_indic = Literal::new(cur_var, true);
-                wmc_params.insert(cur_var, (RealSemiring::one(), RealSemiring::one()));
-                cur_indic.push(new_indic);
-                indicators
-                    .get_mut(&variable)
-                    .unwrap()
-                    .insert(variable_assignment.clone(), cur_var);
+                weights.push((1.0, 1.0));
+                names.push(format!("I({variable}={variable_assignment})"));
                 var_count += 1;
+                Literal::new(cur_var, true)
+            };
+            cur_indic.push(new_indic);
+            indicators.push((variable_assignment.clone(), new_indic));
 
-                for parent_assignment in network.parent_assignments(&variable) {
-                    let cur_param = VarLabel::new_usize(var_count);
-                    let cur_prob = network.conditional_probability(
-                        &variable,
-                        variable_assignment,
-                        &parent_assignment,
-                    );
-                    wmc_params.insert(cur_param, (RealSemiring::one(), RealSemiring(cur_prob)));
-                    var_count += 1;
+            // in CPT order rather than the assignment's, so that clauses
+            // come out the same on every compilation
+            let parent_indicators = |parent_assignment: &HashMap<String, String>| {
+                network
+                    .parents_in_cpt_order(variable)
+                    .iter()
+                    .map(|varname| {
+                        let varval = &parent_assignment[varname];
+                        let label = parent_labels[&(varname.as_str(), varval.as_str())];
+                        Literal::new(label, true)
+                    })
+                    .collect::<Vec<Literal>>()
+            };
+            for group in groups.iter() {
+                let cur_param = VarLabel::new_usize(var_count);
+                let cur_prob = network.conditional_probability(
+                    variable,
+                    variable_assignment,
+                    &parent_assignments[group[0]],
+                );
+                weights.push((1.0, cur_prob));
+                let conditions: Vec<String> = group
+                    .iter()
+                    .map(|i| {
+                        network
+                            .parents_in_cpt_order(variable)
+                            .iter()
+                            .map(|p| format!("{p}={}", parent_assignments[*i][p]))
+                            .collect::<Vec<String>>()
+                            .join(",")
+                    })
+                    .collect();
+                let name = if network.parents(variable).is_empty() {
+                    format!("P({variable}={variable_assignment})")
+                } else {
+                    format!(
+                        "P({variable}={variable_assignment}|{})",
+                        conditions.join(";")
+                    )
+                };
+                names.push(name);
+                var_count += 1;
 
+                if group.len() == 1 {
                     // build cur_param <=> cur_assgn /\ cur_indic
-                    let mut indic_vec: Vec<Literal> = parent_assignment
-                        .iter()
-                        .map(|(varname, varval)| {
-                            let label = indicators[varname][varval];
-                            Literal::new(label, true)
-                        })
-                        .collect();
+                    let mut indic_vec = parent_indicators(&parent_assignments[group[0]]);
                     indic_vec.push(new_indic);
 
                     let mut imp1 = implies(&[Literal::new(cur_param, true)], &indic_vec);
                     let mut imp2 = implies(&indic_vec, &[Literal::new(cur_param, true)]);
                     clauses.append(&mut imp1);
                     clauses.append(&mut imp2);
+                } else {
+                    // build cur_param <=> cur_indic /\ (one of the group's
+                    // parent assignments); since exactly one parent
+                    // assignment holds, the disjunction is written as
+                    // "none of the other parent assignments"
+                    num_shared_parameters += 1;
+                    clauses.append(&mut implies(&[Literal::new(cur_param, true)], &[new_indic]));
+                    for (i, parent_assignment) in parent_assignments.iter().enumerate() {
+                        let mut indic_vec = parent_indicators(parent_assignment);
+                        if group.contains(&i) {
+                            indic_vec.push(new_indic);
+                            clauses
+                                .append(&mut implies(&indic_vec, &[Literal::new(cur_param, true)]));
+                        } else {
+                            let mut clause: Vec<Literal> = indic_vec
+                                .iter()
+                                .map(|l| Literal::new(l.get_label(), false))
+                                .collect();
+                            clause.push(Literal::new(cur_param, false));
+                            clauses.push(clause);
+                        }
+                    }
+                }
+
+                for i in group.iter() {
+                    cur_params.push(Parameter {
+                        label: cur_param,
+                        value: variable_assignment.clone(),
+                        parent_assignment: parent_assignments[*i].clone(),
+                        probability: cur_prob,
+                    });
                 }
             }
+        }
+        if bernoulli {
+            // a literal and its negation are exclusive already
+        } else if options.encoding.uses_log(cur_indic.len()) {
+            let mut bits: Vec<VarLabel> = Vec::new();
+            while 1 << bits.len() < cur_indic.len() {
+                let bit = VarLabel::new_usize(var_count);
+                weights.push((1.0, 1.0));
+                names.push(format!("B({variable},{})", bits.len()));
+                var_count += 1;
+                bits.push(bit);
+            }
+            num_encoding_bits += bits.len();
+            clauses.append(&mut log_encoding(&cur_indic, &bits));
+        } else {
             // build exactly-one for indicator clause
             clauses.append(&mut exactly_one(cur_indic));
         }
+        Fragment {
+            key: network.local_hash(variable),
+            externals,
+            indicators,
+            weights,
+            names,
+            clauses,
+            parameters: cur_params,
+            num_shared_parameters,
+            num_encoding_bits,
+        }
+    }
+}
+
+/// Keeps the encoding of every variable of the last network it compiled, so
+/// that recompiling after an edit only re-encodes the variables whose
+/// states, parents, parents' states, or CPT changed. Editing one CPT
+/// re-encodes one variable; adding an edge re-encodes its child.
+/// ```
+/// use rsgm::{BayesianNetwork, CompilationCache, CompileOptions};
+/// use std::collections::HashMap;
+///
+/// // models the collider A, B -> C
+/// static NETWORK: &str = r#"{
+///     "network": "toy_network",
+///     "variables": ["A", "B", "C"],
+///     "cpts": {
+///         "A": [[0.5], [0.5]],
+///         "B": [[0.25], [0.75]],
+///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+///     },
+///     "states": {
+///         "A": ["F", "T"],
+///         "B": ["F", "T"],
+///         "C": ["F", "T"]
+///     },
+///     "parents" :{
+///         "A": [],
+///         "B": [],
+///         "C": ["A", "B"]
+///     }
+/// }"#;
+///
+/// let mut cache = CompilationCache::new(CompileOptions::default());
+/// cache.recompile(&BayesianNetwork::from_json(NETWORK));
+/// assert_eq!(cache.num_reencoded(), 3);
+///
+/// // make C certain to be F when A = F and B = F
+/// let edited = NETWORK.replace("[[0.9, 0.8", "[[1.0, 0.8").replace("[0.1, 0.2", "[0.0, 0.2");
+/// let bn = cache.recompile(&BayesianNetwork::from_json(&edited));
+/// assert_eq!(cache.num_reencoded(), 1);
+///
+/// // Pr(C = T) = 0.5 * (0.25 * 0.0 + 0.75 * 0.2) + 0.5 * (0.25 * 0.7 + 0.75 * 0.6)
+/// let c_t = HashMap::from([(String::from("C"), String::from("T"))]);
+/// assert!((bn.probability(&c_t) - 0.3875).abs() < 1e-9);
+/// ```
+pub struct CompilationCache {
+    options: CompileOptions,
+    /// maps Variable Name -> its encoding in the last compilation
+    fragments: HashMap<String, Fragment>,
+    /// how many variables the last `recompile` encoded afresh
+    num_reencoded: usize,
+}
+
+impl CompilationCache {
+    /// Creates an empty cache that compiles with `options`
+    pub fn new(options: CompileOptions) -> CompilationCache {
+        CompilationCache {
+            options,
+            fragments: HashMap::new(),
+            num_reencoded: 0,
+        }
+    }
+
+    /// Compiles `network`, reusing the encoding of every variable whose
+    /// `local_hash` is unchanged since the last compilation. The result is
+    /// the same as compiling `network` from scratch.
+    pub fn recompile(&mut self, network: &BayesianNetwork) -> BayesianNetworkCNF {
+        self.fragments
+            .retain(|variable, _| network.states.contains_key(variable));
+        self.num_reencoded = 0;
+        for variable in network.variables() {
+            let key = network.local_hash(variable);
+            if !matches!(self.fragments.get(variable), Some(f) if f.key == key) {
+                let fragment = Fragment::new(network, variable, &self.options);
+                self.fragments.insert(variable.clone(), fragment);
+                self.num_reencoded += 1;
+            }
+        }
+        BayesianNetworkCNF::from_fragments(network, &self.options, &self.fragments)
+    }
+
+    /// how many variables the last `recompile` encoded afresh instead of
+    /// reusing their cached encoding
+    pub fn num_reencoded(&self) -> usize {
+        self.num_reencoded
+    }
+}
+
+impl BayesianNetworkCNF {
+    /// Compiles `network` into a CNF. Labels are handed out in topological
+    /// order, each variable's states before its parameters and parameters in
+    /// the order of `parent_assignments`, so compiling the same network
+    /// twice gives the identical CNF.
+    pub fn from_bayesian_network(network: &BayesianNetwork) -> BayesianNetworkCNF {
+        Self::from_bayesian_network_with_options(network, &CompileOptions::default())
+    }
+
+    pub fn from_bayesian_network_with_options(
+        network: &BayesianNetwork,
+        options: &CompileOptions,
+    ) -> BayesianNetworkCNF {
+        CompilationCache::new(options.clone()).recompile(network)
+    }
+
+    /// Compiles `network` like `from_bayesian_network`, and also returns the
+    /// CNF's weights in another semiring `S`, mapping each weight (a
+    /// conditional probability, or 1) through `f`. Run weighted model counts
+    /// over `cnf()` with these parameters to compute in `S`; the queries of
+    /// this type keep using the real semiring.
+    /// ```
+    /// use rsdd::{
+    ///     builder::{
+    ///         bdd::{BddBuilder, RobddBuilder},
+    ///         cache::all_app::AllTable,
+    ///         BottomUpBuilder,
+    ///     },
+    ///     repr::{bdd::BddPtr, ddnnf::DDNNFPtr},
+    /// };
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF, MaxProductSemiring};
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let network = BayesianNetwork::from_json(NETWORK);
+    /// let (bn, params) = BayesianNetworkCNF::from_bayesian_network_with_semiring(
+    ///     &network,
+    ///     MaxProductSemiring,
+    /// );
+    ///
+    /// let builder = RobddBuilder::<AllTable<BddPtr>>::new_default_order(bn.cnf().num_vars());
+    /// let bdd = builder.compile_cnf(bn.cnf());
+    /// // the most probable explanation is A = F, B = T, C = F
+    /// let mpe = bdd.wmc(builder.get_order(), &params).0;
+    /// assert!((mpe - 0.5 * 0.75 * 0.8).abs() < 1e-9);
+    /// ```
+    pub fn from_bayesian_network_with_semiring<S: Semiring>(
+        network: &BayesianNetwork,
+        f: impl Fn(f64) -> S,
+    ) -> (BayesianNetworkCNF, WmcParams<S>) {
+        let bn = Self::from_bayesian_network(network);
+        let params = bn.semiring_params(&HashMap::new(), f);
+        (bn, params)
+    }
+
+    /// Assembles the CNF of `network` from the fragment of each of its
+    /// variables, also assembling one CNF per component if `options` asks
+    /// to split them
+    fn from_fragments(
+        network: &BayesianNetwork,
+        options: &CompileOptions,
+        fragments: &HashMap<String, Fragment>,
+    ) -> BayesianNetworkCNF {
+        let mut bn = Self::assemble(network, fragments);
+        if options.split_components {
+            let components = network.connected_components();
+            if components.len() > 1 {
+                bn.components = components
+                    .iter()
+                    .map(|vars| Self::assemble(&component_network(network, vars), fragments))
+                    .collect();
+            }
+        }
+        bn
+    }
+
+    /// Numbers the CNF variables of each variable's fragment consecutively,
+    /// in topological order, and points each fragment's external labels at
+    /// its parents' indicators
+    fn assemble(
+        network: &BayesianNetwork,
+        fragments: &HashMap<String, Fragment>,
+    ) -> BayesianNetworkCNF {
+        let mut clauses: Vec<Vec<Literal>> = Vec::new();
+        let mut weights: HashMap<VarLabel, (f64, f64)> = HashMap::new();
+        let mut var_count = 0;
+
+        // maps Variable Name -> (Variable Assignment -> Indicator literal)
+        let mut indicators: HashMap<String, HashMap<String, Literal>> = HashMap::new();
+        let mut parameters: HashMap<String, Vec<Parameter>> = HashMap::new();
+        let mut num_shared_parameters = 0;
+        let mut num_encoding_bits = 0;
+        let mut weight_names: HashMap<String, VarLabel> = HashMap::new();
+        let mut label_ranges: HashMap<String, Range<usize>> = HashMap::new();
+        let order = network.topological_sort();
+
+        for variable in order.iter() {
+            let fragment = &fragments[variable];
+            // the literal in the CNF that the positive literal of each of the
+            // fragment's labels stands for
+            let literals: Vec<Literal> = fragment
+                .externals
+                .iter()
+                .map(|(parent, value)| indicators[parent][value])
+                .chain(
+                    (var_count..var_count + fragment.weights.len())
+                        .map(|v| Literal::new(VarLabel::new_usize(v), true)),
+                )
+                .collect();
+            let relabel = |l: &Literal| {
+                let target = literals[l.get_label().value_usize()];
+                Literal::new(
+                    target.get_label(),
+                    target.get_polarity() == l.get_polarity(),
+                )
+            };
+            clauses.extend(
+                fragment
+                    .clauses
+                    .iter()
+                    .map(|clause| clause.iter().map(relabel).collect::<Vec<Literal>>()),
+            );
+            let own_labels: Vec<VarLabel> = literals[fragment.externals.len()..]
+                .iter()
+                .map(|l| l.get_label())
+                .collect();
+            for ((label, weight), name) in own_labels
+                .iter()
+                .zip(fragment.weights.iter())
+                .zip(fragment.names.iter())
+            {
+                weights.insert(*label, *weight);
+                weight_names.insert(name.clone(), *label);
+            }
+            indicators.insert(
+                variable.clone(),
+                fragment
+                    .indicators
+                    .iter()
+                    .map(|(value, literal)| (value.clone(), relabel(literal)))
+                    .collect(),
+            );
+            parameters.insert(
+                variable.clone(),
+                fragment
+                    .parameters
+                    .iter()
+                    .map(|p| Parameter {
+                        label: own_labels[p.label.value_usize() - fragment.externals.len()],
+                        ..p.clone()
+                    })
+                    .collect(),
+            );
+            num_shared_parameters += fragment.num_shared_parameters;
+            num_encoding_bits += fragment.num_encoding_bits;
+            label_ranges.insert(
+                variable.clone(),
+                var_count..var_count + fragment.weights.len(),
+            );
+            var_count += fragment.weights.len();
+        }
+        let params = WmcParams::new(
+            weights
+                .iter()
+                .map(|(label, (low, high))| (*label, (RealSemiring(*low), RealSemiring(*high))))
+                .collect(),
+        );
         BayesianNetworkCNF {
             cnf: Cnf::new(clauses),
             indicators,
-            params: WmcParams::new(wmc_params),
+            params,
+            order,
+            states: network
+                .variables()
+                .iter()
+                .map(|v| (v.clone(), network.all_possible_assignments(v).clone()))
+                .collect(),
+            parameters,
+            weights,
+            components: Vec::new(),
+            num_shared_parameters,
+            num_encoding_bits,
+            weight_names,
+            source_hash: network.content_hash(),
+            moral_graph: network.moral_graph(),
+            label_ranges,
+            constrained: false,
+            marginal_table: OnceLock::new(),
         }
     }
 
+    /// the CNF variable of the indicator of `var = value`; with
+    /// `Encoding::Bernoulli` both states of a binary variable share one, so
+    /// prefer `indicator_literal`
     pub fn indicator(&self, var: &String, value: &String) -> VarLabel {
+        self.indicators[var][value].get_label()
+    }
+
+    /// the literal that holds exactly when `var = value`
+    pub fn indicator_literal(&self, var: &String, value: &String) -> Literal {
         self.indicators[var][value]
     }
 
+    /// the `content_hash` of the network this was compiled from; a cached
+    /// compilation is still valid for a network exactly when the hashes match
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let network = BayesianNetwork::from_json(NETWORK);
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&network);
+    ///
+    /// assert_eq!(bn.source_hash(), network.content_hash());
+    /// ```
+    pub fn source_hash(&self) -> u64 {
+        self.source_hash
+    }
+
     pub fn cnf(&self) -> &Cnf {
         &self.cnf
     }
@@ -93,14 +786,2391 @@ impl BayesianNetworkCNF {
         &self.params
     }
 
-    pub fn to_dimacs(&self) -> String {
-        format!(
-            "p cnf {} {}{}",
-            self.cnf.clauses().len(),
-            self.cnf.num_vars(),
-            self.cnf.to_dimacs()
+    /// Compiles the CNF into a BDD once and computes its weighted model count
+    /// under each of `params`
+    fn weighted_model_counts(&self, params: &[WmcParams<RealSemiring>]) -> Vec<f64> {
+        self.with_model_counter(|count| params.iter().map(|p| count(p).0).collect())
+    }
+
+    /// Compiles the CNF into a BDD once, or each component's CNF when split,
+    /// and passes `f` a function computing the weighted model count under
+    /// weights given for this CNF's labels
+    fn with_model_counter<S: Semiring, T>(
+        &self,
+        f: impl FnOnce(&dyn Fn(&WmcParams<S>) -> S) -> T,
+    ) -> T {
+        if self.components.is_empty() {
+            self.with_own_counter(f)
+        } else {
+            self.with_component_counters(&self.components, &|_| S::one(), f)
+        }
+    }
+
+    /// `with_model_counter` over `cnf` itself, ignoring any components
+    fn with_own_counter<S: Semiring, T>(
+        &self,
+        f: impl FnOnce(&dyn Fn(&WmcParams<S>) -> S) -> T,
+    ) -> T {
+        let builder = RobddBuilder::<AllTable<BddPtr>>::new_default_order(self.cnf.num_vars());
+        let bdd = builder.compile_cnf(&self.cnf);
+        f(&|params| bdd.wmc(builder.get_order(), params))
+    }
+
+    /// Compiles each of `components` in turn, multiplying its weighted model
+    /// count into `outer`, and passes `f` the product
+    fn with_component_counters<S: Semiring, T>(
+        &self,
+        components: &[BayesianNetworkCNF],
+        outer: &dyn Fn(&WmcParams<S>) -> S,
+        f: impl FnOnce(&dyn Fn(&WmcParams<S>) -> S) -> T,
+    ) -> T {
+        match components.split_first() {
+            None => f(outer),
+            Some((component, rest)) => component.with_own_counter(|count| {
+                let product = |params: &WmcParams<S>| {
+                    outer(params) * count(&component.component_params(self, params))
+                };
+                self.with_component_counters(rest, &product, f)
+            }),
+        }
+    }
+
+    /// Translates `params`, weights for the labels of `whole`, into weights
+    /// for the labels of this component of it, matching labels by the names
+    /// of `export_weights`
+    fn component_params<S: Semiring>(
+        &self,
+        whole: &BayesianNetworkCNF,
+        params: &WmcParams<S>,
+    ) -> WmcParams<S> {
+        WmcParams::new(
+            self.weight_names
+                .iter()
+                .map(|(name, label)| (*label, *params.get_var_weight(whole.weight_names[name])))
+                .collect(),
         )
     }
+
+    /// Like `evidence_params`, but in the semiring `S`, mapping every weight
+    /// through `f`
+    fn semiring_params<S: Semiring>(
+        &self,
+        evidence: &HashMap<String, String>,
+        f: impl Fn(f64) -> S,
+    ) -> WmcParams<S> {
+        WmcParams::new(
+            self.evidence_weights(self.weights.clone(), evidence)
+                .into_iter()
+                .map(|(label, (low, high))| (label, (f(low), f(high))))
+                .collect(),
+        )
+    }
+
+    /// Builds WMC parameters that condition on `evidence` by zeroing the weight
+    /// of every indicator that contradicts it
+    fn evidence_params(&self, evidence: &HashMap<String, String>) -> WmcParams<RealSemiring> {
+        self.evidence_params_with(self.weights.clone(), evidence)
+    }
+
+    /// Like `evidence_params`, but starting from `weights` instead of the
+    /// CNF's own weights
+    fn evidence_params_with(
+        &self,
+        weights: HashMap<VarLabel, (f64, f64)>,
+        evidence: &HashMap<String, String>,
+    ) -> WmcParams<RealSemiring> {
+        WmcParams::new(
+            self.evidence_weights(weights, evidence)
+                .into_iter()
+                .map(|(label, (low, high))| (label, (RealSemiring(low), RealSemiring(high))))
+                .collect(),
+        )
+    }
+
+    /// Zeroes the weight in `weights` of every indicator that contradicts
+    /// `evidence`
+    fn evidence_weights(
+        &self,
+        mut weights: HashMap<VarLabel, (f64, f64)>,
+        evidence: &HashMap<String, String>,
+    ) -> HashMap<VarLabel, (f64, f64)> {
+        for (var, value) in evidence.iter() {
+            let indicators = self
+                .indicators
+                .get(var)
+                .unwrap_or_else(|| panic!("could not find variable {var}"));
+            if !indicators.contains_key(value) {
+                panic!("could not find assignment {value} for variable {var}");
+            }
+            for (other, literal) in indicators.iter() {
+                if other != value {
+                    scale_literal(&mut weights, *literal, 0.0);
+                }
+            }
+        }
+        weights
+    }
+
+    /// Computes the Bayes factor Pr(e1) / Pr(e2) of two pieces of evidence,
+    /// i.e. how many times better `e1` is explained by the network than
+    /// `e2`. The result is NaN if Pr(e2) = 0, rather than infinite or NaN
+    /// depending on Pr(e1).
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C, where B is never T
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[1.0], [0.0]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// let c_t = HashMap::from([(String::from("C"), String::from("T"))]);
+    /// let c_f = HashMap::from([(String::from("C"), String::from("F"))]);
+    /// let b_t = HashMap::from([(String::from("B"), String::from("T"))]);
+    ///
+    /// // Pr(C = T) / Pr(C = F) = (0.5 * 0.1 + 0.5 * 0.7) / (0.5 * 0.9 + 0.5 * 0.3)
+    /// assert!((bn.bayes_factor(&c_t, &c_f) - 0.4 / 0.6).abs() < 1e-9);
+    /// assert_eq!(bn.bayes_factor(&b_t, &c_t), 0.0);
+    /// assert!(bn.bayes_factor(&c_t, &b_t).is_nan());
+    /// ```
+    pub fn bayes_factor(&self, e1: &HashMap<String, String>, e2: &HashMap<String, String>) -> f64 {
+        let denominator = self.probability(e2);
+        if denominator == 0.0 {
+            return f64::NAN;
+        }
+        self.probability(e1) / denominator
+    }
+
+    /// Finds the `k` most probable full assignments that agree with
+    /// `evidence`, each with its joint probability Pr(assignment), in
+    /// decreasing order of probability.
+    ///
+    /// Runs a best-first search over the variables in encoding order,
+    /// extending the most promising partial assignment first; a partial
+    /// assignment is scored by its CPT entries so far times the largest
+    /// entry each remaining variable could contribute, so complete
+    /// assignments come off the frontier in order. Assignments of
+    /// probability 0 are never returned, so fewer than `k` may come back;
+    /// divide by `probability(evidence)` for posterior probabilities.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// let c_t = HashMap::from([(String::from("C"), String::from("T"))]);
+    /// let explanations = bn.top_k_explanations(3, &c_t);
+    ///
+    /// // Pr(A, B, C = T) is 0.225, 0.0875, 0.075, 0.0125 for TT, TF, FT, FF
+    /// let found: Vec<(&str, &str, f64)> = explanations
+    ///     .iter()
+    ///     .map(|(a, p)| (a["A"].as_str(), a["B"].as_str(), *p))
+    ///     .collect();
+    /// assert_eq!(found.len(), 3);
+    /// let expected = [("T", "T", 0.225), ("T", "F", 0.0875), ("F", "T", 0.075)];
+    /// for ((a, b, p), (ea, eb, ep)) in found.iter().zip(expected) {
+    ///     assert_eq!((*a, *b), (ea, eb));
+    ///     assert!((p - ep).abs() < 1e-9);
+    /// }
+    /// assert_eq!(bn.top_k_explanations(10, &c_t).len(), 4);
+    /// ```
+    pub fn top_k_explanations(
+        &self,
+        k: usize,
+        evidence: &HashMap<String, String>,
+    ) -> Vec<(HashMap<String, String>, f64)> {
+        for (var, value) in evidence.iter() {
+            match self.indicators.get(var) {
+                None => panic!("could not find variable {var}"),
+                Some(values) if !values.contains_key(value) => {
+                    panic!("could not find assignment {value} for variable {var}")
+                }
+                _ => {}
+            }
+        }
+        let position: HashMap<&str, usize> = self
+            .order
+            .iter()
+            .enumerate()
+            .map(|(i, var)| (var.as_str(), i))
+            .collect();
+        // the values each variable may still take
+        let candidates: Vec<Vec<&String>> = self
+            .order
+            .iter()
+            .map(|var| match evidence.get(var) {
+                Some(value) => vec![value],
+                None => self.states[self.variable_index(var)].1.iter().collect(),
+            })
+            .collect();
+        // bounds[i]: the largest probability variables order[i..] can add
+        let mut bounds = vec![1.0; self.order.len() + 1];
+        for i in (0..self.order.len()).rev() {
+            let best = self.parameters[&self.order[i]]
+                .iter()
+                .filter(|p| candidates[i].contains(&&p.value))
+                .map(|p| p.probability)
+                .fold(0.0, f64::max);
+            bounds[i] = bounds[i + 1] * best;
+        }
+
+        let mut explanations: Vec<(HashMap<String, String>, f64)> = Vec::new();
+        let mut frontier = BinaryHeap::from([PartialExplanation {
+            bound: bounds[0],
+            probability: 1.0,
+            values: Vec::new(),
+        }]);
+        while explanations.len() < k {
+            let Some(PartialExplanation {
+                probability,
+                values,
+                ..
+            }) = frontier.pop()
+            else {
+                break;
+            };
+            let i = values.len();
+            if i == self.order.len() {
+                let assignment: HashMap<String, String> = self
+                    .order
+                    .iter()
+                    .cloned()
+                    .zip(values.into_iter().cloned())
+                    .collect();
+                // asserted clauses may rule out what the CPTs allow
+                if !self.constrained || self.probability(&assignment) > 0.0 {
+                    explanations.push((assignment, probability));
+                }
+                continue;
+            }
+            let var = &self.order[i];
+            for value in candidates[i].iter() {
+                let entry = self.parameters[var]
+                    .iter()
+                    .find(|p| {
+                        p.value == **value
+                            && p.parent_assignment
+                                .iter()
+                                .all(|(parent, v)| values[position[parent.as_str()]] == v)
+                    })
+                    .map_or(0.0, |p| p.probability);
+                let probability = probability * entry;
+                if probability > 0.0 {
+                    let mut values = values.clone();
+                    values.push(*value);
+                    frontier.push(PartialExplanation {
+                        bound: probability * bounds[i + 1],
+                        probability,
+                        values,
+                    });
+                }
+            }
+        }
+        explanations
+    }
+
+    /// Finds the most probable explanation of `evidence`: the full assignment
+    /// that agrees with it and has the highest joint probability, together
+    /// with that probability. Ties are broken arbitrarily. As with `map`, if
+    /// the evidence has probability 0, returns an empty assignment and NaN.
+    ///
+    /// The weighted model count in the [`MaxProductSemiring`] is the
+    /// probability of the best completion of the evidence, so the CNF is
+    /// compiled once and each variable, in topological order, takes the
+    /// value whose count is largest, at one count per value. See
+    /// `top_k_explanations` for the runners-up.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// let (assignment, p) = bn.mpe(&HashMap::new());
+    /// // Pr(A = F, B = T, C = F) = 0.5 * 0.75 * 0.8
+    /// assert_eq!(assignment["A"], "F");
+    /// assert_eq!(assignment["B"], "T");
+    /// assert_eq!(assignment["C"], "F");
+    /// assert!((p - 0.3).abs() < 1e-9);
+    /// ```
+    pub fn mpe(&self, evidence: &HashMap<String, String>) -> (HashMap<String, String>, f64) {
+        self.with_model_counter(|wmc| {
+            let best = |assignment: &HashMap<String, String>| {
+                wmc(&self.semiring_params(assignment, MaxProductSemiring)).0
+            };
+            let mut p = best(evidence);
+            if p == 0.0 {
+                return (HashMap::new(), f64::NAN);
+            }
+            let mut assignment = evidence.clone();
+            for var in self.order.iter() {
+                if assignment.contains_key(var) {
+                    continue;
+                }
+                let (value, value_p) = self.states[self.variable_index(var)]
+                    .1
+                    .iter()
+                    .map(|value| {
+                        assignment.insert(var.clone(), value.clone());
+                        (value, best(&assignment))
+                    })
+                    .max_by(|(_, p), (_, q)| p.total_cmp(q))
+                    .unwrap();
+                assignment.insert(var.clone(), value.clone());
+                p = value_p;
+            }
+            (assignment, p)
+        })
+    }
+
+    /// Finds the maximum a posteriori assignment to `query_vars` given
+    /// `evidence`, summing out every other variable, and returns it with its
+    /// posterior probability. The returned assignment has exactly the
+    /// variables of `query_vars`; ties are broken arbitrarily. If the
+    /// evidence has probability 0, returns an empty assignment and NaN.
+    ///
+    /// Runs a best-first search over assignments to `query_vars` in order,
+    /// scoring each partial assignment by its probability with the evidence,
+    /// which bounds that of every completion; the CNF is compiled once and
+    /// each step costs one weighted model count per value. Unlike
+    /// `joint_marginal`, this need not visit every assignment.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// // B is summed out rather than maximized
+    /// let (assignment, p) = bn.map(&[String::from("A")], &HashMap::from([
+    ///     (String::from("C"), String::from("T"))
+    /// ]));
+    /// assert_eq!(assignment, HashMap::from([(String::from("A"), String::from("T"))]));
+    /// // Pr(A = T | C = T) = 0.5 * (0.25 * 0.7 + 0.75 * 0.6) / 0.4
+    /// assert!((p - 0.78125).abs() < 1e-9);
+    /// ```
+    pub fn map(
+        &self,
+        query_vars: &[String],
+        evidence: &HashMap<String, String>,
+    ) -> (HashMap<String, String>, f64) {
+        self.with_model_counter(|wmc| {
+            let count =
+                |assignment: &HashMap<String, String>| wmc(&self.evidence_params(assignment)).0;
+            self.best_first_map(query_vars, evidence, count)
+        })
+    }
+
+    /// the search of `map`, with `count` computing Pr(assignment)
+    fn best_first_map(
+        &self,
+        query_vars: &[String],
+        evidence: &HashMap<String, String>,
+        count: impl Fn(&HashMap<String, String>) -> f64,
+    ) -> (HashMap<String, String>, f64) {
+        let z = count(evidence);
+        if z == 0.0 {
+            return (HashMap::new(), f64::NAN);
+        }
+        // the values each query variable may take
+        let candidates: Vec<Vec<&String>> = query_vars
+            .iter()
+            .map(|var| match evidence.get(var) {
+                Some(value) => vec![value],
+                None => self.states[self.variable_index(var)].1.iter().collect(),
+            })
+            .collect();
+
+        let mut frontier = BinaryHeap::from([PartialExplanation {
+            bound: z,
+            probability: z,
+            values: Vec::new(),
+        }]);
+        while let Some(PartialExplanation {
+            probability,
+            values,
+            ..
+        }) = frontier.pop()
+        {
+            let i = values.len();
+            if i == query_vars.len() {
+                let assignment = query_vars
+                    .iter()
+                    .cloned()
+                    .zip(values.into_iter().cloned())
+                    .collect();
+                return (assignment, probability / z);
+            }
+            for value in candidates[i].iter() {
+                let mut values = values.clone();
+                values.push(*value);
+                let mut assignment = evidence.clone();
+                for (var, value) in query_vars.iter().zip(values.iter()) {
+                    assignment.insert(var.clone(), (*value).clone());
+                }
+                let probability = count(&assignment);
+                if probability > 0.0 {
+                    frontier.push(PartialExplanation {
+                        bound: probability,
+                        probability,
+                        values,
+                    });
+                }
+            }
+        }
+        unreachable!("evidence of positive probability has a completion of positive probability")
+    }
+
+    /// Computes the probability that every variable in `assignment` takes its
+    /// assigned value, i.e. Pr(assignment), by weighted model counting.
+    ///
+    /// `assignment` may mention any subset of the network's variables. When it
+    /// assigns every variable, the answer is read off the CPTs by the chain
+    /// rule instead of by a weighted model count.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// let p = bn.probability(&HashMap::from([
+    ///     (String::from("A"), String::from("T")),
+    ///     (String::from("C"), String::from("T")),
+    /// ]));
+    /// assert!((p - 0.5 * (0.25 * 0.7 + 0.75 * 0.6)).abs() < 1e-9);
+    /// ```
+    pub fn probability(&self, assignment: &HashMap<String, String>) -> f64 {
+        if !self.constrained
+            && assignment.len() == self.order.len()
+            && self.order.iter().all(|var| assignment.contains_key(var))
+        {
+            return self.joint_probability(assignment);
+        }
+        self.weighted_model_counts(&[self.evidence_params(assignment)])[0]
+    }
+
+    /// Computes Pr(assignment) for an assignment to every variable as the
+    /// product of one CPT entry per variable
+    fn joint_probability(&self, assignment: &HashMap<String, String>) -> f64 {
+        self.order
+            .iter()
+            .map(|var| {
+                let value = &assignment[var];
+                self.parameters[var]
+                    .iter()
+                    .find(|p| {
+                        p.value == *value
+                            && p.parent_assignment
+                                .iter()
+                                .all(|(parent, v)| assignment[parent] == *v)
+                    })
+                    .unwrap_or_else(|| {
+                        panic!("could not find assignment {value} for variable {var}")
+                    })
+                    .probability
+            })
+            .product()
+    }
+
+    /// the position of `name` in the network's `variables`, for use with
+    /// `marginal_by_index`
+    pub fn variable_index(&self, name: &str) -> usize {
+        self.states
+            .iter()
+            .position(|(v, _)| v == name)
+            .unwrap_or_else(|| panic!("could not find variable {name}"))
+    }
+
+    /// the position of `value` among the states of `var`, for use with
+    /// `marginal_by_index`
+    pub fn value_index(&self, var: &str, value: &str) -> usize {
+        self.states[self.variable_index(var)]
+            .1
+            .iter()
+            .position(|s| s == value)
+            .unwrap_or_else(|| panic!("could not find assignment {value} for variable {var}"))
+    }
+
+    /// Computes Pr(var = value) by weighted model counting; see
+    /// `probability` for queries over several variables
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// // Pr(C = T) = sum over A, B of Pr(A) Pr(B) Pr(C = T | A, B)
+    /// let expected = 0.5 * 0.25 * 0.1 + 0.5 * 0.75 * 0.2 + 0.5 * 0.25 * 0.7 + 0.5 * 0.75 * 0.6;
+    /// assert!((bn.marginal("C", "T") - expected).abs() < 1e-9);
+    /// assert!((bn.marginal("B", "F") - 0.25).abs() < 1e-9);
+    /// ```
+    pub fn marginal(&self, var: &str, value: &str) -> f64 {
+        self.probability(&HashMap::from([(var.to_string(), value.to_string())]))
+    }
+
+    /// Computes Pr(query | evidence) for `query` given as `(variable, value)`,
+    /// as the ratio of the weighted model counts of query and evidence
+    /// together and of evidence alone, both counted over one compilation.
+    /// Returns 0 if the query contradicts the evidence, and NaN if the
+    /// evidence has probability 0.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[1.0], [0.0]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// let a_t = (String::from("A"), String::from("T"));
+    /// let c_t = HashMap::from([(String::from("C"), String::from("T"))]);
+    /// let b_t = HashMap::from([(String::from("B"), String::from("T"))]);
+    ///
+    /// // Pr(A = T | C = T) = 0.5 * 0.7 / (0.5 * 0.1 + 0.5 * 0.7)
+    /// assert!((bn.posterior(&a_t, &c_t) - 0.875).abs() < 1e-9);
+    /// assert!(bn.posterior(&a_t, &b_t).is_nan());
+    /// ```
+    pub fn posterior(&self, query: &(String, String), evidence: &HashMap<String, String>) -> f64 {
+        let (var, value) = query;
+        let mut joint = evidence.clone();
+        joint.insert(var.clone(), value.clone());
+        let counts = self
+            .weighted_model_counts(&[self.evidence_params(evidence), self.evidence_params(&joint)]);
+        if counts[0] == 0.0 {
+            return f64::NAN;
+        }
+        if matches!(evidence.get(var), Some(observed) if observed != value) {
+            return 0.0;
+        }
+        counts[1] / counts[0]
+    }
+
+    /// Computes Pr(query | soft evidence) for `query` given as
+    /// `(variable, value)`, where `soft` maps variables to one likelihood per
+    /// state, in the order of `BayesianNetwork::all_possible_assignments`.
+    /// Each likelihood multiplies the weight of its state's indicator, so only
+    /// the ratios between a variable's likelihoods matter: uniform likelihoods
+    /// leave the marginal unchanged, and a single nonzero likelihood is hard
+    /// evidence. Returns NaN if the soft evidence has probability 0.
+    ///
+    /// Panics if a variable in `soft` is not in the network or has a number
+    /// of likelihoods other than its number of states.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// let query = (String::from("A"), String::from("T"));
+    /// let soft = |likelihoods: Vec<f64>| HashMap::from([(String::from("C"), likelihoods)]);
+    ///
+    /// // a sensor reading C = T with likelihood ratio 0.8 : 0.2
+    /// // Pr(A = T | soft) = 0.2875 / (0.2875 + 0.1525)
+    /// let p = bn.posterior_with_soft_evidence(&query, &soft(vec![0.2, 0.8]));
+    /// assert!((p - 0.2875 / 0.44).abs() < 1e-9);
+    /// // uniform likelihoods carry no information
+    /// let p = bn.posterior_with_soft_evidence(&query, &soft(vec![0.5, 0.5]));
+    /// assert!((p - 0.5).abs() < 1e-9);
+    /// // and all the weight on one state is hard evidence
+    /// let p = bn.posterior_with_soft_evidence(&query, &soft(vec![0.0, 1.0]));
+    /// assert!((p - 0.78125).abs() < 1e-9);
+    /// ```
+    pub fn posterior_with_soft_evidence(
+        &self,
+        query: &(String, String),
+        soft: &HashMap<String, Vec<f64>>,
+    ) -> f64 {
+        let mut weights = self.weights.clone();
+        for (var, likelihoods) in soft.iter() {
+            let values = &self.states[self.variable_index(var)].1;
+            if likelihoods.len() != values.len() {
+                panic!(
+                    "expected {} likelihoods for variable {var}, got {}",
+                    values.len(),
+                    likelihoods.len()
+                );
+            }
+            for (value, likelihood) in values.iter().zip(likelihoods.iter()) {
+                scale_literal(&mut weights, self.indicators[var][value], *likelihood);
+            }
+        }
+        let (var, value) = query;
+        let joint = HashMap::from([(var.clone(), value.clone())]);
+        let counts = self.weighted_model_counts(&[
+            self.evidence_params_with(weights.clone(), &HashMap::new()),
+            self.evidence_params_with(weights, &joint),
+        ]);
+        if counts[0] == 0.0 {
+            return f64::NAN;
+        }
+        counts[1] / counts[0]
+    }
+
+    /// Computes Pr(var = value) for the variable at `var_idx` in the
+    /// network's `variables` and its state at `value_idx`, so callers that
+    /// work with indices never handle names.
+    ///
+    /// The first call compiles the CNF once to count the marginal of every
+    /// value of every variable, and later calls look theirs up, so looping
+    /// over all indices costs a single compilation.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// let c = bn.variable_index("C");
+    /// let t = bn.value_index("C", "T");
+    ///
+    /// assert_eq!((c, t), (2, 1));
+    /// assert!((bn.marginal_by_index(c, t) - 0.4).abs() < 1e-9);
+    /// ```
+    pub fn marginal_by_index(&self, var_idx: usize, value_idx: usize) -> f64 {
+        let (var, values) = self
+            .states
+            .get(var_idx)
+            .unwrap_or_else(|| panic!("could not find variable with index {var_idx}"));
+        if value_idx >= values.len() {
+            panic!("could not find assignment with index {value_idx} for variable {var}");
+        }
+        let table = self.marginal_table.get_or_init(|| {
+            let params: Vec<WmcParams<RealSemiring>> = self
+                .states
+                .iter()
+                .flat_map(|(var, values)| {
+                    values.iter().map(move |value| {
+                        self.evidence_params(&HashMap::from([(var.clone(), value.clone())]))
+                    })
+                })
+                .collect();
+            let mut counts = self.weighted_model_counts(&params).into_iter();
+            self.states
+                .iter()
+                .map(|(_, values)| values.iter().map(|_| counts.next().unwrap()).collect())
+                .collect()
+        });
+        table[var_idx][value_idx]
+    }
+
+    /// Computes the joint distribution over `vars` given `evidence`, summing out
+    /// every other variable. Each key lists one assignment to `vars` as
+    /// `(variable, value)` pairs in the order of `vars`, and the values are
+    /// normalized by Pr(evidence). Assignments that contradict the evidence
+    /// get probability 0; if the evidence itself has probability 0, every
+    /// entry is NaN.
+    ///
+    /// The CNF is compiled once and reused for every entry of the table.
+    ///
+    /// # Panics
+    /// Panics if the table would have more than `MAX_JOINT_TABLE_SIZE` entries.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// let vars = [String::from("A"), String::from("B")];
+    /// let evidence = HashMap::from([(String::from("C"), String::from("T"))]);
+    /// let joint = bn.joint_marginal(&vars, &evidence);
+    ///
+    /// assert_eq!(joint.len(), 4);
+    /// let a_t_b_t = vec![
+    ///     (String::from("A"), String::from("T")),
+    ///     (String::from("B"), String::from("T")),
+    /// ];
+    /// // Pr(A = T, B = T, C = T) / Pr(C = T) = (0.5 * 0.75 * 0.6) / 0.4
+    /// assert!((joint[&a_t_b_t] - 0.5625).abs() < 1e-9);
+    /// assert!((joint.values().sum::<f64>() - 1.0).abs() < 1e-9);
+    /// ```
+    pub fn joint_marginal(
+        &self,
+        vars: &[String],
+        evidence: &HashMap<String, String>,
+    ) -> HashMap<Vec<(String, String)>, f64> {
+        let mut table: Vec<Vec<(String, String)>> = vec![Vec::new()];
+        for var in vars {
+            let values = self
+                .indicators
+                .get(var)
+                .unwrap_or_else(|| panic!("could not find variable {var}"));
+            let size = table.len() * values.len();
+            if size > MAX_JOINT_TABLE_SIZE {
+                panic!("joint table over {vars:?} exceeds {MAX_JOINT_TABLE_SIZE} entries");
+            }
+            let mut values: Vec<&String> = values.keys().collect();
+            values.sort();
+            table = table
+                .into_iter()
+                .flat_map(|row| {
+                    values.iter().map(move |value| {
+                        let mut row = row.clone();
+                        row.push((var.clone(), (*value).clone()));
+                        row
+                    })
+                })
+                .collect();
+        }
+
+        // the first count is Pr(evidence); the rest are Pr(row, evidence) for
+        // each row that does not contradict the evidence
+        let mut params = vec![self.evidence_params(evidence)];
+        let mut consistent: Vec<bool> = Vec::new();
+        for row in table.iter() {
+            let mut assignment = evidence.clone();
+            let agrees = row.iter().all(|(var, value)| {
+                !matches!(assignment.insert(var.clone(), value.clone()), Some(old) if old != *value)
+            });
+            consistent.push(agrees);
+            if agrees {
+                params.push(self.evidence_params(&assignment));
+            }
+        }
+        let counts = self.weighted_model_counts(&params);
+        let z = counts[0];
+        let mut counts = counts[1..].iter();
+        table
+            .into_iter()
+            .zip(consistent)
+            .map(|(row, agrees)| {
+                let p = if agrees { *counts.next().unwrap() } else { 0.0 };
+                (row, p / z)
+            })
+            .collect()
+    }
+
+    /// Computes Pr(var = value | evidence) like `marginals`, but carries out
+    /// the weighted model counts in log space (see [`LogSemiring`]) and only
+    /// exponentiates the final ratio.
+    ///
+    /// Prefer this when Pr(evidence) may be smaller than about 1e-300, e.g.
+    /// when observing many variables of a large network: the ordinary
+    /// weighted model count then underflows to 0, and the query returns NaN
+    /// or 0 instead of the true answer. It is somewhat slower, since every
+    /// addition takes a logarithm. As elsewhere, the result is NaN if the
+    /// evidence truly has probability 0.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    /// use std::collections::HashMap;
+    ///
+    /// // models a cause A of two extremely rare effects X and Y
+    /// static NETWORK: &str = r#"{
+    ///     "network": "rare_effects",
+    ///     "variables": ["A", "X", "Y"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "X": [[1.0, 1.0], [1e-200, 1e-190]],
+    ///         "Y": [[1.0, 1.0], [1e-200, 1e-190]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "X": ["F", "T"],
+    ///         "Y": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "X": ["A"],
+    ///         "Y": ["A"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// let evidence = HashMap::from([
+    ///     (String::from("X"), String::from("T")),
+    ///     (String::from("Y"), String::from("T")),
+    /// ]);
+    ///
+    /// // Pr(X = T, Y = T) is about 0.5 * 1e-380, which underflows to 0...
+    /// assert!(bn.marginals(&evidence)["A"]["F"].is_nan());
+    /// // ...but Pr(A = F | X = T, Y = T) = 1e-400 / (1e-400 + 1e-380) in log space
+    /// let p = bn.marginal_logspace("A", "F", &evidence);
+    /// assert!((p / 1e-20 - 1.0).abs() < 1e-6);
+    /// assert!((bn.marginal_logspace("A", "T", &evidence) - 1.0).abs() < 1e-9);
+    /// ```
+    pub fn marginal_logspace(
+        &self,
+        var: &str,
+        value: &str,
+        evidence: &HashMap<String, String>,
+    ) -> f64 {
+        let log_params = |evidence: &HashMap<String, String>| {
+            self.semiring_params(evidence, LogSemiring::from_real)
+        };
+        let mut joint = evidence.clone();
+        let agrees =
+            !matches!(joint.insert(var.to_string(), value.to_string()), Some(old) if old != value);
+        let joint_params = log_params(&joint);
+        self.with_model_counter(|wmc| {
+            let z = wmc(&log_params(evidence)).0;
+            let count = if agrees {
+                wmc(&joint_params).0
+            } else {
+                f64::NEG_INFINITY
+            };
+            (count - z).exp()
+        })
+    }
+
+    /// Computes Pr(var = value | evidence) for every state of `var`, in the
+    /// order of its states. The CNF is compiled once and each entry is
+    /// normalized by the sum of the entries rather than by a separate
+    /// Pr(evidence), so the result sums to 1 up to rounding. If the evidence
+    /// has probability 0, every entry is NaN.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// let evidence = HashMap::from([(String::from("C"), String::from("T"))]);
+    /// let a = bn.distribution("A", &evidence);
+    ///
+    /// // Pr(A = T | C = T) = 0.5 * (0.25 * 0.7 + 0.75 * 0.6) / 0.4
+    /// assert!((a[1] - 0.78125).abs() < 1e-9);
+    /// assert!((a[0] + a[1] - 1.0).abs() < 1e-9);
+    /// ```
+    pub fn distribution(&self, var: &str, evidence: &HashMap<String, String>) -> Vec<f64> {
+        let values = &self.states[self.variable_index(var)].1;
+        let mut params: Vec<WmcParams<RealSemiring>> = Vec::new();
+        let mut consistent: Vec<bool> = Vec::new();
+        for value in values.iter() {
+            let agrees = !matches!(evidence.get(var), Some(observed) if observed != value);
+            consistent.push(agrees);
+            if agrees {
+                let mut assignment = evidence.clone();
+                assignment.insert(var.to_string(), value.clone());
+                params.push(self.evidence_params(&assignment));
+            }
+        }
+        let counts = self.weighted_model_counts(&params);
+        let total: f64 = counts.iter().sum();
+        let mut counts = counts.iter();
+        consistent
+            .into_iter()
+            .map(|agrees| {
+                let count = if agrees { *counts.next().unwrap() } else { 0.0 };
+                count / total
+            })
+            .collect()
+    }
+
+    /// Computes Pr(var = value | evidence) for every value of every variable,
+    /// as a map from variable to value to probability. Observed variables get
+    /// probability 1 for their observed value and 0 otherwise; if the evidence
+    /// itself has probability 0, every entry is NaN.
+    ///
+    /// The CNF is compiled once and every entry shares the normalizer
+    /// Pr(evidence).
+    pub fn marginals(
+        &self,
+        evidence: &HashMap<String, String>,
+    ) -> HashMap<String, HashMap<String, f64>> {
+        let mut params = vec![self.evidence_params(evidence)];
+        let mut queries: Vec<(&String, &String)> = Vec::new();
+        for (var, values) in self.indicators.iter() {
+            if evidence.contains_key(var) {
+                continue;
+            }
+            for value in values.keys() {
+                let mut assignment = evidence.clone();
+                assignment.insert(var.clone(), value.clone());
+                params.push(self.evidence_params(&assignment));
+                queries.push((var, value));
+            }
+        }
+        let counts = self.weighted_model_counts(&params);
+        let z = counts[0];
+
+        let mut marginals: HashMap<String, HashMap<String, f64>> = HashMap::new();
+        for ((var, value), count) in queries.into_iter().zip(counts[1..].iter()) {
+            marginals
+                .entry(var.clone())
+                .or_default()
+                .insert(value.clone(), count / z);
+        }
+        for (var, observed) in evidence.iter() {
+            let values = self.indicators[var]
+                .keys()
+                .map(|value| {
+                    let count = if value == observed { z } else { 0.0 };
+                    (value.clone(), count / z)
+                })
+                .collect();
+            marginals.insert(var.clone(), values);
+        }
+        marginals
+    }
+
+    /// Computes Pr(var = value) for every value of every variable, as a map
+    /// from variable to value to probability. This is `marginals` without
+    /// evidence: the CNF is compiled into a BDD once and every entry is one
+    /// weighted model count over that BDD, so it is far cheaper than calling
+    /// `marginal`, which compiles afresh, once per value.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// let marginals = bn.all_marginals();
+    ///
+    /// assert!((marginals["B"]["T"] - 0.75).abs() < 1e-9);
+    /// // Pr(C = T) = 0.5 * (0.25 * 0.1 + 0.75 * 0.2) + 0.5 * (0.25 * 0.7 + 0.75 * 0.6)
+    /// assert!((marginals["C"]["T"] - 0.4).abs() < 1e-9);
+    /// assert_eq!(marginals["A"].len(), 2);
+    /// ```
+    pub fn all_marginals(&self) -> HashMap<String, HashMap<String, f64>> {
+        self.marginals(&HashMap::new())
+    }
+
+    /// Computes the posterior marginal of every variable after observing
+    /// `obs_var = obs_value`. This is exactly `marginals` with a one-element
+    /// evidence map, and likewise compiles the CNF once and computes the
+    /// normalizer Pr(obs_var = obs_value) once for all entries, so it is
+    /// cheap enough to call every time a single observation changes.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// let marginals = bn.marginals_given_one("C", "T");
+    ///
+    /// // Pr(A = T | C = T) = 0.5 * (0.25 * 0.7 + 0.75 * 0.6) / 0.4
+    /// assert!((marginals["A"]["T"] - 0.78125).abs() < 1e-9);
+    /// assert!((marginals["B"]["T"] - 0.75).abs() < 1e-9);
+    /// assert_eq!(marginals["C"]["T"], 1.0);
+    /// assert_eq!(marginals["C"]["F"], 0.0);
+    /// ```
+    pub fn marginals_given_one(
+        &self,
+        obs_var: &str,
+        obs_value: &str,
+    ) -> HashMap<String, HashMap<String, f64>> {
+        self.marginals(&HashMap::from([(
+            obs_var.to_string(),
+            obs_value.to_string(),
+        )]))
+    }
+
+    /// Computes the expected value of `var` given `evidence`, reading each of
+    /// its state labels as a number: the sum of value * Pr(var = value | evidence).
+    /// The result is NaN if the evidence has probability 0.
+    ///
+    /// Returns an error if `var` is not in the network or any of its states
+    /// does not parse as an `f64`.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C, where C counts something
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["0", "10"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    ///
+    /// // Pr(C = 10) = 0.4
+    /// assert!((bn.expected_value("C", &HashMap::new()).unwrap() - 4.0).abs() < 1e-9);
+    /// let a_t = HashMap::from([(String::from("A"), String::from("T"))]);
+    /// // Pr(C = 10 | A = T) = 0.25 * 0.7 + 0.75 * 0.6
+    /// assert!((bn.expected_value("C", &a_t).unwrap() - 6.25).abs() < 1e-9);
+    /// assert!(bn.expected_value("A", &HashMap::new()).is_err());
+    /// ```
+    pub fn expected_value(
+        &self,
+        var: &str,
+        evidence: &HashMap<String, String>,
+    ) -> Result<f64, BnError> {
+        let values = self
+            .indicators
+            .get(var)
+            .ok_or_else(|| BnError::UnknownVariable(var.to_string()))?;
+        for value in values.keys() {
+            if value.trim().parse::<f64>().is_err() {
+                return Err(BnError::NonNumericState(var.to_string(), value.clone()));
+            }
+        }
+        Ok(self
+            .joint_marginal(&[var.to_string()], evidence)
+            .into_iter()
+            .map(|(row, p)| row[0].1.trim().parse::<f64>().unwrap() * p)
+            .sum())
+    }
+
+    /// Conditions on range evidence about an ordinal variable, such as
+    /// "`var` > 2", by asserting that `var` takes one of the states whose
+    /// label, read as a number, compares to `threshold` as `op`. For example,
+    /// `Ordering::Greater` selects the states above `threshold`; "at least 3"
+    /// on integer states is `Ordering::Greater` with a threshold of 2.5.
+    ///
+    /// The returned CNF answers queries jointly with the range evidence:
+    /// `probability` gives Pr(assignment, range) and the normalized queries
+    /// (e.g. `joint_marginal`) condition on the range as well as their own
+    /// evidence. Calling this again adds another range, e.g. to bound `var`
+    /// from both sides. If no state matches, the evidence is impossible.
+    ///
+    /// Returns an error if `var` is not in the network or any of its states
+    /// does not parse as an `f64`.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    /// use std::{cmp::Ordering, collections::HashMap};
+    ///
+    /// // models a cause A of a count N
+    /// static NETWORK: &str = r#"{
+    ///     "network": "ordinal",
+    ///     "variables": ["A", "N"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "N": [[0.6, 0.2], [0.3, 0.3], [0.1, 0.5]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "N": ["1", "2", "3"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "N": ["A"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// // N >= 2
+    /// let at_least_two = bn.with_ordinal_evidence("N", Ordering::Greater, 1.5).unwrap();
+    /// let a_t = HashMap::from([(String::from("A"), String::from("T"))]);
+    ///
+    /// // Pr(A = T, N >= 2) = 0.5 * (0.3 + 0.5)
+    /// assert!((at_least_two.probability(&a_t) - 0.4).abs() < 1e-9);
+    /// // Pr(A = T | N >= 2) = 0.4 / (0.4 + 0.5 * (0.3 + 0.1))
+    /// let posterior = at_least_two.joint_marginal(&[String::from("A")], &HashMap::new());
+    /// let a_t = vec![(String::from("A"), String::from("T"))];
+    /// assert!((posterior[&a_t] - 2.0 / 3.0).abs() < 1e-9);
+    /// assert!(bn.with_ordinal_evidence("A", Ordering::Less, 0.0).is_err());
+    /// ```
+    pub fn with_ordinal_evidence(
+        &self,
+        var: &str,
+        op: Ordering,
+        threshold: f64,
+    ) -> Result<BayesianNetworkCNF, BnError> {
+        let values = self
+            .indicators
+            .get(var)
+            .ok_or_else(|| BnError::UnknownVariable(var.to_string()))?;
+        let mut clause: Vec<Literal> = Vec::new();
+        for (value, literal) in values.iter() {
+            let number = value
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| BnError::NonNumericState(var.to_string(), value.clone()))?;
+            if number.partial_cmp(&threshold) == Some(op) {
+                clause.push(*literal);
+            }
+        }
+        let mut clauses = self.cnf.clauses().to_vec();
+        clauses.push(clause);
+        Ok(BayesianNetworkCNF {
+            cnf: Cnf::new(clauses),
+            // the components' CNFs do not carry the range
+            components: Vec::new(),
+            constrained: true,
+            marginal_table: OnceLock::new(),
+            ..self.clone()
+        })
+    }
+
+    /// Compiles the CNF once and passes `f` a [`RunningEvidence`] over it that
+    /// starts with no evidence. Each `observe` only re-weights the compiled
+    /// structure, so monitoring Pr(evidence) over a stream of observations
+    /// costs one weighted model count per observation; a sharp drop flags a
+    /// surprising observation.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// let likelihoods = bn.with_running_evidence(|running| {
+    ///     vec![
+    ///         running.observe("A", "F"),
+    ///         running.observe("B", "F"),
+    ///         running.observe("C", "T"),
+    ///     ]
+    /// });
+    ///
+    /// assert!((likelihoods[0] - 0.5).abs() < 1e-9);
+    /// assert!((likelihoods[1] - 0.125).abs() < 1e-9);
+    /// // C = T is unlikely given A = F and B = F
+    /// assert!((likelihoods[2] - 0.0125).abs() < 1e-9);
+    /// ```
+    pub fn with_running_evidence<T>(&self, f: impl FnOnce(&mut RunningEvidence<'_>) -> T) -> T {
+        self.with_model_counter(|count| {
+            let mut running = RunningEvidence {
+                cnf: self,
+                count,
+                params: self.evidence_params(&HashMap::new()),
+                evidence: HashMap::new(),
+            };
+            f(&mut running)
+        })
+    }
+
+    /// Suggests an order of the CNF variables for a decision diagram: the
+    /// network's variables in the min-fill elimination order of its moral
+    /// graph (see `BayesianNetwork::min_fill_ordering`), each contributing
+    /// all of its indicators, parameters, and encoding bits in turn. Variables
+    /// that share a factor are neighbours in the moral graph, so this keeps
+    /// the CNF variables of a CPT close to those of its parents, where the
+    /// default order (by label, i.e. topological) can pull them far apart.
+    /// Pass it to `with_bdd`.
+    pub fn var_order(&self) -> Vec<VarLabel> {
+        min_fill_order(&self.moral_graph)
+            .0
+            .iter()
+            .flat_map(|var| self.label_ranges[var].clone().map(VarLabel::new_usize))
+            .collect()
+    }
+
+    /// Compiles the CNF into a BDD whose variables are ordered by `order`, a
+    /// permutation of the CNF's labels such as `var_order`, and passes `f`
+    /// the BDD along with the order to count it under. The size of a BDD
+    /// depends heavily on its variable order, which the queries of this type
+    /// leave to rsdd's default.
+    /// ```
+    /// use rsdd::repr::ddnnf::DDNNFPtr;
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// let order = bn.var_order();
+    /// let mut labels: Vec<usize> = order.iter().map(|l| l.value_usize()).collect();
+    /// labels.sort();
+    /// assert_eq!(labels, (0..bn.cnf().num_vars()).collect::<Vec<usize>>());
+    ///
+    /// let (z, nodes) = bn.with_bdd(&order, |bdd, order| {
+    ///     (bdd.wmc(order, bn.params()).0, bdd.count_nodes())
+    /// });
+    /// assert!((z - 1.0).abs() < 1e-9);
+    /// assert!(nodes > 0);
+    /// ```
+    pub fn with_bdd<T>(&self, order: &[VarLabel], f: impl FnOnce(BddPtr<'_>, &VarOrder) -> T) -> T {
+        let builder = RobddBuilder::<AllTable<BddPtr>>::new(VarOrder::new(order));
+        let bdd = builder.compile_cnf(&self.cnf);
+        f(bdd, builder.get_order())
+    }
+
+    /// Exports the weight `(low, high)` of every CNF variable, keyed by a name
+    /// that depends only on the network, not on how the CNF numbers its
+    /// variables:
+    /// - `I(X=x)` for the indicator of `X = x`, with weight `(1, 1)`
+    /// - `P(X=x)` for the parameter of a variable without parents, and
+    ///   `P(X=x|A=a,B=b)` for the parameter Pr(X = x | A = a, B = b), with
+    ///   parents in CPT order and weight `(1, Pr)`. A parameter shared by
+    ///   several parent assignments lists them all, separated by `;`.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let mut bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// let mut weights = bn.export_weights();
+    /// assert_eq!(weights.len(), 18);
+    /// assert_eq!(weights["I(A=T)"], (1.0, 1.0));
+    /// assert_eq!(weights["P(C=T|A=T,B=F)"], (1.0, 0.7));
+    ///
+    /// // make A certain to be true
+    /// weights.insert(String::from("P(A=F)"), (1.0, 0.0));
+    /// weights.insert(String::from("P(A=T)"), (1.0, 1.0));
+    /// bn.import_weights(&weights).unwrap();
+    /// let a_t = HashMap::from([(String::from("A"), String::from("T"))]);
+    /// assert!((bn.probability(&a_t) - 1.0).abs() < 1e-9);
+    /// ```
+    pub fn export_weights(&self) -> HashMap<String, (f64, f64)> {
+        self.weight_names
+            .iter()
+            .map(|(name, label)| (name.clone(), self.weights[label]))
+            .collect()
+    }
+
+    /// Sets the weights of the named CNF variables, leaving every other
+    /// weight as it was; see `export_weights` for the names. Parameter
+    /// weights also become the probabilities used by sampling and by
+    /// `probability` on full assignments.
+    ///
+    /// Returns an error, without changing anything, if a name is unknown.
+    pub fn import_weights(&mut self, weights: &HashMap<String, (f64, f64)>) -> Result<(), BnError> {
+        if let Some(unknown) = weights.keys().find(|n| !self.weight_names.contains_key(*n)) {
+            return Err(BnError::UnknownWeight(unknown.clone()));
+        }
+        for (name, weight) in weights.iter() {
+            self.weights.insert(self.weight_names[name], *weight);
+        }
+        for parameter in self.parameters.values_mut().flatten() {
+            parameter.probability = self.weights[&parameter.label].1;
+        }
+        self.marginal_table = OnceLock::new();
+        self.params = WmcParams::new(
+            self.weights
+                .iter()
+                .map(|(label, (low, high))| (*label, (RealSemiring(*low), RealSemiring(*high))))
+                .collect(),
+        );
+        for component in self.components.iter_mut() {
+            let restricted = weights
+                .iter()
+                .filter(|(name, _)| component.weight_names.contains_key(*name))
+                .map(|(name, weight)| (name.clone(), *weight))
+                .collect();
+            component.import_weights(&restricted)?;
+        }
+        Ok(())
+    }
+
+    /// The weighted model count of the CNF with no evidence, i.e. the total
+    /// probability mass of the network, which is 1 for a correctly encoded
+    /// network whose CPT columns sum to 1. After `with_ordinal_evidence` it
+    /// is the probability of the range evidence instead.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// assert!((bn.partition_function() - 1.0).abs() < 1e-9);
+    ///
+    /// // the column for A = T, B = F now sums to 0.9, and A = T, B = F has
+    /// // probability 0.5 * 0.25
+    /// let unnormalized = NETWORK.replace("0.3, 0.4", "0.2, 0.4");
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(&unnormalized));
+    /// assert!((bn.partition_function() - (1.0 - 0.1 * 0.5 * 0.25)).abs() < 1e-9);
+    /// ```
+    pub fn partition_function(&self) -> f64 {
+        self.weighted_model_counts(&[self.evidence_params(&HashMap::new())])[0]
+    }
+
+    /// Checks that the weighted model count with no evidence, i.e. the total
+    /// probability mass of the network, is within `tol` of 1. This holds for
+    /// every correctly encoded network whose CPT columns sum to 1, so a
+    /// failure points at an unnormalized CPT or an encoding bug.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// assert!(bn.partition_is_normalized(1e-9));
+    ///
+    /// // the column for A = T, B = F now sums to 0.9
+    /// let unnormalized = NETWORK.replace("0.3, 0.4", "0.2, 0.4");
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(&unnormalized));
+    /// assert!(!bn.partition_is_normalized(1e-9));
+    /// ```
+    pub fn partition_is_normalized(&self, tol: f64) -> bool {
+        (self.partition_function() - 1.0).abs() <= tol
+            && self
+                .components
+                .iter()
+                .all(|component| component.partition_is_normalized(tol))
+    }
+
+    /// Computes the expected reduction in the entropy of `target` from
+    /// observing `observe`, given `evidence`: the conditional mutual
+    /// information I(target; observe | evidence), in bits. It is 0 exactly
+    /// when the two are independent given the evidence, and is at most the
+    /// entropy of `target`, so ranking candidate variables by it picks the
+    /// most informative measurement to make next.
+    ///
+    /// The result is NaN if the evidence has probability 0.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// let no_evidence = HashMap::new();
+    /// let c_t = HashMap::from([(String::from("C"), String::from("T"))]);
+    ///
+    /// // A and B are independent until their common child is observed
+    /// assert!(bn.information_gain("A", "B", &no_evidence).abs() < 1e-9);
+    /// assert!(bn.information_gain("A", "B", &c_t) > 1e-3);
+    /// assert!(bn.information_gain("A", "C", &no_evidence) > 0.0);
+    /// // observing A itself removes all of its entropy: one bit for a fair coin
+    /// assert!((bn.information_gain("A", "A", &no_evidence) - 1.0).abs() < 1e-9);
+    /// ```
+    pub fn information_gain(
+        &self,
+        target: &str,
+        observe: &str,
+        evidence: &HashMap<String, String>,
+    ) -> f64 {
+        let joint = self.joint_marginal(&[target.to_string(), observe.to_string()], evidence);
+        let mut target_marginal: HashMap<&String, f64> = HashMap::new();
+        let mut observe_marginal: HashMap<&String, f64> = HashMap::new();
+        for (row, p) in joint.iter() {
+            *target_marginal.entry(&row[0].1).or_insert(0.0) += p;
+            *observe_marginal.entry(&row[1].1).or_insert(0.0) += p;
+        }
+        joint
+            .iter()
+            .map(|(row, p)| {
+                if *p == 0.0 {
+                    0.0
+                } else {
+                    p * (p / (target_marginal[&row[0].1] * observe_marginal[&row[1].1])).log2()
+                }
+            })
+            .sum()
+    }
+
+    /// Describes how Pr(query | evidence) depends jointly on two CPT
+    /// parameters, named `p1` and `p2` as in `export_weights`. The returned
+    /// function maps new values `(x, y)` of the two parameters to the query
+    /// probability.
+    ///
+    /// When a parameter changes, the other entries of its CPT column are
+    /// scaled proportionally so the column still sums to 1 (or, if the
+    /// parameter was 1, share the remainder equally). Because the weighted
+    /// model count is multilinear in the weights, Pr(query, evidence) and
+    /// Pr(evidence) are then both bilinear in `(x, y)`; each is pinned down by
+    /// its values at the four corners of the unit square, so the function
+    /// costs four pairs of weighted model counts over one compilation rather
+    /// than one compilation per point.
+    ///
+    /// # Panics
+    /// Panics if either name is not a parameter or both lie in the same
+    /// CPT column.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// let c_t = HashMap::from([(String::from("C"), String::from("T"))]);
+    /// let f = bn.sensitivity_2d("P(A=T)", "P(B=T)", &c_t, &HashMap::new());
+    ///
+    /// // at the network's own parameters, f is just Pr(C = T)
+    /// assert!((f(0.5, 0.75) - 0.4).abs() < 1e-9);
+    /// // with A and B certain, f reads off the CPT of C
+    /// assert!((f(1.0, 1.0) - 0.6).abs() < 1e-9);
+    /// assert!((f(0.0, 1.0) - 0.2).abs() < 1e-9);
+    /// ```
+    pub fn sensitivity_2d(
+        &self,
+        p1: &str,
+        p2: &str,
+        query: &HashMap<String, String>,
+        evidence: &HashMap<String, String>,
+    ) -> impl Fn(f64, f64) -> f64 {
+        let (label1, column1) = self.parameter_column(p1);
+        let (label2, column2) = self.parameter_column(p2);
+        if label1 == label2 || column1.contains(&label2) {
+            panic!("parameters {p1} and {p2} are in the same CPT column");
+        }
+        // the weights with parameter `label` set to `x`, co-varying `column`
+        let set = |weights: &mut HashMap<VarLabel, (f64, f64)>,
+                   label: VarLabel,
+                   column: &[VarLabel],
+                   x: f64| {
+            let old = self.weights[&label].1;
+            weights.insert(label, (1.0, x));
+            for other in column.iter() {
+                let p = if old == 1.0 {
+                    (1.0 - x) / column.len() as f64
+                } else {
+                    self.weights[other].1 * (1.0 - x) / (1.0 - old)
+                };
+                weights.insert(*other, (1.0, p));
+            }
+        };
+
+        let mut joint = evidence.clone();
+        let consistent = query.iter().all(|(var, value)| {
+            !matches!(joint.insert(var.clone(), value.clone()), Some(old) if old != *value)
+        });
+        let corners = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)];
+        let mut params = Vec::new();
+        for (x, y) in corners {
+            let mut weights = self.weights.clone();
+            set(&mut weights, label1, &column1, x);
+            set(&mut weights, label2, &column2, y);
+            params.push(self.evidence_params_with(weights.clone(), evidence));
+            params.push(self.evidence_params_with(weights, &joint));
+        }
+        let counts = self.weighted_model_counts(&params);
+        let denominator: Vec<f64> = counts.iter().step_by(2).copied().collect();
+        let numerator: Vec<f64> = counts
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .map(|c| if consistent { *c } else { 0.0 })
+            .collect();
+        let bilinear = |c: &[f64], x: f64, y: f64| {
+            c[0] * (1.0 - x) * (1.0 - y)
+                + c[1] * x * (1.0 - y)
+                + c[2] * (1.0 - x) * y
+                + c[3] * x * y
+        };
+        move |x, y| bilinear(&numerator, x, y) / bilinear(&denominator, x, y)
+    }
+
+    /// Finds the parameter variable named `name` (see `export_weights`) and
+    /// the other parameter variables in its CPT column(s)
+    fn parameter_column(&self, name: &str) -> (VarLabel, Vec<VarLabel>) {
+        let label = *self
+            .weight_names
+            .get(name)
+            .filter(|_| name.starts_with("P("))
+            .unwrap_or_else(|| panic!("could not find parameter {name}"));
+        let (variable, parameter) = self
+            .parameters
+            .iter()
+            .find_map(|(variable, ps)| ps.iter().find(|p| p.label == label).map(|p| (variable, p)))
+            .unwrap();
+        let mut column: Vec<VarLabel> = Vec::new();
+        for other in self.parameters[variable].iter() {
+            if other.value != parameter.value
+                && other.parent_assignment == parameter.parent_assignment
+                && !column.contains(&other.label)
+            {
+                column.push(other.label);
+            }
+        }
+        (label, column)
+    }
+
+    /// Lists the CPT entries whose values Pr(evidence) depends on, in
+    /// encoding order. Only ancestors of the evidence (and the evidence
+    /// variables themselves) matter, since every other variable sums out to 1;
+    /// among their entries, only those whose value and parent assignment
+    /// agree with the evidence take part in any consistent assignment.
+    /// Every other parameter could be set to any weight without changing
+    /// Pr(evidence).
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    ///
+    /// // Pr(A = T) only involves A's own entry
+    /// let a_t = HashMap::from([(String::from("A"), String::from("T"))]);
+    /// let active = bn.active_parameters(&a_t);
+    /// assert_eq!(active.len(), 1);
+    /// assert_eq!((active[0].variable.as_str(), active[0].value.as_str()), ("A", "T"));
+    ///
+    /// // Pr(A = T, C = T) involves both entries of B and the two entries
+    /// // Pr(C = T | A = T, B = b)
+    /// let a_t_c_t = HashMap::from([
+    ///     (String::from("A"), String::from("T")),
+    ///     (String::from("C"), String::from("T")),
+    /// ]);
+    /// assert_eq!(bn.active_parameters(&a_t_c_t).len(), 5);
+    /// ```
+    pub fn active_parameters(&self, evidence: &HashMap<String, String>) -> Vec<ParamRef> {
+        let agrees = |assignment: &HashMap<String, String>| {
+            assignment
+                .iter()
+                .all(|(var, value)| !matches!(evidence.get(var), Some(e) if e != value))
+        };
+        let mut relevant: Vec<&String> = Vec::new();
+        let mut frontier: Vec<&String> = evidence.keys().collect();
+        while let Some(var) = frontier.pop() {
+            if relevant.contains(&var) {
+                continue;
+            }
+            let parameters = self
+                .parameters
+                .get(var)
+                .unwrap_or_else(|| panic!("could not find variable {var}"));
+            if let Some(p) = parameters.first() {
+                frontier.extend(p.parent_assignment.keys());
+            }
+            relevant.push(var);
+        }
+        self.order
+            .iter()
+            .filter(|var| relevant.contains(var))
+            .flat_map(|var| {
+                self.parameters[var]
+                    .iter()
+                    .filter(|p| {
+                        !matches!(evidence.get(var), Some(e) if *e != p.value)
+                            && agrees(&p.parent_assignment)
+                    })
+                    .map(|p| ParamRef {
+                        variable: var.clone(),
+                        value: p.value.clone(),
+                        parent_assignment: p.parent_assignment.clone(),
+                    })
+            })
+            .collect()
+    }
+
+    /// Computes the posterior over `target` for each of `rows`, treating each
+    /// row as evidence, as a map from each value of `target` to its
+    /// probability. A row's own value for `target`, if any, is ignored, so
+    /// rows of labelled data can be passed as they are. Rows whose evidence
+    /// has probability 0 get NaN for every value.
+    ///
+    /// The CNF is compiled once for all rows; each row only swaps the
+    /// evidence weights.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// let rows = vec![
+    ///     HashMap::from([(String::from("C"), String::from("T"))]),
+    ///     HashMap::from([
+    ///         (String::from("A"), String::from("F")),
+    ///         (String::from("B"), String::from("F")),
+    ///         (String::from("C"), String::from("T")),
+    ///     ]),
+    /// ];
+    /// let posteriors = bn.predict(&rows, "A");
+    ///
+    /// // Pr(A = T | C = T)
+    /// assert!((posteriors[0]["T"] - 0.78125).abs() < 1e-9);
+    /// // the second row's own value of A is not used as evidence:
+    /// // Pr(A = T | B = F, C = T) = 0.5 * 0.7 / (0.5 * 0.1 + 0.5 * 0.7)
+    /// assert!((posteriors[1]["T"] - 0.875).abs() < 1e-9);
+    /// ```
+    pub fn predict(
+        &self,
+        rows: &[HashMap<String, String>],
+        target: &str,
+    ) -> Vec<HashMap<String, f64>> {
+        let values: Vec<&String> = self
+            .indicators
+            .get(target)
+            .unwrap_or_else(|| panic!("could not find variable {target}"))
+            .keys()
+            .collect();
+        let mut params = Vec::new();
+        for row in rows.iter() {
+            let mut evidence = row.clone();
+            for value in values.iter() {
+                evidence.insert(target.to_string(), (*value).clone());
+                params.push(self.evidence_params(&evidence));
+            }
+        }
+        let counts = self.weighted_model_counts(&params);
+        counts
+            .chunks(values.len())
+            .map(|counts| {
+                let z: f64 = counts.iter().sum();
+                values
+                    .iter()
+                    .zip(counts)
+                    .map(|(value, count)| ((*value).clone(), count / z))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Computes the average negative log-likelihood (in nats) that `predict`
+    /// assigns to each row's own value of `target`; lower is better, and a
+    /// classifier that always predicts the true value with certainty scores 0.
+    ///
+    /// Returns an error if `rows` is empty, which leaves nothing to average,
+    /// or if a row has no value for `target` or its value is not one of
+    /// `target`'s states.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// let rows = vec![HashMap::from([
+    ///     (String::from("A"), String::from("T")),
+    ///     (String::from("C"), String::from("T")),
+    /// ])];
+    ///
+    /// // -ln Pr(A = T | C = T)
+    /// assert!((bn.log_loss(&rows, "A").unwrap() + 0.78125f64.ln()).abs() < 1e-9);
+    /// assert!(bn.log_loss(&rows, "B").is_err());
+    /// assert!(bn.log_loss(&[], "A").is_err());
+    /// ```
+    pub fn log_loss(&self, rows: &[HashMap<String, String>], target: &str) -> Result<f64, BnError> {
+        if rows.is_empty() {
+            return Err(BnError::NoRows);
+        }
+        let mut truth: Vec<&String> = Vec::new();
+        for (i, row) in rows.iter().enumerate() {
+            truth.push(
+                row.get(target)
+                    .ok_or_else(|| BnError::MissingValue(i, target.to_string()))?,
+            );
+        }
+        let posteriors = self.predict(rows, target);
+        let mut total = 0.0;
+        for (posterior, value) in posteriors.iter().zip(truth) {
+            let p = posterior
+                .get(value)
+                .ok_or_else(|| BnError::UnknownState(target.to_string(), value.clone()))?;
+            total -= p.ln();
+        }
+        Ok(total / rows.len() as f64)
+    }
+
+    /// Draws a full assignment by visiting the encoded variables in
+    /// topological order and choosing each one's value with probability
+    /// given by the weights of the parameter variables consistent with the
+    /// values already drawn for its parents.
+    fn sample_assignment<R: Rng>(&self, rng: &mut R) -> HashMap<String, String> {
+        let mut assignment: HashMap<String, String> = HashMap::new();
+        for variable in self.order.iter() {
+            let candidates: Vec<&Parameter> = self.parameters[variable]
+                .iter()
+                .filter(|p| {
+                    p.parent_assignment
+                        .iter()
+                        .all(|(parent, value)| assignment[parent] == *value)
+                })
+                .collect();
+            let weights: Vec<f64> = candidates.iter().map(|p| p.probability).collect();
+            let chosen = candidates[sample_index(&weights, rng)];
+            assignment.insert(variable.clone(), chosen.value.clone());
+        }
+        assignment
+    }
+
+    /// Draws `n` independent full assignments, each with probability
+    /// proportional to its weight in the compiled CNF, i.e. exactly from the
+    /// distribution the CNF's weights define (including weights changed by
+    /// `import_weights`).
+    ///
+    /// The CNF is compiled once. Each sample then descends the variables in
+    /// encoding order, choosing every value with probability proportional to
+    /// the weighted model count of the compiled BDD restricted to the values
+    /// chosen so far. Those counts are cached by prefix and shared between
+    /// samples, so later samples mostly reuse earlier work.
+    /// ```
+    /// use rand::{rngs::StdRng, SeedableRng};
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// let samples = bn.sample_from_compiled(10_000, &mut StdRng::seed_from_u64(0));
+    ///
+    /// assert_eq!(samples.len(), 10_000);
+    /// let c_t = samples.iter().filter(|s| s["C"] == "T").count() as f64 / 10_000.0;
+    /// assert!((c_t - 0.4).abs() < 0.03);
+    /// ```
+    pub fn sample_from_compiled<R: Rng>(
+        &self,
+        n: usize,
+        rng: &mut R,
+    ) -> Vec<HashMap<String, String>> {
+        self.with_model_counter(|wmc| self.sample_with_counter(n, rng, wmc))
+    }
+
+    /// the descent of `sample_from_compiled`, with `wmc` counting the
+    /// compiled CNF
+    fn sample_with_counter<R: Rng>(
+        &self,
+        n: usize,
+        rng: &mut R,
+        wmc: &dyn Fn(&WmcParams<RealSemiring>) -> RealSemiring,
+    ) -> Vec<HashMap<String, String>> {
+        let values: Vec<Vec<&String>> = self
+            .order
+            .iter()
+            .map(|var| {
+                let mut values: Vec<&String> = self.indicators[var].keys().collect();
+                values.sort();
+                values
+            })
+            .collect();
+        // the weight below each value of the next variable, keyed by the
+        // indices of the values chosen so far
+        let mut branch_counts: HashMap<Vec<usize>, Vec<f64>> = HashMap::new();
+        (0..n)
+            .map(|_| {
+                let mut assignment: HashMap<String, String> = HashMap::new();
+                let mut prefix: Vec<usize> = Vec::new();
+                for (var, values) in self.order.iter().zip(values.iter()) {
+                    let counts = branch_counts.entry(prefix.clone()).or_insert_with(|| {
+                        values
+                            .iter()
+                            .map(|value| {
+                                let mut branch = assignment.clone();
+                                branch.insert(var.clone(), (*value).clone());
+                                wmc(&self.evidence_params(&branch)).0
+                            })
+                            .collect()
+                    });
+                    let choice = sample_index(counts, rng);
+                    assignment.insert(var.clone(), values[choice].clone());
+                    prefix.push(choice);
+                }
+                assignment
+            })
+            .collect()
+    }
+
+    /// Estimates Pr(var = value) by Monte Carlo sampling over the CNF's
+    /// weights instead of an exact weighted model count.
+    ///
+    /// Draws enough independent samples that, by Hoeffding's inequality, the
+    /// estimate is within `epsilon` of the true marginal with probability at
+    /// least `1 - delta`; this takes `ceil(ln(2 / delta) / (2 epsilon^2))`
+    /// samples, each costing time linear in the size of the network.
+    ///
+    /// Unlike exact WMC this never needs to compile the CNF into a decision
+    /// diagram, so it stays cheap on networks whose diagrams blow up. The
+    /// price is that the guarantee is additive: for rare events (marginals
+    /// much smaller than `epsilon`) the estimate carries little information,
+    /// and tightening `epsilon` increases the sample count quadratically.
+    pub fn approx_marginal<R: Rng>(
+        &self,
+        var: &str,
+        value: &str,
+        epsilon: f64,
+        delta: f64,
+        rng: &mut R,
+    ) -> f64 {
+        assert!(epsilon > 0.0, "epsilon must be positive");
+        assert!(delta > 0.0 && delta < 1.0, "delta must be in (0, 1)");
+        assert!(
+            self.indicators
+                .get(var)
+                .is_some_and(|v| v.contains_key(value)),
+            "could not find assignment {value} for variable {var}"
+        );
+        let num_samples = ((2.0 / delta).ln() / (2.0 * epsilon * epsilon)).ceil() as usize;
+        let hits = (0..num_samples)
+            .filter(|_| self.sample_assignment(rng)[var] == value)
+            .count();
+        hits as f64 / num_samples as f64
+    }
+
+    /// summarizes the size of the compiled CNF
+    pub fn stats(&self) -> CnfStats {
+        let num_indicators = self
+            .indicators
+            .values()
+            .flat_map(|v| v.values().map(|l| l.get_label()))
+            .collect::<HashSet<VarLabel>>()
+            .len();
+        CnfStats {
+            num_vars: self.cnf.num_vars(),
+            num_clauses: self.cnf.clauses().len(),
+            num_indicators,
+            num_parameters: self.cnf.num_vars() - num_indicators - self.num_encoding_bits,
+            num_shared_parameters: self.num_shared_parameters,
+            num_encoding_bits: self.num_encoding_bits,
+        }
+    }
+
+    /// Writes the CNF and its weights in the DIMACS format read by weighted
+    /// model counters such as c2d and d4. The CNF variable with label `i` is
+    /// DIMACS variable `i + 1`; `dimacs_var_map` says which of them are
+    /// indicators. Each variable gets two `c p weight` lines, one for each
+    /// literal, taken from `params()`.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    /// let dimacs = bn.to_dimacs();
+    /// let stats = bn.stats();
+    ///
+    /// let header = format!("p cnf {} {}", stats.num_vars, stats.num_clauses);
+    /// assert!(dimacs.lines().any(|line| line == header));
+    /// assert_eq!(
+    ///     dimacs.lines().filter(|line| line.starts_with("c p weight")).count(),
+    ///     2 * stats.num_vars
+    /// );
+    ///
+    /// let (id, indicator) = bn
+    ///     .dimacs_var_map()
+    ///     .into_iter()
+    ///     .find(|(_, (var, value))| var == "C" && value == "T")
+    ///     .unwrap();
+    /// assert_eq!(id, bn.indicator(&indicator.0, &indicator.1).value_usize() + 1);
+    /// ```
+    pub fn to_dimacs(&self) -> String {
+        let num_vars = self.cnf.num_vars();
+        let mut lines = vec![
+            String::from("c t wmc"),
+            format!("p cnf {} {}", num_vars, self.cnf.clauses().len()),
+        ];
+        for id in 1..=num_vars {
+            let (low, high) = self.params.get_var_weight(VarLabel::new_usize(id - 1));
+            lines.push(format!("c p weight {id} {} 0", high.0));
+            lines.push(format!("c p weight -{id} {} 0", low.0));
+        }
+        lines.extend(self.cnf.clauses().iter().map(|clause| {
+            clause
+                .iter()
+                .map(|l| {
+                    let id = l.get_label().value_usize() + 1;
+                    if l.get_polarity() {
+                        format!("{id} ")
+                    } else {
+                        format!("-{id} ")
+                    }
+                })
+                .chain(std::iter::once(String::from("0")))
+                .collect::<String>()
+        }));
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    /// maps the DIMACS variable of each indicator in `to_dimacs` to the
+    /// (variable, value) its positive literal indicates; the DIMACS variables
+    /// missing from the map are parameters and encoding bits
+    pub fn dimacs_var_map(&self) -> HashMap<usize, (String, String)> {
+        self.indicators
+            .iter()
+            .flat_map(|(var, literals)| {
+                literals
+                    .iter()
+                    .filter(|(_, literal)| literal.get_polarity())
+                    .map(move |(value, literal)| {
+                        (
+                            literal.get_label().value_usize() + 1,
+                            (var.clone(), value.clone()),
+                        )
+                    })
+            })
+            .collect()
+    }
+}
+
+/// restricts `network` to the variables of one of its weakly-connected components
+fn component_network(network: &BayesianNetwork, component: &[String]) -> BayesianNetwork {
+    BayesianNetwork {
+        network: network.network.clone(),
+        variables: component.to_vec(),
+        cpts: component
+            .iter()
+            .map(|v| (v.clone(), network.cpts[v].clone()))
+            .collect(),
+        states: component
+            .iter()
+            .map(|v| (v.clone(), network.states[v].clone()))
+            .collect(),
+        parents: component
+            .iter()
+            .map(|v| (v.clone(), network.parents[v].clone()))
+            .collect(),
+        metadata: HashMap::new(),
+        topological_order: OnceLock::new(),
+    }
+}
+
+/// multiplies the weight of `literal` in `weights` by `factor`
+fn scale_literal(weights: &mut HashMap<VarLabel, (f64, f64)>, literal: Literal, factor: f64) {
+    let (low, high) = weights.entry(literal.get_label()).or_insert((1.0, 1.0));
+    if literal.get_polarity() {
+        *high *= factor;
+    } else {
+        *low *= factor;
+    }
 }
 
 /// construct a CNF for the two TERMS (i.e., conjunctions of literals) t1 => t2
@@ -132,3 +3202,769 @@ fn exactly_one(lits: Vec<Literal>) -> Vec<Vec<Literal>> {
     }
     r
 }
+
+/// A prefix of an assignment to the variables in encoding order, ordered by
+/// an upper bound on the probability of any completion
+struct PartialExplanation<'a> {
+    bound: f64,
+    /// the weight of the prefix itself: the product of its CPT entries in
+    /// `top_k_explanations`, or its probability with the evidence in `map`
+    probability: f64,
+    values: Vec<&'a String>,
+}
+
+impl PartialEq for PartialExplanation<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for PartialExplanation<'_> {}
+
+impl PartialOrd for PartialExplanation<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PartialExplanation<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.bound.total_cmp(&other.bound)
+    }
+}
+
+/// Makes exactly one of `indicators` hold by giving state `i` the code `i`
+/// in binary over `bits` (least significant bit first): each indicator holds
+/// exactly when the bits spell its code, and codes past the last state are
+/// forbidden
+fn log_encoding(indicators: &[Literal], bits: &[VarLabel]) -> Vec<Vec<Literal>> {
+    let code = |i: usize| -> Vec<Literal> {
+        bits.iter()
+            .enumerate()
+            .map(|(j, bit)| Literal::new(*bit, (i >> j) & 1 == 1))
+            .collect()
+    };
+    let mut r: Vec<Vec<Literal>> = Vec::new();
+    for (i, indicator) in indicators.iter().enumerate() {
+        r.append(&mut implies(&[*indicator], &code(i)));
+        r.append(&mut implies(&code(i), &[*indicator]));
+    }
+    for unused in indicators.len()..(1 << bits.len()) {
+        r.push(code(unused).iter().map(|l| l.negated()).collect());
+    }
+    r
+}
+
+#[test]
+fn test_approx_marginal() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// models the collider A, B -> C
+    static NETWORK: &str = r#"{
+        "network": "toy_network",
+        "variables": ["A", "B", "C"],
+        "cpts": {
+            "A": [[0.5], [0.5]],
+            "B": [[0.25], [0.75]],
+            "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+        },
+        "states": {
+            "A": ["F", "T"],
+            "B": ["F", "T"],
+            "C": ["F", "T"]
+        },
+        "parents" :{
+            "A": [],
+            "B": [],
+            "C": ["A", "B"]
+        }
+    }"#;
+
+    let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    let mut rng = StdRng::seed_from_u64(0);
+    // Pr(C = T) = 0.5 * (0.25 * 0.1 + 0.75 * 0.2) + 0.5 * (0.25 * 0.7 + 0.75 * 0.6) = 0.4
+    let p = bn.approx_marginal("C", "T", 0.01, 0.001, &mut rng);
+    assert!((p - 0.4).abs() < 0.01);
+    let p = bn.approx_marginal("B", "T", 0.01, 0.001, &mut rng);
+    assert!((p - 0.75).abs() < 0.01);
+}
+
+#[test]
+fn test_split_components() {
+    /// models two independent colliders A, B -> C and D, E -> F
+    static NETWORK: &str = r#"{
+        "network": "two_colliders",
+        "variables": ["A", "B", "C", "D", "E", "F"],
+        "cpts": {
+            "A": [[0.5], [0.5]],
+            "B": [[0.25], [0.75]],
+            "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]],
+            "D": [[0.1], [0.9]],
+            "E": [[0.6], [0.4]],
+            "F": [[0.2, 0.5, 0.35, 0.05], [0.8, 0.5, 0.65, 0.95]]
+        },
+        "states": {
+            "A": ["F", "T"],
+            "B": ["F", "T"],
+            "C": ["F", "T"],
+            "D": ["F", "T"],
+            "E": ["F", "T"],
+            "F": ["F", "T"]
+        },
+        "parents" :{
+            "A": [],
+            "B": [],
+            "C": ["A", "B"],
+            "D": [],
+            "E": [],
+            "F": ["D", "E"]
+        }
+    }"#;
+
+    let network = BayesianNetwork::from_json(NETWORK);
+    let monolithic = BayesianNetworkCNF::from_bayesian_network(&network);
+    let split = BayesianNetworkCNF::from_bayesian_network_with_options(
+        &network,
+        &CompileOptions {
+            split_components: true,
+            ..Default::default()
+        },
+    );
+    assert_eq!(split.components.len(), 2);
+
+    for var in network.variables() {
+        for value in network.all_possible_assignments(var) {
+            let query = HashMap::from([(var.clone(), value.clone())]);
+            assert!((monolithic.probability(&query) - split.probability(&query)).abs() < 1e-9);
+        }
+    }
+
+    // a query spanning both components
+    let query = HashMap::from([
+        (String::from("C"), String::from("T")),
+        (String::from("F"), String::from("F")),
+    ]);
+    assert!((monolithic.probability(&query) - split.probability(&query)).abs() < 1e-9);
+
+    // the other queries are answered by the components too
+    let evidence = HashMap::from([(String::from("C"), String::from("T"))]);
+    let expected = monolithic.marginals(&evidence);
+    let marginals = split.marginals(&evidence);
+    for (var, values) in expected.iter() {
+        for (value, p) in values.iter() {
+            assert!((marginals[var][value] - p).abs() < 1e-9);
+        }
+    }
+    let query = [String::from("A"), String::from("E")];
+    let (assignment, p) = split.map(&query, &evidence);
+    let (expected, expected_p) = monolithic.map(&query, &evidence);
+    assert_eq!(assignment, expected);
+    assert!((p - expected_p).abs() < 1e-9);
+    assert!(
+        (split.marginal_logspace("A", "T", &evidence)
+            - monolithic.marginal_logspace("A", "T", &evidence))
+        .abs()
+            < 1e-9
+    );
+    let running = |bn: &BayesianNetworkCNF| {
+        bn.with_running_evidence(|running| {
+            vec![running.observe("C", "T"), running.observe("D", "F")]
+        })
+    };
+    for (p, q) in running(&split).iter().zip(running(&monolithic).iter()) {
+        assert!((p - q).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn test_full_assignment_fast_path() {
+    static NETWORK: &str = r#"{
+        "network": "toy_network",
+        "variables": ["A", "B", "C"],
+        "cpts": {
+            "A": [[0.5], [0.5]],
+            "B": [[0.25], [0.75]],
+            "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+        },
+        "states": {
+            "A": ["F", "T"],
+            "B": ["F", "T"],
+            "C": ["F", "T"]
+        },
+        "parents" :{
+            "A": [],
+            "B": [],
+            "C": ["A", "B"]
+        }
+    }"#;
+
+    let network = BayesianNetwork::from_json(NETWORK);
+    let bn = BayesianNetworkCNF::from_bayesian_network(&network);
+    let mut total = 0.0;
+    for a in ["F", "T"] {
+        for b in ["F", "T"] {
+            for c in ["F", "T"] {
+                let assignment = HashMap::from([
+                    (String::from("A"), String::from(a)),
+                    (String::from("B"), String::from(b)),
+                    (String::from("C"), String::from(c)),
+                ]);
+                let fast = bn.probability(&assignment);
+                let wmc = bn.weighted_model_counts(&[bn.evidence_params(&assignment)])[0];
+                assert!((fast - wmc).abs() < 1e-9);
+                total += fast;
+            }
+        }
+    }
+    assert!((total - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_share_parameters() {
+    /// a collider whose CPT for C repeats the same column three times
+    static NETWORK: &str = r#"{
+        "network": "repeated_columns",
+        "variables": ["A", "B", "C"],
+        "cpts": {
+            "A": [[0.5], [0.5]],
+            "B": [[0.25], [0.75]],
+            "C": [[0.9, 0.9, 0.3, 0.9], [0.1, 0.1, 0.7, 0.1]]
+        },
+        "states": {
+            "A": ["F", "T"],
+            "B": ["F", "T"],
+            "C": ["F", "T"]
+        },
+        "parents" :{
+            "A": [],
+            "B": [],
+            "C": ["A", "B"]
+        }
+    }"#;
+
+    let network = BayesianNetwork::from_json(NETWORK);
+    let plain = BayesianNetworkCNF::from_bayesian_network(&network);
+    let shared = BayesianNetworkCNF::from_bayesian_network_with_options(
+        &network,
+        &CompileOptions {
+            share_parameters: true,
+            ..Default::default()
+        },
+    );
+    assert_eq!(plain.stats().num_parameters, 12);
+    assert_eq!(plain.stats().num_shared_parameters, 0);
+    // C's four columns collapse into two groups, one of them shared
+    assert_eq!(shared.stats().num_parameters, 8);
+    assert_eq!(shared.stats().num_shared_parameters, 2);
+
+    let mut queries: Vec<HashMap<String, String>> = vec![HashMap::new()];
+    for var in network.variables() {
+        queries = queries
+            .into_iter()
+            .flat_map(|q| {
+                let mut extended: Vec<HashMap<String, String>> = network
+                    .all_possible_assignments(var)
+                    .iter()
+                    .map(|value| {
+                        let mut q = q.clone();
+                        q.insert(var.clone(), value.clone());
+                        q
+                    })
+                    .collect();
+                extended.push(q);
+                extended
+            })
+            .collect();
+    }
+    // every partial and full assignment, counted by WMC on both encodings
+    assert_eq!(queries.len(), 27);
+    for query in queries.iter() {
+        let p = plain.weighted_model_counts(&[plain.evidence_params(query)])[0];
+        let s = shared.weighted_model_counts(&[shared.evidence_params(query)])[0];
+        assert!((p - s).abs() < 1e-9, "{query:?}: {p} != {s}");
+    }
+}
+
+#[test]
+fn test_encodings_agree() {
+    /// X has five states and is a parent of Y; A is binary and a parent of X
+    static NETWORK: &str = r#"{
+        "network": "five_states",
+        "variables": ["A", "X", "Y"],
+        "cpts": {
+            "A": [[0.3], [0.7]],
+            "X": [[0.1, 0.3], [0.2, 0.1], [0.3, 0.1], [0.25, 0.4], [0.15, 0.1]],
+            "Y": [[0.9, 0.1, 0.5, 0.6, 0.25], [0.1, 0.9, 0.5, 0.4, 0.75]]
+        },
+        "states": {
+            "A": ["F", "T"],
+            "X": ["x0", "x1", "x2", "x3", "x4"],
+            "Y": ["F", "T"]
+        },
+        "parents" :{
+            "A": [],
+            "X": ["A"],
+            "Y": ["X"]
+        }
+    }"#;
+
+    let network = BayesianNetwork::from_json(NETWORK);
+    let compile = |encoding| {
+        BayesianNetworkCNF::from_bayesian_network_with_options(
+            &network,
+            &CompileOptions {
+                encoding,
+                ..Default::default()
+            },
+        )
+    };
+    let one_hot = compile(Encoding::OneHot);
+    let log = compile(Encoding::Log);
+    let auto = compile(Encoding::Auto { log_threshold: 2 });
+    let bernoulli = compile(Encoding::Bernoulli);
+    assert_eq!(one_hot.stats().num_encoding_bits, 0);
+    // A and Y share one variable between their two indicators
+    assert_eq!(
+        bernoulli.stats().num_indicators,
+        one_hot.stats().num_indicators - 2
+    );
+    assert_eq!(
+        bernoulli.stats().num_parameters,
+        one_hot.stats().num_parameters
+    );
+    // A and Y get one bit each, X gets three
+    assert_eq!(log.stats().num_encoding_bits, 5);
+    assert_eq!(auto.stats().num_encoding_bits, 3);
+    assert_eq!(auto.stats().num_parameters, one_hot.stats().num_parameters);
+
+    for evidence in [
+        HashMap::new(),
+        HashMap::from([(String::from("Y"), String::from("T"))]),
+    ] {
+        let expected = one_hot.marginals(&evidence);
+        for bn in [&log, &auto, &bernoulli] {
+            let marginals = bn.marginals(&evidence);
+            for (var, values) in expected.iter() {
+                for (value, p) in values.iter() {
+                    assert!((marginals[var][value] - p).abs() < 1e-9);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_bernoulli_encoding() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let network = BayesianNetwork::random(8, 3, 2, &mut StdRng::seed_from_u64(4));
+    let one_hot = BayesianNetworkCNF::from_bayesian_network(&network);
+    let bernoulli = BayesianNetworkCNF::from_bayesian_network_with_options(
+        &network,
+        &CompileOptions {
+            encoding: Encoding::Bernoulli,
+            ..Default::default()
+        },
+    );
+    assert_eq!(bernoulli.stats().num_vars, one_hot.stats().num_vars - 8);
+    assert!(bernoulli.stats().num_clauses < one_hot.stats().num_clauses);
+
+    let evidence = HashMap::from([
+        (String::from("X7"), String::from("s0")),
+        (String::from("X2"), String::from("s1")),
+    ]);
+    let expected = one_hot.marginals(&evidence);
+    let marginals = bernoulli.marginals(&evidence);
+    for (var, values) in expected.iter() {
+        for (value, p) in values.iter() {
+            assert!((marginals[var][value] - p).abs() < 1e-9);
+        }
+    }
+
+    let query = (String::from("X0"), String::from("s0"));
+    let soft = HashMap::from([(String::from("X5"), vec![0.2, 0.9])]);
+    assert!(
+        (bernoulli.posterior_with_soft_evidence(&query, &soft)
+            - one_hot.posterior_with_soft_evidence(&query, &soft))
+        .abs()
+            < 1e-9
+    );
+    let running = |bn: &BayesianNetworkCNF| {
+        bn.with_running_evidence(|running| {
+            vec![
+                running.observe("X3", "s0"),
+                running.observe("X6", "s1"),
+                running.observe("X3", "s1"),
+            ]
+        })
+    };
+    let expected = running(&one_hot);
+    for (p, q) in running(&bernoulli).iter().zip(expected.iter()) {
+        assert!((p - q).abs() < 1e-9);
+    }
+    assert_eq!(expected[2], 0.0);
+}
+
+#[test]
+fn test_deterministic_labels() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let network = BayesianNetwork::random(10, 3, 3, &mut StdRng::seed_from_u64(5));
+    // each compilation iterates its own hash maps in a different order
+    let first = BayesianNetworkCNF::from_bayesian_network(&network);
+    let second =
+        BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(&network.to_json()));
+    assert_eq!(first.indicators, second.indicators);
+    assert_eq!(first.export_weights(), second.export_weights());
+    assert_eq!(first.to_dimacs(), second.to_dimacs());
+}
+
+#[test]
+fn test_compilation_cache() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let mut network = BayesianNetwork::random(7, 2, 3, &mut StdRng::seed_from_u64(0));
+    let mut cache = CompilationCache::new(CompileOptions {
+        encoding: Encoding::Log,
+        ..Default::default()
+    });
+    cache.recompile(&network);
+    assert_eq!(cache.num_reencoded(), 7);
+
+    // renaming a state of X0 changes the encoding of X0 and of its children;
+    // editing X5's CPT changes only X5
+    network.states.get_mut("X0").unwrap()[0] = String::from("renamed");
+    network.cpts.get_mut("X5").unwrap().swap(0, 1);
+    let num_changed = network
+        .variables()
+        .iter()
+        .filter(|v| *v == "X0" || *v == "X5" || network.parents(v).contains(&String::from("X0")))
+        .count();
+
+    let cached = cache.recompile(&network);
+    assert_eq!(cache.num_reencoded(), num_changed);
+    let fresh = BayesianNetworkCNF::from_bayesian_network(&network);
+    for variable in network.variables() {
+        for value in network.all_possible_assignments(variable) {
+            let assignment = HashMap::from([(variable.clone(), value.clone())]);
+            assert!(
+                (cached.probability(&assignment) - fresh.probability(&assignment)).abs() < 1e-9
+            );
+        }
+    }
+}
+
+#[test]
+fn test_distribution() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let network = BayesianNetwork::random(6, 2, 3, &mut StdRng::seed_from_u64(1));
+    let bn = BayesianNetworkCNF::from_bayesian_network(&network);
+    let evidence = HashMap::from([(String::from("X5"), String::from("s2"))]);
+    let marginals = bn.marginals(&evidence);
+    for variable in network.variables() {
+        let distribution = bn.distribution(variable, &evidence);
+        assert_eq!(distribution.len(), 3);
+        assert!((distribution.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        for (value, p) in network
+            .all_possible_assignments(variable)
+            .iter()
+            .zip(distribution)
+        {
+            assert!((marginals[variable][value] - p).abs() < 1e-9);
+        }
+    }
+    assert_eq!(bn.distribution("X5", &evidence), vec![0.0, 0.0, 1.0]);
+}
+
+#[test]
+fn test_mpe() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    static NETWORK: &str = r#"{
+        "network": "toy_network",
+        "variables": ["A", "B", "C"],
+        "cpts": {
+            "A": [[0.5], [0.5]],
+            "B": [[0.25], [0.75]],
+            "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+        },
+        "states": {
+            "A": ["F", "T"],
+            "B": ["F", "T"],
+            "C": ["F", "T"]
+        },
+        "parents" :{
+            "A": [],
+            "B": [],
+            "C": ["A", "B"]
+        }
+    }"#;
+    let network = BayesianNetwork::from_json(NETWORK);
+    let bn = BayesianNetworkCNF::from_bayesian_network(&network);
+
+    // every joint assignment of the collider, by the chain rule
+    let mut joints: Vec<(HashMap<String, String>, f64)> = Vec::new();
+    for a in ["F", "T"] {
+        for b in ["F", "T"] {
+            for c in ["F", "T"] {
+                let assignment = HashMap::from([
+                    (String::from("A"), a.to_string()),
+                    (String::from("B"), b.to_string()),
+                    (String::from("C"), c.to_string()),
+                ]);
+                let p = network.joint_probability(&assignment);
+                joints.push((assignment, p));
+            }
+        }
+    }
+    let evidences = [
+        HashMap::new(),
+        HashMap::from([(String::from("C"), String::from("T"))]),
+        HashMap::from([(String::from("A"), String::from("T"))]),
+        HashMap::from([
+            (String::from("A"), String::from("F")),
+            (String::from("C"), String::from("T")),
+        ]),
+    ];
+    for evidence in evidences.iter() {
+        let (expected, expected_p) = joints
+            .iter()
+            .filter(|(a, _)| evidence.iter().all(|(v, value)| a[v] == *value))
+            .max_by(|(_, p), (_, q)| p.total_cmp(q))
+            .unwrap();
+        let (assignment, p) = bn.mpe(evidence);
+        assert_eq!(&assignment, expected);
+        assert!((p - expected_p).abs() < 1e-12);
+    }
+
+    // impossible evidence is reported the same way as by map
+    let certain = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(
+        &NETWORK.replace("[[0.5], [0.5]]", "[[1.0], [0.0]]"),
+    ));
+    let a_t = HashMap::from([(String::from("A"), String::from("T"))]);
+    let (assignment, p) = certain.mpe(&a_t);
+    assert!(assignment.is_empty() && p.is_nan());
+    let (assignment, p) = certain.map(&[String::from("B")], &a_t);
+    assert!(assignment.is_empty() && p.is_nan());
+
+    // the max-product count decodes the same explanation under every
+    // encoding, and with the collider as a second component to split off
+    let collider = BayesianNetwork::from_json(
+        &NETWORK
+            .replace(r#""A""#, r#""D""#)
+            .replace(r#""B""#, r#""E""#)
+            .replace(r#""C""#, r#""F""#),
+    );
+    let random = BayesianNetwork::random(5, 2, 3, &mut StdRng::seed_from_u64(4))
+        .union(&collider)
+        .unwrap();
+    let evidence = HashMap::from([
+        (String::from("X4"), String::from("s0")),
+        (String::from("F"), String::from("T")),
+    ]);
+    let expected = BayesianNetworkCNF::from_bayesian_network(&random)
+        .top_k_explanations(1, &evidence)
+        .pop()
+        .unwrap();
+    for encoding in [Encoding::OneHot, Encoding::Log, Encoding::Bernoulli] {
+        for split_components in [false, true] {
+            let options = CompileOptions {
+                split_components,
+                share_parameters: true,
+                encoding,
+            };
+            let bn = BayesianNetworkCNF::from_bayesian_network_with_options(&random, &options);
+            let (assignment, p) = bn.mpe(&evidence);
+            assert_eq!(assignment, expected.0);
+            assert!((p - expected.1).abs() < 1e-12);
+        }
+    }
+}
+
+#[test]
+fn test_map() {
+    use crate::{Inference, VariableEliminationEngine};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let network = BayesianNetwork::random(6, 2, 3, &mut StdRng::seed_from_u64(2));
+    let bn = BayesianNetworkCNF::from_bayesian_network(&network);
+    let engine = VariableEliminationEngine::new(&network);
+    let evidence = HashMap::from([(String::from("X5"), String::from("s1"))]);
+    for query in [
+        vec![String::from("X0")],
+        vec![String::from("X1"), String::from("X3")],
+        vec![String::from("X2"), String::from("X5"), String::from("X0")],
+    ] {
+        let (assignment, p) = bn.map(&query, &evidence);
+        let (expected, expected_p) = engine.map(&query, &evidence);
+        assert_eq!(assignment, expected);
+        assert!((p - expected_p).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn test_single_state_variable() {
+    /// K is a constant with the single state "on", between A and C
+    static NETWORK: &str = r#"{
+        "network": "constant",
+        "variables": ["A", "K", "C"],
+        "cpts": {
+            "A": [[0.3], [0.7]],
+            "K": [[1.0, 1.0]],
+            "C": [[0.9, 0.2], [0.1, 0.8]]
+        },
+        "states": {
+            "A": ["F", "T"],
+            "K": ["on"],
+            "C": ["F", "T"]
+        },
+        "parents" :{
+            "A": [],
+            "K": ["A"],
+            "C": ["A", "K"]
+        }
+    }"#;
+
+    let network = BayesianNetwork::from_json(NETWORK);
+    let k_on = HashMap::from([(String::from("K"), String::from("on"))]);
+    for encoding in [Encoding::OneHot, Encoding::Log] {
+        let bn = BayesianNetworkCNF::from_bayesian_network_with_options(
+            &network,
+            &CompileOptions {
+                encoding,
+                ..Default::default()
+            },
+        );
+        // the constant needs no encoding bits of its own
+        assert_eq!(
+            bn.stats().num_encoding_bits,
+            if encoding == Encoding::Log { 2 } else { 0 }
+        );
+        assert!((bn.probability(&k_on) - 1.0).abs() < 1e-9);
+        let marginals = bn.all_marginals();
+        assert!((marginals["K"]["on"] - 1.0).abs() < 1e-9);
+        // Pr(C = T) = 0.3 * 0.1 + 0.7 * 0.8
+        assert!((marginals["C"]["T"] - 0.59).abs() < 1e-9);
+        assert_eq!(bn.marginals(&k_on), marginals);
+    }
+
+    // a constant whose CPT entry is not 1 scales every probability
+    let scaled = BayesianNetwork::from_json(&NETWORK.replace("[[1.0, 1.0]]", "[[0.5, 0.5]]"));
+    let bn = BayesianNetworkCNF::from_bayesian_network(&scaled);
+    assert!((bn.probability(&k_on) - 0.5).abs() < 1e-9);
+    assert!((bn.marginal("C", "T") - 0.5 * 0.59).abs() < 1e-9);
+}
+
+#[test]
+fn test_to_dimacs_weighted_model_count() {
+    static NETWORK: &str = r#"{
+        "network": "toy_network",
+        "variables": ["A", "B", "C"],
+        "cpts": {
+            "A": [[0.5], [0.5]],
+            "B": [[0.25], [0.75]],
+            "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+        },
+        "states": {
+            "A": ["F", "T"],
+            "B": ["F", "T"],
+            "C": ["F", "T"]
+        },
+        "parents" :{
+            "A": [],
+            "B": [],
+            "C": ["A", "B"]
+        }
+    }"#;
+
+    let bn = BayesianNetworkCNF::from_bayesian_network(&BayesianNetwork::from_json(NETWORK));
+    let dimacs = bn.to_dimacs();
+
+    // read the file back the way an external counter would
+    let mut num_vars = 0;
+    let mut weights: HashMap<i64, f64> = HashMap::new();
+    let mut clauses: Vec<Vec<i64>> = Vec::new();
+    for line in dimacs.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            ["p", "cnf", vars, _] => num_vars = vars.parse().unwrap(),
+            ["c", "p", "weight", lit, weight, "0"] => {
+                weights.insert(lit.parse().unwrap(), weight.parse().unwrap());
+            }
+            ["c", ..] | [] => {}
+            lits => clauses.push(lits.iter().map(|l| l.parse().unwrap()).collect()),
+        }
+    }
+    assert_eq!(weights.len(), 2 * num_vars);
+    assert!(clauses.iter().all(|clause| clause.last() == Some(&0)));
+
+    // the weighted model count by enumerating every assignment, where
+    // `forced` fixes one literal
+    let wmc = |forced: Option<i64>| -> f64 {
+        (0..1u64 << num_vars)
+            .map(|bits| {
+                let value = |lit: i64| (bits >> (lit.unsigned_abs() - 1) & 1 == 1) == (lit > 0);
+                let satisfied = clauses
+                    .iter()
+                    .all(|clause| clause.iter().any(|&l| l != 0 && value(l)))
+                    && !matches!(forced, Some(l) if !value(l));
+                if !satisfied {
+                    return 0.0;
+                }
+                (1..=num_vars as i64)
+                    .map(|id| weights[&if value(id) { id } else { -id }])
+                    .product()
+            })
+            .sum()
+    };
+    assert!((wmc(None) - 1.0).abs() < 1e-9);
+    let (c_true, _) = bn
+        .dimacs_var_map()
+        .into_iter()
+        .find(|(_, indicator)| indicator == &(String::from("C"), String::from("T")))
+        .unwrap();
+    assert!((wmc(Some(c_true as i64)) - 0.4).abs() < 1e-9);
+    assert_eq!(bn.dimacs_var_map().len(), 6);
+}
+
+#[test]
+fn test_partition_function() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let random = BayesianNetwork::random(8, 3, 3, &mut StdRng::seed_from_u64(6));
+    let z = BayesianNetworkCNF::from_bayesian_network(&random).partition_function();
+    assert!((z - 1.0).abs() < 1e-9);
+
+    // sachs.json rounds its CPTs, so its columns sum to 1 only within 1e-7
+    let sachs = BayesianNetwork::from_json(include_str!("../bayesian_networks/sachs.json"));
+    assert!(sachs.check_normalized(1e-6).is_ok());
+    let z = BayesianNetworkCNF::from_bayesian_network(&sachs).partition_function();
+    assert!((z - 1.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_marginal_by_index() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let network = BayesianNetwork::random(5, 2, 3, &mut StdRng::seed_from_u64(6));
+    let mut bn = BayesianNetworkCNF::from_bayesian_network(&network);
+    let check = |bn: &BayesianNetworkCNF| {
+        for (i, var) in network.variables().iter().enumerate() {
+            for (j, value) in network.all_possible_assignments(var).iter().enumerate() {
+                assert!((bn.marginal_by_index(i, j) - bn.marginal(var, value)).abs() < 1e-12);
+            }
+        }
+    };
+    check(&bn);
+
+    // the table counted by the first call is dropped with the old weights
+    bn.import_weights(&HashMap::from([
+        (String::from("P(X0=s0)"), (1.0, 0.0)),
+        (String::from("P(X0=s1)"), (1.0, 1.0)),
+        (String::from("P(X0=s2)"), (1.0, 0.0)),
+    ]))
+    .unwrap();
+    assert!((bn.marginal_by_index(0, 1) - 1.0).abs() < 1e-9);
+    check(&bn);
+}
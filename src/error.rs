@@ -0,0 +1,227 @@
+//! Errors produced while loading, editing, or querying a Bayesian network
+
+use std::fmt;
+
+#[derive(Debug)]
+pub enum BnError {
+    /// the input could not be parsed as JSON of the expected shape
+    Json(serde_json::Error),
+    /// the input could not be read, e.g. because a file does not exist
+    Io(std::io::Error),
+    /// the input is not well-formed in the named non-JSON `format`
+    Syntax {
+        format: &'static str,
+        message: String,
+    },
+    /// a serialized network declared a `format_version` this crate cannot read
+    UnsupportedFormatVersion(u64),
+    /// a variable name was used by more than one network or definition
+    VariableCollision(String),
+    /// a variable name does not appear in the network
+    UnknownVariable(String),
+    /// an edge `(parent, child)` was given more than once
+    DuplicateEdge(String, String),
+    /// a probability outside [0, 1], or NaN
+    InvalidProbability(f64),
+    /// an edge `(parent, child)` that was expected to exist does not
+    UnknownEdge(String, String),
+    /// a variable needed a CPT but none was supplied
+    MissingCpt(String),
+    /// a CPT was supplied for a variable that does not need one
+    UnexpectedCpt(String),
+    /// a CPT does not have `expected` (rows, columns)
+    CptShape {
+        variable: String,
+        expected: (usize, usize),
+    },
+    /// `(variable, state)`: the state is not one of the variable's states
+    UnknownState(String, String),
+    /// `(variable, parent)`: a parent assignment gives the parent no value
+    MissingParent(String, String),
+    /// `(variable, key)`: a parent assignment gives a value to a variable
+    /// that is not a parent
+    UnexpectedParent(String, String),
+    /// `(variable, state)`: the state is listed or defined more than once
+    DuplicateState(String, String),
+    /// `(variable, group)`: a group of states to merge is empty
+    EmptyStateGroup(String, String),
+    /// `(variable, state)`: the state was expected to be a number but is not
+    NonNumericState(String, String),
+    /// a weight name does not belong to any variable of the compiled CNF
+    UnknownWeight(String),
+    /// `(row, variable)`: a row of data has no value for the variable
+    MissingValue(usize, String),
+    /// `(row, variable, value)`: a row of data gives the variable a value
+    /// that is not one of its states
+    InvalidValue(usize, String, String),
+    /// an average over rows of data was asked of no rows
+    NoRows,
+    /// a CPT column conditions on parent values that have probability 0;
+    /// holds the column's label, e.g. `C|A=T,B=F`
+    ImpossibleColumn(String),
+    /// the graph is not acyclic; holds the variables on or below a cycle
+    Cycle(Vec<String>),
+    /// the network failed `BayesianNetwork::validate`
+    Invalid(Vec<ValidationError>),
+}
+
+impl fmt::Display for BnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BnError::Json(err) => write!(f, "error parsing JSON: {err}"),
+            BnError::Io(err) => write!(f, "error reading input: {err}"),
+            BnError::Syntax { format, message } => write!(f, "error parsing {format}: {message}"),
+            BnError::UnsupportedFormatVersion(version) => {
+                write!(f, "unsupported network format version {version}")
+            }
+            BnError::VariableCollision(v) => write!(f, "variable {v} is defined more than once"),
+            BnError::UnknownVariable(v) => write!(f, "could not find variable {v}"),
+            BnError::DuplicateEdge(p, c) => write!(f, "edge {p} -> {c} already exists"),
+            BnError::UnknownEdge(p, c) => write!(f, "edge {p} -> {c} does not exist"),
+            BnError::InvalidProbability(p) => write!(f, "probability {p} is not in [0, 1]"),
+            BnError::MissingCpt(v) => write!(f, "no CPT supplied for variable {v}"),
+            BnError::UnexpectedCpt(v) => write!(f, "unexpected CPT supplied for variable {v}"),
+            BnError::CptShape {
+                variable,
+                expected: (rows, columns),
+            } => write!(
+                f,
+                "CPT for variable {variable} must have {rows} rows of {columns} columns"
+            ),
+            BnError::UnknownState(v, s) => {
+                write!(f, "could not find assignment {s} for variable {v}")
+            }
+            BnError::MissingParent(v, p) => {
+                write!(f, "no value assigned to parent {p} of variable {v}")
+            }
+            BnError::UnexpectedParent(v, k) => {
+                write!(f, "{k} is assigned but is not a parent of variable {v}")
+            }
+            BnError::DuplicateState(v, s) => {
+                write!(f, "state {s} of variable {v} appears more than once")
+            }
+            BnError::EmptyStateGroup(v, g) => {
+                write!(f, "state group {g} of variable {v} is empty")
+            }
+            BnError::NonNumericState(v, s) => {
+                write!(f, "state {s} of variable {v} is not a number")
+            }
+            BnError::UnknownWeight(name) => write!(f, "could not find weight {name}"),
+            BnError::MissingValue(row, v) => write!(f, "row {row} has no value for variable {v}"),
+            BnError::InvalidValue(row, v, s) => {
+                write!(f, "row {row} gives variable {v} the unknown value {s}")
+            }
+            BnError::NoRows => write!(f, "no rows of data were given"),
+            BnError::ImpossibleColumn(column) => {
+                write!(f, "column {column} conditions on an event of probability 0")
+            }
+            BnError::Cycle(vars) => {
+                write!(f, "graph contains a cycle among {}", vars.join(", "))
+            }
+            BnError::Invalid(errors) => {
+                let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "malformed network: {}", errors.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for BnError {}
+
+/// One structural problem found by `BayesianNetwork::validate`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    /// a variable in `variables` has no entry in the named map (`cpts`,
+    /// `states`, or `parents`)
+    MissingEntry {
+        variable: String,
+        field: &'static str,
+    },
+    /// a variable has no states, so no assignment to the network exists
+    NoStates { variable: String },
+    /// a variable names a parent that is not in `variables`
+    UnknownParent { variable: String, parent: String },
+    /// a CPT does not have one row per state of its variable
+    RowCount {
+        variable: String,
+        expected: usize,
+        actual: usize,
+    },
+    /// row `row` of a CPT does not have one column per parent assignment
+    RowLength {
+        variable: String,
+        row: usize,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::MissingEntry { variable, field } => {
+                write!(f, "variable {variable} has no entry in {field}")
+            }
+            ValidationError::NoStates { variable } => {
+                write!(f, "variable {variable} has no states")
+            }
+            ValidationError::UnknownParent { variable, parent } => {
+                write!(f, "parent {parent} of variable {variable} does not exist")
+            }
+            ValidationError::RowCount {
+                variable,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "CPT for variable {variable} has {actual} rows, expected {expected}"
+            ),
+            ValidationError::RowLength {
+                variable,
+                row,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "row {row} of CPT for variable {variable} has {actual} columns, expected {expected}"
+            ),
+        }
+    }
+}
+
+/// A graph that should be acyclic has a cycle
+#[derive(Debug, Clone, PartialEq)]
+pub struct CycleError {
+    /// the variables on or below a cycle, sorted
+    pub variables: Vec<String>,
+}
+
+impl fmt::Display for CycleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "graph contains a cycle among {}",
+            self.variables.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for CycleError {}
+
+impl From<CycleError> for BnError {
+    fn from(err: CycleError) -> Self {
+        BnError::Cycle(err.variables)
+    }
+}
+
+impl From<std::io::Error> for BnError {
+    fn from(err: std::io::Error) -> Self {
+        BnError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for BnError {
+    fn from(err: serde_json::Error) -> Self {
+        BnError::Json(err)
+    }
+}
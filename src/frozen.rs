@@ -0,0 +1,284 @@
+//! An immutable, integer-indexed form of a Bayesian network for fast queries
+
+use std::collections::HashMap;
+
+use crate::{BayesianNetwork, BayesianNetworkCNF, BnError};
+
+/// A Bayesian network that can no longer be edited, with every name interned
+/// to an index and every lookup precomputed.
+///
+/// Variables are indexed by their position in the network's `variables` and
+/// states by their position in the variable's states. Parents are listed in
+/// CPT order. Queries return `None` instead of panicking when given an
+/// unknown name or an out-of-range index.
+#[derive(Debug, Clone)]
+pub struct FrozenNetwork {
+    /// the network this was frozen from
+    source: BayesianNetwork,
+    /// the network compiled once at freezing, handed out by
+    /// `BayesianNetworkCNF::from_frozen`
+    cnf: BayesianNetworkCNF,
+    variable_index: HashMap<String, usize>,
+    states: Vec<Vec<String>>,
+    state_index: Vec<HashMap<String, usize>>,
+    parents: Vec<Vec<usize>>,
+    children: Vec<Vec<usize>>,
+    /// `strides[v][i]`: how far the CPT column of `v` moves per state of its
+    /// `i`th parent
+    strides: Vec<Vec<usize>>,
+    /// `cpts[v][column * num_states(v) + state]`
+    cpts: Vec<Vec<f64>>,
+    topological_order: Vec<usize>,
+}
+
+impl BayesianNetwork {
+    /// Freezes the network into a [`FrozenNetwork`] for fast, panic-free
+    /// queries by index, compiling it to a CNF once along the way.
+    ///
+    /// Returns an error if the network fails `validate` or has a cycle.
+    /// ```
+    /// use rsgm::{BayesianNetwork, BnError};
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let frozen = BayesianNetwork::from_json(NETWORK).freeze().unwrap();
+    /// let (a, c) = (frozen.variable_index("A").unwrap(), frozen.variable_index("C").unwrap());
+    /// let t = frozen.state_index(c, "T").unwrap();
+    ///
+    /// assert_eq!(frozen.children(a), &[c]);
+    /// // Pr(C = T | A = T, B = F)
+    /// assert_eq!(frozen.conditional_probability(c, t, &[1, 0]), Some(0.7));
+    /// // Pr(A = T, B = T, C = T) = 0.5 * 0.75 * 0.6
+    /// assert!((frozen.joint_probability(&[1, 1, 1]).unwrap() - 0.225).abs() < 1e-9);
+    /// assert_eq!(frozen.variable_index("D"), None);
+    /// assert_eq!(frozen.conditional_probability(c, t, &[1]), None);
+    ///
+    /// let short_row = NETWORK.replace("[0.1, 0.2, 0.7, 0.6]", "[0.1, 0.2, 0.7]");
+    /// assert!(matches!(
+    ///     BayesianNetwork::from_json(&short_row).freeze(),
+    ///     Err(BnError::Invalid(_))
+    /// ));
+    /// ```
+    pub fn freeze(self) -> Result<FrozenNetwork, BnError> {
+        self.validate().map_err(BnError::Invalid)?;
+        self.try_topological_sort()?;
+        let variable_index: HashMap<String, usize> = self
+            .variables
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (v.clone(), i))
+            .collect();
+        let states: Vec<Vec<String>> = self
+            .variables
+            .iter()
+            .map(|v| self.states[v].clone())
+            .collect();
+        let state_index = states
+            .iter()
+            .map(|s| s.iter().enumerate().map(|(i, s)| (s.clone(), i)).collect())
+            .collect();
+        let parents: Vec<Vec<usize>> = self
+            .variables
+            .iter()
+            .map(|v| {
+                self.parents_in_cpt_order(v)
+                    .iter()
+                    .map(|p| variable_index[p])
+                    .collect()
+            })
+            .collect();
+        let mut children: Vec<Vec<usize>> = vec![Vec::new(); self.variables.len()];
+        for (child, ps) in parents.iter().enumerate() {
+            for p in ps.iter() {
+                children[*p].push(child);
+            }
+        }
+        let strides = parents
+            .iter()
+            .map(|ps| {
+                let mut stride = 1;
+                let mut strides: Vec<usize> = ps
+                    .iter()
+                    .rev()
+                    .map(|p| {
+                        let s = stride;
+                        stride *= states[*p].len();
+                        s
+                    })
+                    .collect();
+                strides.reverse();
+                strides
+            })
+            .collect();
+        let cpts = self
+            .variables
+            .iter()
+            .map(|v| {
+                let cpt = &self.cpts[v];
+                let num_columns = cpt.first().map_or(0, |row| row.len());
+                (0..num_columns * cpt.len())
+                    .map(|i| cpt[i % cpt.len()][i / cpt.len()])
+                    .collect()
+            })
+            .collect();
+        let topological_order = self
+            .topological_sort()
+            .iter()
+            .map(|v| variable_index[v])
+            .collect();
+        Ok(FrozenNetwork {
+            cnf: BayesianNetworkCNF::from_bayesian_network(&self),
+            source: self,
+            variable_index,
+            states,
+            state_index,
+            parents,
+            children,
+            strides,
+            cpts,
+            topological_order,
+        })
+    }
+}
+
+impl FrozenNetwork {
+    pub fn network(&self) -> &str {
+        &self.source.network
+    }
+
+    pub fn num_variables(&self) -> usize {
+        self.states.len()
+    }
+
+    /// the name of the variable at `var`
+    pub fn variable(&self, var: usize) -> Option<&str> {
+        self.source.variables.get(var).map(|v| v.as_str())
+    }
+
+    pub fn variable_index(&self, name: &str) -> Option<usize> {
+        self.variable_index.get(name).copied()
+    }
+
+    /// the states of the variable at `var`
+    pub fn states(&self, var: usize) -> Option<&[String]> {
+        self.states.get(var).map(|s| s.as_slice())
+    }
+
+    pub fn state_index(&self, var: usize, state: &str) -> Option<usize> {
+        self.state_index.get(var)?.get(state).copied()
+    }
+
+    /// the parents of the variable at `var`, in CPT order; empty if `var` is
+    /// out of range
+    pub fn parents(&self, var: usize) -> &[usize] {
+        self.parents.get(var).map_or(&[], |p| p.as_slice())
+    }
+
+    /// the children of the variable at `var`, in the order of `variables`;
+    /// empty if `var` is out of range
+    pub fn children(&self, var: usize) -> &[usize] {
+        self.children.get(var).map_or(&[], |c| c.as_slice())
+    }
+
+    /// every variable, parents before children
+    pub fn topological_order(&self) -> &[usize] {
+        &self.topological_order
+    }
+
+    /// Pr(var = state | parents = parent_states), where `parent_states` gives
+    /// the state of each parent in CPT order
+    pub fn conditional_probability(
+        &self,
+        var: usize,
+        state: usize,
+        parent_states: &[usize],
+    ) -> Option<f64> {
+        let parents = self.parents.get(var)?;
+        if parent_states.len() != parents.len() {
+            return None;
+        }
+        let mut column = 0;
+        for ((p, s), stride) in parents.iter().zip(parent_states).zip(&self.strides[var]) {
+            if *s >= self.states[*p].len() {
+                return None;
+            }
+            column += s * stride;
+        }
+        let num_states = self.states[var].len();
+        if state >= num_states {
+            return None;
+        }
+        self.cpts[var].get(column * num_states + state).copied()
+    }
+
+    /// Pr(assignment) for a full assignment giving the state of every
+    /// variable, by the chain rule
+    pub fn joint_probability(&self, assignment: &[usize]) -> Option<f64> {
+        if assignment.len() != self.num_variables() {
+            return None;
+        }
+        let mut p = 1.0;
+        for (var, state) in assignment.iter().enumerate() {
+            let parent_states: Vec<usize> =
+                self.parents[var].iter().map(|p| assignment[*p]).collect();
+            p *= self.conditional_probability(var, *state, &parent_states)?;
+        }
+        Some(p)
+    }
+}
+
+impl BayesianNetworkCNF {
+    /// The CNF of a frozen network, as `from_bayesian_network` compiled it
+    /// when the network was frozen; nothing is recompiled
+    /// ```
+    /// use rsgm::{BayesianNetwork, BayesianNetworkCNF};
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "network": "toy_network",
+    ///     "variables": ["A", "B", "C"],
+    ///     "cpts": {
+    ///         "A": [[0.5], [0.5]],
+    ///         "B": [[0.25], [0.75]],
+    ///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+    ///     },
+    ///     "states": {
+    ///         "A": ["F", "T"],
+    ///         "B": ["F", "T"],
+    ///         "C": ["F", "T"]
+    ///     },
+    ///     "parents" :{
+    ///         "A": [],
+    ///         "B": [],
+    ///         "C": ["A", "B"]
+    ///     }
+    /// }"#;
+    ///
+    /// let frozen = BayesianNetwork::from_json(NETWORK).freeze().unwrap();
+    /// let bn = BayesianNetworkCNF::from_frozen(&frozen);
+    /// // Pr(C = T) = 0.5 * (0.25 * 0.1 + 0.75 * 0.2 + 0.25 * 0.7 + 0.75 * 0.6)
+    /// assert!((bn.marginal("C", "T") - 0.4).abs() < 1e-9);
+    /// ```
+    pub fn from_frozen(network: &FrozenNetwork) -> BayesianNetworkCNF {
+        network.cnf.clone()
+    }
+}
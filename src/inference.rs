@@ -0,0 +1,607 @@
+//! Backend-agnostic probabilistic inference
+//!
+//! [`Inference`] is implemented by every way this crate can answer a query
+//! about a Bayesian network: exact weighted model counting ([`WmcEngine`]),
+//! exact variable elimination ([`VariableEliminationEngine`]), and
+//! approximate likelihood-weighted sampling ([`SamplingEngine`]). Code written
+//! against the trait can switch backends without changing its queries.
+
+use rand::{rngs::StdRng, SeedableRng};
+use std::collections::HashMap;
+
+use crate::{BayesianNetwork, BayesianNetworkCNF};
+
+/// Answers marginal, conditional, and MAP queries about a Bayesian network
+/// ```
+/// use rsgm::{
+///     BayesianNetwork, Inference, SamplingEngine, VariableEliminationEngine, WmcEngine,
+/// };
+/// use std::collections::HashMap;
+///
+/// // models the collider A, B -> C
+/// static NETWORK: &str = r#"{
+///     "network": "toy_network",
+///     "variables": ["A", "B", "C"],
+///     "cpts": {
+///         "A": [[0.5], [0.5]],
+///         "B": [[0.25], [0.75]],
+///         "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+///     },
+///     "states": {
+///         "A": ["F", "T"],
+///         "B": ["F", "T"],
+///         "C": ["F", "T"]
+///     },
+///     "parents" :{
+///         "A": [],
+///         "B": [],
+///         "C": ["A", "B"]
+///     }
+/// }"#;
+///
+/// fn explain_away(engine: &dyn Inference) -> f64 {
+///     let evidence = HashMap::from([(String::from("C"), String::from("T"))]);
+///     engine.conditional("A", "T", &evidence)
+/// }
+///
+/// let bn = BayesianNetwork::from_json(NETWORK);
+/// // Pr(A = T | C = T) = 0.5 * (0.25 * 0.7 + 0.75 * 0.6) / 0.4
+/// assert!((explain_away(&WmcEngine::new(&bn)) - 0.78125).abs() < 1e-9);
+/// assert!((explain_away(&VariableEliminationEngine::new(&bn)) - 0.78125).abs() < 1e-9);
+/// assert!((explain_away(&SamplingEngine::new(&bn, 20_000, 0)) - 0.78125).abs() < 0.05);
+/// ```
+pub trait Inference {
+    /// Computes Pr(var = value)
+    fn marginal(&self, var: &str, value: &str) -> f64 {
+        self.conditional(var, value, &HashMap::new())
+    }
+
+    /// Computes Pr(var = value | evidence); NaN if the evidence has
+    /// probability 0
+    fn conditional(&self, var: &str, value: &str, evidence: &HashMap<String, String>) -> f64;
+
+    /// Finds the most probable joint assignment to `vars` given `evidence`,
+    /// summing out every other variable, and returns it with its posterior
+    /// probability. Ties are broken arbitrarily; the probability is NaN if
+    /// the evidence has probability 0.
+    fn map(
+        &self,
+        vars: &[String],
+        evidence: &HashMap<String, String>,
+    ) -> (HashMap<String, String>, f64);
+}
+
+/// Picks the entry with the highest probability, keeping the first of equals
+fn argmax<I: IntoIterator<Item = (HashMap<String, String>, f64)>>(
+    entries: I,
+) -> (HashMap<String, String>, f64) {
+    let mut entries = entries.into_iter();
+    let first = entries.next().expect("no assignments to maximize over");
+    entries.fold(
+        first,
+        |best, entry| if entry.1 > best.1 { entry } else { best },
+    )
+}
+
+/// Exact inference by weighted model counting over a compiled CNF
+pub struct WmcEngine {
+    cnf: BayesianNetworkCNF,
+}
+
+impl WmcEngine {
+    pub fn new(network: &BayesianNetwork) -> WmcEngine {
+        WmcEngine {
+            cnf: BayesianNetworkCNF::from_bayesian_network(network),
+        }
+    }
+
+    pub fn cnf(&self) -> &BayesianNetworkCNF {
+        &self.cnf
+    }
+}
+
+impl From<BayesianNetworkCNF> for WmcEngine {
+    fn from(cnf: BayesianNetworkCNF) -> Self {
+        WmcEngine { cnf }
+    }
+}
+
+impl Inference for WmcEngine {
+    fn conditional(&self, var: &str, value: &str, evidence: &HashMap<String, String>) -> f64 {
+        let joint = self.cnf.joint_marginal(&[var.to_string()], evidence);
+        *joint
+            .get(&vec![(var.to_string(), value.to_string())])
+            .unwrap_or_else(|| panic!("could not find assignment {value} for variable {var}"))
+    }
+
+    fn map(
+        &self,
+        vars: &[String],
+        evidence: &HashMap<String, String>,
+    ) -> (HashMap<String, String>, f64) {
+        argmax(
+            self.cnf
+                .joint_marginal(vars, evidence)
+                .into_iter()
+                .map(|(row, p)| (row.into_iter().collect(), p)),
+        )
+    }
+}
+
+/// A table over the joint states of `vars`, stored with the last variable
+/// varying fastest (the same layout as a CPT column index)
+#[derive(Debug, Clone)]
+struct Factor {
+    vars: Vec<String>,
+    cards: Vec<usize>,
+    values: Vec<f64>,
+}
+
+/// the state indices of entry `idx` of a table with cardinalities `cards`
+fn decode(mut idx: usize, cards: &[usize]) -> Vec<usize> {
+    let mut states = vec![0; cards.len()];
+    for (state, card) in states.iter_mut().zip(cards).rev() {
+        *state = idx % card;
+        idx /= card;
+    }
+    states
+}
+
+impl Factor {
+    /// the CPT of `variable` as a factor over its parents and itself
+    fn from_cpt(network: &BayesianNetwork, variable: &str) -> Factor {
+        let mut vars = network.parents_in_cpt_order(variable).to_vec();
+        vars.push(variable.to_string());
+        let cards: Vec<usize> = vars.iter().map(|v| network.num_states(v)).collect();
+        let card = cards[cards.len() - 1];
+        let cpt = &network.cpts[variable];
+        let size = cards.iter().product();
+        let values = (0..size).map(|i| cpt[i % card][i / card]).collect();
+        Factor {
+            vars,
+            cards,
+            values,
+        }
+    }
+
+    /// the index into `values` of the entry agreeing with `states`, an
+    /// assignment to the (super)set of variables `vars`
+    fn offset(&self, vars: &[String], states: &[usize]) -> usize {
+        self.vars.iter().zip(&self.cards).fold(0, |idx, (v, card)| {
+            idx * card + states[vars.iter().position(|x| x == v).unwrap()]
+        })
+    }
+
+    fn product(&self, other: &Factor) -> Factor {
+        let mut vars = self.vars.clone();
+        let mut cards = self.cards.clone();
+        for (v, card) in other.vars.iter().zip(&other.cards) {
+            if !vars.contains(v) {
+                vars.push(v.clone());
+                cards.push(*card);
+            }
+        }
+        let size = cards.iter().product();
+        let values = (0..size)
+            .map(|i| {
+                let states = decode(i, &cards);
+                self.values[self.offset(&vars, &states)]
+                    * other.values[other.offset(&vars, &states)]
+            })
+            .collect();
+        Factor {
+            vars,
+            cards,
+            values,
+        }
+    }
+
+    /// sums `var` out of the factor
+    fn sum_out(&self, var: &str) -> Factor {
+        let pos = self.vars.iter().position(|v| v == var).unwrap();
+        let mut result = Factor {
+            vars: self.vars.clone(),
+            cards: self.cards.clone(),
+            values: vec![],
+        };
+        result.vars.remove(pos);
+        result.cards.remove(pos);
+        result.values = vec![0.0; result.cards.iter().product()];
+        for (i, p) in self.values.iter().enumerate() {
+            let states = decode(i, &self.cards);
+            let j = result.offset(&self.vars, &states);
+            result.values[j] += p;
+        }
+        result
+    }
+
+    /// fixes `var` to state index `state` and drops it from the factor
+    fn reduce(&self, var: &str, state: usize) -> Factor {
+        let pos = self.vars.iter().position(|v| v == var).unwrap();
+        let mut vars = self.vars.clone();
+        let mut cards = self.cards.clone();
+        vars.remove(pos);
+        cards.remove(pos);
+        let size = cards.iter().product();
+        let values = (0..size)
+            .map(|i| {
+                let mut states = decode(i, &cards);
+                states.insert(pos, state);
+                self.values[self.offset(&self.vars, &states)]
+            })
+            .collect();
+        Factor {
+            vars,
+            cards,
+            values,
+        }
+    }
+}
+
+/// Multiplies the factors that mention `var` and sums `var` out of the
+/// product
+fn eliminate(factors: Vec<Factor>, var: &str) -> Vec<Factor> {
+    let (touching, mut rest): (Vec<Factor>, Vec<Factor>) = factors
+        .into_iter()
+        .partition(|f| f.vars.iter().any(|v| v == var));
+    if let Some(product) = touching.into_iter().reduce(|a, b| a.product(&b)) {
+        rest.push(product.sum_out(var));
+    }
+    rest
+}
+
+/// Exact inference by variable elimination over the network's CPTs
+pub struct VariableEliminationEngine {
+    network: BayesianNetwork,
+    /// the order to eliminate variables in, if not chosen greedily
+    order: Option<Vec<String>>,
+}
+
+impl VariableEliminationEngine {
+    pub fn new(network: &BayesianNetwork) -> VariableEliminationEngine {
+        VariableEliminationEngine {
+            network: network.clone(),
+            order: None,
+        }
+    }
+
+    /// Like `new`, but eliminates variables in `order`, e.g. the one from
+    /// `BayesianNetwork::min_fill_ordering`, instead of choosing each
+    /// greedily. Query and evidence variables are skipped; variables missing
+    /// from `order` are eliminated greedily after the rest.
+    ///
+    /// Panics if `order` names a variable that is not in the network.
+    /// ```
+    /// use rsgm::{BayesianNetwork, Inference, VariableEliminationEngine};
+    /// use std::collections::HashMap;
+    ///
+    /// let bn = BayesianNetwork::from_bif(include_str!("../bayesian_networks/asia.bif")).unwrap();
+    /// let (order, _) = bn.min_fill_ordering();
+    /// let ordered = VariableEliminationEngine::with_order(&bn, order);
+    /// let greedy = VariableEliminationEngine::new(&bn);
+    ///
+    /// let evidence = HashMap::from([(String::from("xray"), String::from("yes"))]);
+    /// let p = ordered.conditional("lung", "yes", &evidence);
+    /// assert!((p - greedy.conditional("lung", "yes", &evidence)).abs() < 1e-9);
+    /// ```
+    pub fn with_order(network: &BayesianNetwork, order: Vec<String>) -> VariableEliminationEngine {
+        for var in order.iter() {
+            if !network.states.contains_key(var) {
+                panic!("could not find variable {var}");
+            }
+        }
+        VariableEliminationEngine {
+            network: network.clone(),
+            order: Some(order),
+        }
+    }
+
+    /// Computes the normalized joint distribution over `vars` given
+    /// `evidence` as a factor whose variables are exactly `vars`, in order.
+    ///
+    /// Variables are eliminated in the engine's order if it has one, and
+    /// otherwise greedily, each time choosing the one whose elimination
+    /// creates the smallest intermediate factor.
+    fn joint(&self, vars: &[String], evidence: &HashMap<String, String>) -> Factor {
+        let network = &self.network;
+        for var in vars {
+            if !network.states.contains_key(var) {
+                panic!("could not find variable {var}");
+            }
+        }
+        let evidence: HashMap<&str, usize> = evidence
+            .iter()
+            .map(|(var, value)| (var.as_str(), network.state_index(var, value)))
+            .collect();
+
+        let mut factors: Vec<Factor> = network
+            .variables
+            .iter()
+            .map(|v| {
+                let mut factor = Factor::from_cpt(network, v);
+                for (var, state) in evidence.iter() {
+                    if factor.vars.iter().any(|x| x == var) && !vars.iter().any(|x| x == var) {
+                        factor = factor.reduce(var, *state);
+                    }
+                }
+                factor
+            })
+            .collect();
+        // evidence on a query variable keeps the variable but zeroes its
+        // other states
+        for var in vars {
+            if let Some(state) = evidence.get(var.as_str()) {
+                let card = network.num_states(var);
+                factors.push(Factor {
+                    vars: vec![var.clone()],
+                    cards: vec![card],
+                    values: (0..card)
+                        .map(|i| if i == *state { 1.0 } else { 0.0 })
+                        .collect(),
+                });
+            }
+        }
+
+        let mut hidden: Vec<&String> = network
+            .variables
+            .iter()
+            .filter(|v| !vars.contains(v) && !evidence.contains_key(v.as_str()))
+            .collect();
+        if let Some(order) = &self.order {
+            for var in order.iter().filter(|v| hidden.contains(v)) {
+                factors = eliminate(factors, var);
+            }
+            hidden.retain(|v| !order.contains(v));
+        }
+        while !hidden.is_empty() {
+            let cost = |var: &String| -> usize {
+                let mut scope: Vec<(&String, usize)> = Vec::new();
+                for f in factors.iter().filter(|f| f.vars.contains(var)) {
+                    for (v, card) in f.vars.iter().zip(&f.cards) {
+                        if !scope.iter().any(|(x, _)| *x == v) {
+                            scope.push((v, *card));
+                        }
+                    }
+                }
+                scope.iter().map(|(_, card)| card).product()
+            };
+            let (pos, _) = hidden
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, v)| cost(v))
+                .unwrap();
+            let var = hidden.remove(pos);
+            factors = eliminate(factors, var);
+        }
+
+        let product = factors
+            .into_iter()
+            .reduce(|a, b| a.product(&b))
+            .expect("network has no variables");
+        // lay the result out in the order of `vars`
+        let cards: Vec<usize> = vars.iter().map(|v| network.num_states(v)).collect();
+        let size: usize = cards.iter().product();
+        let mut values: Vec<f64> = (0..size)
+            .map(|i| product.values[product.offset(vars, &decode(i, &cards))])
+            .collect();
+        let z: f64 = values.iter().sum();
+        for p in values.iter_mut() {
+            *p /= z;
+        }
+        Factor {
+            vars: vars.to_vec(),
+            cards,
+            values,
+        }
+    }
+}
+
+impl Inference for VariableEliminationEngine {
+    fn conditional(&self, var: &str, value: &str, evidence: &HashMap<String, String>) -> f64 {
+        let factor = self.joint(&[var.to_string()], evidence);
+        factor.values[self.network.state_index(var, value)]
+    }
+
+    fn map(
+        &self,
+        vars: &[String],
+        evidence: &HashMap<String, String>,
+    ) -> (HashMap<String, String>, f64) {
+        let factor = self.joint(vars, evidence);
+        argmax(factor.values.iter().enumerate().map(|(i, p)| {
+            let states = decode(i, &factor.cards);
+            let assignment = vars
+                .iter()
+                .zip(states)
+                .map(|(v, s)| (v.clone(), self.network.states[v][s].clone()))
+                .collect();
+            (assignment, *p)
+        }))
+    }
+}
+
+/// Approximate inference by likelihood weighting: every sample is drawn
+/// forward through the network with the evidence variables clamped, and is
+/// weighted by the probability of the evidence given its sampled parents.
+///
+/// Each query draws `num_samples` samples from a generator seeded with
+/// `seed`, so repeated queries are reproducible.
+pub struct SamplingEngine {
+    network: BayesianNetwork,
+    order: Vec<String>,
+    num_samples: usize,
+    seed: u64,
+}
+
+impl SamplingEngine {
+    pub fn new(network: &BayesianNetwork, num_samples: usize, seed: u64) -> SamplingEngine {
+        SamplingEngine {
+            network: network.clone(),
+            order: network.topological_sort(),
+            num_samples,
+            seed,
+        }
+    }
+
+    /// Draws `num_samples` weighted samples, each mapping every variable to
+    /// its state index
+    fn weighted_samples(
+        &self,
+        evidence: &HashMap<String, String>,
+    ) -> Vec<(HashMap<&str, usize>, f64)> {
+        let network = &self.network;
+        let evidence: HashMap<&str, usize> = evidence
+            .iter()
+            .map(|(var, value)| (var.as_str(), network.state_index(var, value)))
+            .collect();
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        (0..self.num_samples)
+            .map(|_| {
+                let sample = network.forward_sample_clamped(&self.order, &evidence, &mut rng);
+                let weight: f64 = evidence
+                    .iter()
+                    .map(|(var, state)| {
+                        network.cpts[*var][*state][network.sampled_column(var, &sample)]
+                    })
+                    .product();
+                (sample, weight)
+            })
+            .collect()
+    }
+}
+
+impl Inference for SamplingEngine {
+    fn conditional(&self, var: &str, value: &str, evidence: &HashMap<String, String>) -> f64 {
+        let state = self.network.state_index(var, value);
+        let samples = self.weighted_samples(evidence);
+        let total: f64 = samples.iter().map(|(_, w)| w).sum();
+        let hits: f64 = samples
+            .iter()
+            .filter(|(sample, _)| sample[var] == state)
+            .map(|(_, w)| w)
+            .sum();
+        hits / total
+    }
+
+    fn map(
+        &self,
+        vars: &[String],
+        evidence: &HashMap<String, String>,
+    ) -> (HashMap<String, String>, f64) {
+        for var in vars {
+            if !self.network.states.contains_key(var) {
+                panic!("could not find variable {var}");
+            }
+        }
+        let samples = self.weighted_samples(evidence);
+        let total: f64 = samples.iter().map(|(_, w)| w).sum();
+        let mut counts: HashMap<Vec<usize>, f64> = HashMap::new();
+        for (sample, weight) in samples.iter() {
+            let key = vars.iter().map(|v| sample[v.as_str()]).collect();
+            *counts.entry(key).or_insert(0.0) += weight;
+        }
+        argmax(counts.into_iter().map(|(key, weight)| {
+            let assignment = vars
+                .iter()
+                .zip(key)
+                .map(|(v, s)| (v.clone(), self.network.states[v][s].clone()))
+                .collect();
+            (assignment, weight / total)
+        }))
+    }
+}
+
+#[test]
+fn test_backends_agree() {
+    static NETWORK: &str = r#"{
+        "network": "toy_network",
+        "variables": ["A", "B", "C"],
+        "cpts": {
+            "A": [[0.5], [0.5]],
+            "B": [[0.25], [0.75]],
+            "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+        },
+        "states": {
+            "A": ["F", "T"],
+            "B": ["F", "T"],
+            "C": ["F", "T"]
+        },
+        "parents" :{
+            "A": [],
+            "B": [],
+            "C": ["A", "B"]
+        }
+    }"#;
+
+    let bn = BayesianNetwork::from_json(NETWORK);
+    let wmc = WmcEngine::new(&bn);
+    let ve = VariableEliminationEngine::new(&bn);
+    let sampling = SamplingEngine::new(&bn, 20_000, 7);
+    let c_t = HashMap::from([(String::from("C"), String::from("T"))]);
+    let a_b = [String::from("A"), String::from("B")];
+
+    let engines: [(&dyn Inference, f64); 3] = [(&wmc, 1e-9), (&ve, 1e-9), (&sampling, 0.05)];
+    for (engine, tolerance) in engines {
+        assert!((engine.marginal("C", "T") - 0.4).abs() < tolerance);
+        assert!((engine.conditional("B", "T", &c_t) - 0.75).abs() < tolerance);
+        // evidence on the query variable itself
+        assert!((engine.conditional("C", "F", &c_t)).abs() < tolerance);
+
+        let (assignment, p) = engine.map(&a_b, &c_t);
+        assert_eq!(assignment["A"], "T");
+        assert_eq!(assignment["B"], "T");
+        assert!((p - 0.5625).abs() < tolerance);
+    }
+}
+
+#[test]
+fn test_variable_elimination_agrees_with_enumeration() {
+    static NETWORK: &str = r#"{
+        "network": "toy_network",
+        "variables": ["A", "B", "C"],
+        "cpts": {
+            "A": [[0.5], [0.5]],
+            "B": [[0.25], [0.75]],
+            "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]]
+        },
+        "states": {
+            "A": ["F", "T"],
+            "B": ["F", "T"],
+            "C": ["F", "T"]
+        },
+        "parents" :{
+            "A": [],
+            "B": [],
+            "C": ["A", "B"]
+        }
+    }"#;
+
+    for bn in [
+        BayesianNetwork::from_json(NETWORK),
+        BayesianNetwork::from_bif(include_str!("../bayesian_networks/asia.bif")).unwrap(),
+    ] {
+        let mut reversed = bn.topological_sort();
+        reversed.reverse();
+        let engines = [
+            VariableEliminationEngine::new(&bn),
+            VariableEliminationEngine::with_order(&bn, bn.min_fill_ordering().0),
+            VariableEliminationEngine::with_order(&bn, reversed),
+        ];
+        let last = bn.variables().last().unwrap();
+        let observed = HashMap::from([(last.clone(), bn.states[last][1].clone())]);
+        for evidence in [HashMap::new(), observed] {
+            for var in bn.variables().iter() {
+                for value in bn.states[var].iter() {
+                    let expected = bn.marginal_by_enumeration(var, value, &evidence);
+                    for engine in engines.iter() {
+                        let p = engine.conditional(var, value, &evidence);
+                        assert!(
+                            (p - expected).abs() < 1e-9,
+                            "{var} = {value}: {p} vs {expected}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
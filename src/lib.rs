@@ -1,5 +1,26 @@
 mod bayesian_network;
+mod bif;
+mod builder;
 mod compiler;
+mod error;
+mod frozen;
+mod inference;
+mod log_semiring;
+mod max_product_semiring;
+mod pgmpy;
+pub mod sampling;
+mod serialization;
+pub mod structure_learning;
+mod warning;
+mod xdsl;
 
 pub use self::bayesian_network::*;
+pub use self::builder::*;
 pub use self::compiler::*;
+pub use self::error::*;
+pub use self::frozen::*;
+pub use self::inference::*;
+pub use self::log_semiring::*;
+pub use self::max_product_semiring::*;
+pub use self::serialization::*;
+pub use self::warning::*;
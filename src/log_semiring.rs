@@ -0,0 +1,65 @@
+//! A semiring for weighted model counting in log space
+
+use std::{
+    fmt,
+    ops::{Add, Mul},
+};
+
+use rsdd::util::semirings::Semiring;
+
+/// The real numbers under + and *, each represented by its natural
+/// logarithm, so that products of many small probabilities do not underflow.
+/// Multiplication adds logarithms and addition is log-sum-exp; 0 is
+/// represented by negative infinity.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct LogSemiring(pub f64);
+
+impl LogSemiring {
+    /// the representation of the real number `x`, which must be
+    /// non-negative
+    pub fn from_real(x: f64) -> LogSemiring {
+        LogSemiring(x.ln())
+    }
+}
+
+impl fmt::Display for LogSemiring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "exp({})", self.0)
+    }
+}
+
+impl Add for LogSemiring {
+    type Output = LogSemiring;
+
+    fn add(self, other: LogSemiring) -> LogSemiring {
+        let (hi, lo) = if self.0 >= other.0 {
+            (self.0, other.0)
+        } else {
+            (other.0, self.0)
+        };
+        if lo == f64::NEG_INFINITY {
+            return LogSemiring(hi);
+        }
+        LogSemiring(hi + (lo - hi).exp().ln_1p())
+    }
+}
+
+impl Mul for LogSemiring {
+    type Output = LogSemiring;
+
+    // multiplying reals adds their logarithms
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn mul(self, other: LogSemiring) -> LogSemiring {
+        LogSemiring(self.0 + other.0)
+    }
+}
+
+impl Semiring for LogSemiring {
+    fn one() -> LogSemiring {
+        LogSemiring(0.0)
+    }
+
+    fn zero() -> LogSemiring {
+        LogSemiring(f64::NEG_INFINITY)
+    }
+}
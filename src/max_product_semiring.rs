@@ -0,0 +1,49 @@
+//! A semiring for most probable explanations
+
+use std::{
+    fmt,
+    ops::{Add, Mul},
+};
+
+use rsdd::util::semirings::Semiring;
+
+/// The non-negative reals under max and *. The weighted model count of a CNF
+/// in this semiring is the largest weight of any single model, so for a
+/// compiled Bayesian network it is the probability of the most probable
+/// explanation rather than of the evidence.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct MaxProductSemiring(pub f64);
+
+impl fmt::Display for MaxProductSemiring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Add for MaxProductSemiring {
+    type Output = MaxProductSemiring;
+
+    // the sum over models becomes a max over models
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn add(self, other: MaxProductSemiring) -> MaxProductSemiring {
+        MaxProductSemiring(self.0.max(other.0))
+    }
+}
+
+impl Mul for MaxProductSemiring {
+    type Output = MaxProductSemiring;
+
+    fn mul(self, other: MaxProductSemiring) -> MaxProductSemiring {
+        MaxProductSemiring(self.0 * other.0)
+    }
+}
+
+impl Semiring for MaxProductSemiring {
+    fn one() -> MaxProductSemiring {
+        MaxProductSemiring(1.0)
+    }
+
+    fn zero() -> MaxProductSemiring {
+        MaxProductSemiring(0.0)
+    }
+}
@@ -0,0 +1,284 @@
+//! Import of networks exported by pgmpy as JSON
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::{collections::HashMap, sync::OnceLock};
+
+use crate::{BayesianNetwork, BnError};
+
+fn syntax_error(message: impl Into<String>) -> BnError {
+    BnError::Syntax {
+        format: "pgmpy JSON",
+        message: message.into(),
+    }
+}
+
+/// a whole exported model
+#[derive(Deserialize)]
+struct PgmpyNetwork {
+    #[serde(default)]
+    name: String,
+    nodes: Vec<String>,
+    #[serde(default)]
+    edges: Vec<(String, String)>,
+    cpds: Vec<PgmpyCpd>,
+}
+
+/// one exported `TabularCPD`
+#[derive(Deserialize)]
+struct PgmpyCpd {
+    variable: String,
+    variable_card: usize,
+    /// either one row per state of `variable`, or the same table flattened
+    /// column by column
+    values: Value,
+    evidence: Option<Vec<String>>,
+    evidence_card: Option<Vec<usize>>,
+    #[serde(default)]
+    state_names: HashMap<String, Vec<Value>>,
+}
+
+/// pgmpy writes state names as strings or, by default, as integers
+fn state_name(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Reads a CPD's `values` as a `rows` x `columns` CPT
+fn cpd_table(cpd: &PgmpyCpd, rows: usize, columns: usize) -> Result<Vec<Vec<f64>>, BnError> {
+    let number = |v: &Value| {
+        v.as_f64().ok_or_else(|| {
+            syntax_error(format!("{} has non-numeric probability {v}", cpd.variable))
+        })
+    };
+    let shape_error = || BnError::CptShape {
+        variable: cpd.variable.clone(),
+        expected: (rows, columns),
+    };
+    let values = cpd
+        .values
+        .as_array()
+        .ok_or_else(|| syntax_error(format!("{} has no array of values", cpd.variable)))?;
+    if values.iter().all(|row| row.is_array()) {
+        if values.len() != rows {
+            return Err(shape_error());
+        }
+        values
+            .iter()
+            .map(|row| {
+                let row = row.as_array().unwrap();
+                if row.len() != columns {
+                    return Err(shape_error());
+                }
+                row.iter().map(number).collect()
+            })
+            .collect()
+    } else {
+        if values.len() != rows * columns {
+            return Err(shape_error());
+        }
+        let flat = values
+            .iter()
+            .map(number)
+            .collect::<Result<Vec<f64>, BnError>>()?;
+        Ok((0..rows)
+            .map(|s| (0..columns).map(|c| flat[c * rows + s]).collect())
+            .collect())
+    }
+}
+
+impl BayesianNetwork {
+    /// Reads a network exported by pgmpy as JSON, with a list of `nodes`, a
+    /// list of `edges` as `[parent, child]` pairs, and one entry of `cpds`
+    /// per node.
+    ///
+    /// Each CPD gives its `variable`, its number of states `variable_card`,
+    /// its parents as `evidence` (in CPT order) with their numbers of states
+    /// as `evidence_card`, and optionally `state_names` mapping variables to
+    /// their states; states default to `0`, `1`, ... as in pgmpy. Its
+    /// `values` are either one row per state of the variable, or the same
+    /// table flattened column-major, i.e. one column after another; in both
+    /// cases the columns are ordered with the last parent varying fastest, as
+    /// in this crate. The edges must be exactly those implied by the CPDs.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"{
+    ///     "name": "toy_network",
+    ///     "nodes": ["A", "B", "C"],
+    ///     "edges": [["A", "C"], ["B", "C"]],
+    ///     "cpds": [
+    ///         {"variable": "A", "variable_card": 2, "values": [[0.5], [0.5]],
+    ///          "state_names": {"A": ["F", "T"]}},
+    ///         {"variable": "B", "variable_card": 2, "values": [[0.25], [0.75]],
+    ///          "state_names": {"B": ["F", "T"]}},
+    ///         {"variable": "C", "variable_card": 2,
+    ///          "values": [0.9, 0.1, 0.8, 0.2, 0.3, 0.7, 0.4, 0.6],
+    ///          "evidence": ["A", "B"], "evidence_card": [2, 2],
+    ///          "state_names": {"C": ["F", "T"], "A": ["F", "T"], "B": ["F", "T"]}}
+    ///     ]
+    /// }"#;
+    ///
+    /// let bn = BayesianNetwork::from_pgmpy_json(NETWORK).unwrap();
+    ///
+    /// assert_eq!(bn.variables(), &vec!["A", "B", "C"]);
+    /// assert_eq!(bn.conditional_probability("C", "T", &HashMap::from([
+    ///     (String::from("A"), String::from("T")),
+    ///     (String::from("B"), String::from("F"))
+    /// ])), 0.7);
+    /// ```
+    pub fn from_pgmpy_json(text: &str) -> Result<BayesianNetwork, BnError> {
+        let export: PgmpyNetwork = serde_json::from_str(text)?;
+        let mut cpds: HashMap<&str, &PgmpyCpd> = HashMap::new();
+        for cpd in export.cpds.iter() {
+            if !export.nodes.contains(&cpd.variable) {
+                return Err(BnError::UnknownVariable(cpd.variable.clone()));
+            }
+            if cpds.insert(&cpd.variable, cpd).is_some() {
+                return Err(BnError::VariableCollision(cpd.variable.clone()));
+            }
+        }
+
+        let mut bn = BayesianNetwork {
+            network: export.name.clone(),
+            variables: Vec::new(),
+            cpts: HashMap::new(),
+            states: HashMap::new(),
+            parents: HashMap::new(),
+            metadata: HashMap::new(),
+            topological_order: OnceLock::new(),
+        };
+        for node in export.nodes.iter() {
+            if bn.states.contains_key(node) {
+                return Err(BnError::VariableCollision(node.clone()));
+            }
+            let cpd = cpds
+                .get(node.as_str())
+                .ok_or_else(|| syntax_error(format!("{node} has no CPD")))?;
+            let states: Vec<String> = match cpd.state_names.get(node) {
+                Some(names) => names.iter().map(state_name).collect(),
+                None => (0..cpd.variable_card).map(|i| i.to_string()).collect(),
+            };
+            if states.len() != cpd.variable_card {
+                return Err(syntax_error(format!(
+                    "{node} has {} state names but variable_card {}",
+                    states.len(),
+                    cpd.variable_card
+                )));
+            }
+            for (i, state) in states.iter().enumerate() {
+                if states[..i].contains(state) {
+                    return Err(BnError::DuplicateState(node.clone(), state.clone()));
+                }
+            }
+            bn.variables.push(node.clone());
+            bn.states.insert(node.clone(), states);
+            bn.parents
+                .insert(node.clone(), cpd.evidence.clone().unwrap_or_default());
+        }
+
+        let mut num_edges = 0;
+        for variable in bn.variables.iter() {
+            let cpd = cpds[variable.as_str()];
+            let parents = &bn.parents[variable];
+            num_edges += parents.len();
+            for parent in parents.iter() {
+                if !bn.states.contains_key(parent) {
+                    return Err(BnError::UnknownVariable(parent.clone()));
+                }
+            }
+            if let Some(cards) = &cpd.evidence_card {
+                let expected: Vec<usize> = parents.iter().map(|p| bn.num_states(p)).collect();
+                if *cards != expected {
+                    return Err(syntax_error(format!(
+                        "evidence_card of {variable} is {cards:?}, but its evidence has {expected:?} states"
+                    )));
+                }
+            }
+            let columns: usize = parents.iter().map(|p| bn.num_states(p)).product();
+            let cpt = cpd_table(cpd, bn.num_states(variable), columns)?;
+            bn.cpts.insert(variable.clone(), cpt);
+        }
+        let edges_match = export.edges.len() == num_edges
+            && export.edges.iter().all(|(parent, child)| {
+                bn.parents
+                    .get(child)
+                    .is_some_and(|parents| parents.contains(parent))
+            });
+        if !edges_match {
+            return Err(syntax_error("edges do not match the evidence of the CPDs"));
+        }
+        bn.try_topological_sort()?;
+        Ok(bn)
+    }
+}
+
+#[test]
+fn test_from_pgmpy_json_errors() {
+    fn network(c_cpd: &str, edges: &str) -> String {
+        format!(
+            r#"{{
+                "nodes": ["A", "C"],
+                "edges": {edges},
+                "cpds": [
+                    {{"variable": "A", "variable_card": 2, "values": [[0.5], [0.5]]}},
+                    {c_cpd}
+                ]
+            }}"#
+        )
+    }
+
+    // default state names, and a table with one row per state
+    let ok = BayesianNetwork::from_pgmpy_json(&network(
+        r#"{"variable": "C", "variable_card": 3, "values": [[0.2, 1], [0.3, 0], [0.5, 0]],
+            "evidence": ["A"], "evidence_card": [2]}"#,
+        r#"[["A", "C"]]"#,
+    ))
+    .unwrap();
+    assert_eq!(ok.states["A"], vec!["0", "1"]);
+    assert_eq!(
+        ok.cpts["C"],
+        vec![vec![0.2, 1.0], vec![0.3, 0.0], vec![0.5, 0.0]]
+    );
+
+    assert!(matches!(
+        BayesianNetwork::from_pgmpy_json(&network(
+            r#"{"variable": "C", "variable_card": 1, "values": [1, 1, 1], "evidence": ["A"]}"#,
+            r#"[["A", "C"]]"#,
+        )),
+        Err(BnError::CptShape {
+            expected: (1, 2),
+            ..
+        })
+    ));
+    assert!(matches!(
+        BayesianNetwork::from_pgmpy_json(&network(
+            r#"{"variable": "C", "variable_card": 1, "values": [1], "evidence": ["Q"]}"#,
+            r#"[["Q", "C"]]"#,
+        )),
+        Err(BnError::UnknownVariable(v)) if v == "Q"
+    ));
+    assert!(matches!(
+        BayesianNetwork::from_pgmpy_json(&network(
+            r#"{"variable": "C", "variable_card": 1, "values": [1, 1], "evidence": ["A"]}"#,
+            "[]",
+        )),
+        Err(BnError::Syntax { .. })
+    ));
+    assert!(matches!(
+        BayesianNetwork::from_pgmpy_json(&network(
+            r#"{"variable": "C", "variable_card": 1, "values": [1, 1], "evidence": ["A"],
+                "evidence_card": [3]}"#,
+            r#"[["A", "C"]]"#,
+        )),
+        Err(BnError::Syntax { .. })
+    ));
+    assert!(matches!(
+        BayesianNetwork::from_pgmpy_json(r#"{"nodes": ["A"], "cpds": []}"#),
+        Err(BnError::Syntax { .. })
+    ));
+}
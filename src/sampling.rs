@@ -0,0 +1,91 @@
+//! Building blocks for sampling-based inference
+
+use rand::Rng;
+
+/// Resamples weighted particles by systematic (low-variance) resampling,
+/// returning as many particles as were given.
+///
+/// A single uniform offset `u` in `[0, 1/n)` places `n` evenly spaced
+/// pointers `u, u + 1/n, ...` across the normalized cumulative weights, and
+/// each pointer selects the particle whose weight interval it falls in. A
+/// particle with normalized weight `w` is thus copied either `floor(n * w)` or
+/// `ceil(n * w)` times. Weights need not be normalized; if they are all zero
+/// (e.g. no particle is consistent with the evidence) no particle survives
+/// and the result is empty.
+/// ```
+/// use rand::{rngs::StdRng, SeedableRng};
+/// use rsgm::sampling::systematic_resample;
+///
+/// let particles = vec![("a", 0.5), ("b", 1.5), ("c", 0.0)];
+/// let resampled = systematic_resample(&particles, &mut StdRng::seed_from_u64(0));
+///
+/// assert_eq!(resampled.len(), 3);
+/// assert!(!resampled.contains(&"c"));
+/// assert!(resampled.iter().filter(|p| **p == "b").count() >= 2);
+/// ```
+pub fn systematic_resample<T: Clone, R: Rng>(particles: &[(T, f64)], rng: &mut R) -> Vec<T> {
+    let n = particles.len();
+    let total: f64 = particles.iter().map(|(_, w)| w).sum();
+    if n == 0 || total <= 0.0 {
+        return Vec::new();
+    }
+    let step = total / n as f64;
+    let mut pointer = rng.gen::<f64>() * step;
+    let mut resampled: Vec<T> = Vec::with_capacity(n);
+    let mut cumulative = 0.0;
+    for (particle, weight) in particles.iter() {
+        cumulative += weight;
+        while pointer < cumulative && resampled.len() < n {
+            resampled.push(particle.clone());
+            pointer += step;
+        }
+    }
+    // rounding can leave the last pointers just past the final cumulative
+    // weight; they belong to the last particle with any weight
+    if resampled.len() < n {
+        let (last, _) = particles.iter().rev().find(|(_, w)| *w > 0.0).unwrap();
+        resampled.resize(n, last.clone());
+    }
+    resampled
+}
+
+/// Draws an index with probability proportional to its weight, by walking
+/// the cumulative weights; weights need not be normalized
+pub(crate) fn sample_index<R: Rng>(weights: &[f64], rng: &mut R) -> usize {
+    let total: f64 = weights.iter().sum();
+    let mut u = rng.gen::<f64>() * total;
+    for (i, w) in weights.iter().enumerate() {
+        if u < *w {
+            return i;
+        }
+        u -= w;
+    }
+    weights.len() - 1
+}
+
+#[test]
+fn test_systematic_resample() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let particles: Vec<(usize, f64)> = (0..100)
+        .map(|i| (i, if i < 10 { 9.0 } else { 0.1 }))
+        .collect();
+    for _ in 0..20 {
+        let resampled = systematic_resample(&particles, &mut rng);
+        assert_eq!(resampled.len(), 100);
+        // the first 10 particles carry 90 / 99 of the weight, so each is
+        // copied 9 or 10 times
+        for i in 0..10 {
+            let copies = resampled.iter().filter(|p| **p == i).count();
+            assert!(
+                (9..=10).contains(&copies),
+                "particle {i} copied {copies} times"
+            );
+        }
+        assert!(resampled.iter().filter(|p| **p >= 10).count() <= 10);
+    }
+
+    assert!(systematic_resample(&[("a", 0.0)], &mut rng).is_empty());
+    assert!(systematic_resample::<&str, _>(&[], &mut rng).is_empty());
+}
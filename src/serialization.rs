@@ -0,0 +1,151 @@
+//! A versioned, deterministic on-disk format for Bayesian networks
+//!
+//! Files written by [`serialize_v1`] carry a `format_version` tag and store
+//! every map with sorted keys, so the same network always produces the same
+//! bytes. [`deserialize_any`] reads any version this crate knows about,
+//! including the original untagged layout accepted by
+//! [`BayesianNetwork::from_json`].
+
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, sync::OnceLock};
+
+use crate::{bayesian_network::deserialize_states, BayesianNetwork, BnError, VariableMeta};
+
+/// the `format_version` written by [`serialize_v1`]
+pub const FORMAT_VERSION_V1: u64 = 1;
+
+/// The version 1 on-disk layout of a Bayesian network
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SerializedNetwork {
+    format_version: u64,
+    network: String,
+    variables: Vec<String>,
+    cpts: BTreeMap<String, Vec<Vec<f64>>>,
+    #[serde(deserialize_with = "deserialize_states")]
+    states: BTreeMap<String, Vec<String>>,
+    parents: BTreeMap<String, Vec<String>>,
+    /// added after version 1 was released, so optional
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, VariableMeta>,
+}
+
+impl SerializedNetwork {
+    pub fn format_version(&self) -> u64 {
+        self.format_version
+    }
+}
+
+impl From<&BayesianNetwork> for SerializedNetwork {
+    fn from(bn: &BayesianNetwork) -> Self {
+        SerializedNetwork {
+            format_version: FORMAT_VERSION_V1,
+            network: bn.network.clone(),
+            variables: bn.variables.clone(),
+            cpts: bn.cpts.clone().into_iter().collect(),
+            states: bn.states.clone().into_iter().collect(),
+            parents: bn.parents.clone().into_iter().collect(),
+            metadata: bn.metadata.clone().into_iter().collect(),
+        }
+    }
+}
+
+impl From<SerializedNetwork> for BayesianNetwork {
+    fn from(sn: SerializedNetwork) -> Self {
+        BayesianNetwork {
+            network: sn.network,
+            variables: sn.variables,
+            cpts: sn.cpts.into_iter().collect(),
+            states: sn.states.into_iter().collect(),
+            parents: sn.parents.into_iter().collect(),
+            metadata: sn.metadata.into_iter().collect(),
+            topological_order: OnceLock::new(),
+        }
+    }
+}
+
+/// only used to peek at the version tag before committing to a layout
+#[derive(Deserialize)]
+struct VersionProbe {
+    format_version: Option<u64>,
+}
+
+/// Serialize `bn` in the version 1 format.
+///
+/// Maps are written with sorted keys and `variables` keeps its declared order,
+/// so the output is byte-for-byte reproducible.
+pub fn serialize_v1(bn: &BayesianNetwork) -> String {
+    serde_json::to_string(&SerializedNetwork::from(bn))
+        .expect("a Bayesian network is always serializable")
+}
+
+/// Deserialize a network written in any known format:
+/// - no `format_version`: the original layout read by `BayesianNetwork::from_json`
+/// - `format_version: 1`: the layout written by [`serialize_v1`]
+pub fn deserialize_any(str: &str) -> Result<BayesianNetwork, BnError> {
+    let probe: VersionProbe = serde_json::from_str(str)?;
+    match probe.format_version {
+        None => Ok(serde_json::from_str::<BayesianNetwork>(str)?),
+        Some(FORMAT_VERSION_V1) => Ok(serde_json::from_str::<SerializedNetwork>(str)?.into()),
+        Some(version) => Err(BnError::UnsupportedFormatVersion(version)),
+    }
+}
+
+#[test]
+fn test_serialize_v1_pinned() {
+    static NETWORK: &str = r#"{
+        "network": "toy_network",
+        "variables": ["A", "B", "C"],
+        "cpts": {
+            "C": [[0.9, 0.8, 0.3, 0.4], [0.1, 0.2, 0.7, 0.6]],
+            "A": [[0.5], [0.5]],
+            "B": [[0.25], [0.75]]
+        },
+        "states": {
+            "A": ["F", "T"],
+            "C": ["F", "T"],
+            "B": ["F", "T"]
+        },
+        "parents" :{
+            "C": ["A", "B"],
+            "B": [],
+            "A": []
+        }
+    }"#;
+
+    let bn = BayesianNetwork::from_json(NETWORK);
+    let serialized = serialize_v1(&bn);
+    assert_eq!(serialized, PINNED_V1);
+
+    // the legacy layout and v1 both load, and re-serializing is a fixpoint
+    let legacy = deserialize_any(NETWORK).unwrap();
+    assert_eq!(serialize_v1(&legacy), PINNED_V1);
+    let v1 = deserialize_any(&serialized).unwrap();
+    assert_eq!(serialize_v1(&v1), PINNED_V1);
+
+    // metadata is written only when present, and read back
+    let mut annotated = bn.clone();
+    let meta = VariableMeta {
+        position: Some((1.0, 2.0)),
+        ..Default::default()
+    };
+    annotated.set_metadata("A", meta.clone()).unwrap();
+    let serialized = serialize_v1(&annotated);
+    assert!(serialized.ends_with(r#""metadata":{"A":{"position":[1.0,2.0]}}}"#));
+    assert_eq!(
+        deserialize_any(&serialized).unwrap().metadata("A"),
+        Some(&meta)
+    );
+
+    assert!(matches!(
+        deserialize_any(r#"{"format_version": 99}"#),
+        Err(BnError::UnsupportedFormatVersion(99))
+    ));
+}
+
+#[cfg(test)]
+static PINNED_V1: &str = concat!(
+    r#"{"format_version":1,"network":"toy_network","variables":["A","B","C"],"#,
+    r#""cpts":{"A":[[0.5],[0.5]],"B":[[0.25],[0.75]],"C":[[0.9,0.8,0.3,0.4],[0.1,0.2,0.7,0.6]]},"#,
+    r#""states":{"A":["F","T"],"B":["F","T"],"C":["F","T"]},"#,
+    r#""parents":{"A":[],"B":[],"C":["A","B"]}}"#
+);
@@ -0,0 +1,339 @@
+//! Learning the graph of a Bayesian network from data
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::OnceLock,
+};
+
+use crate::{BayesianNetwork, BnError, States};
+
+/// How many recently visited graphs `hill_climb` refuses to return to
+const TABU_LENGTH: usize = 10;
+
+/// How many moves in a row `hill_climb` makes without beating the best score
+/// so far before giving up
+const MAX_NON_IMPROVING_MOVES: usize = 10;
+
+/// One change to the graph considered by `hill_climb`
+#[derive(Debug, Clone, Copy)]
+enum Move<'a> {
+    Add(&'a str, &'a str),
+    Remove(&'a str, &'a str),
+    Reverse(&'a str, &'a str),
+}
+
+/// Learns a Bayesian network over `variables` from fully observed `data` by
+/// greedy hill climbing on the BIC score: starting from the empty graph, it
+/// repeatedly applies whichever single edge addition, removal, or reversal
+/// improves the score most, skipping moves that would create a cycle. Once no
+/// move improves the score it keeps taking the best move for a while, never
+/// returning to a recently visited graph, since graphs that score the same
+/// (for instance by reversing one edge of a complete graph) can separate a
+/// local optimum from a better one. The best graph seen has its CPTs fit
+/// with `BayesianNetwork::fit_cpts_mle`.
+///
+/// The BIC score of a graph is the log-likelihood of the data under its
+/// maximum-likelihood CPTs, minus ln(N)/2 per free parameter for N rows.
+/// It is a sum of one term per variable and its parents, so each move only
+/// rescores the families it changes, and every family's score is cached.
+///
+/// Returns an error if a variable has no entry in `states`, or for the rows
+/// `BayesianNetwork::log_likelihood` would reject.
+/// ```
+/// use rand::{rngs::StdRng, SeedableRng};
+/// use rsgm::{structure_learning::hill_climb, BayesianNetwork};
+///
+/// // models the collider A, B -> C, where C is usually true if A or B is
+/// static NETWORK: &str = r#"{
+///     "network": "toy_network",
+///     "variables": ["A", "B", "C"],
+///     "cpts": {
+///         "A": [[0.5], [0.5]],
+///         "B": [[0.25], [0.75]],
+///         "C": [[0.95, 0.2, 0.2, 0.05], [0.05, 0.8, 0.8, 0.95]]
+///     },
+///     "states": {
+///         "A": ["F", "T"],
+///         "B": ["F", "T"],
+///         "C": ["F", "T"]
+///     },
+///     "parents" :{
+///         "A": [],
+///         "B": [],
+///         "C": ["A", "B"]
+///     }
+/// }"#;
+///
+/// let truth = BayesianNetwork::from_json(NETWORK);
+/// let data = truth.sample_n(5000, &mut StdRng::seed_from_u64(0));
+/// let states = truth
+///     .variables()
+///     .iter()
+///     .map(|v| (v.clone(), truth.all_possible_assignments(v).clone()))
+///     .collect();
+///
+/// let learned = hill_climb(&data, truth.variables(), &states).unwrap();
+/// let mut parents = learned.parents("C").clone();
+/// parents.sort();
+/// assert_eq!(parents, vec!["A", "B"]);
+/// assert!(learned.parents("A").is_empty() && learned.parents("B").is_empty());
+/// ```
+pub fn hill_climb(
+    data: &[HashMap<String, String>],
+    variables: &[String],
+    states: &States,
+) -> Result<BayesianNetwork, BnError> {
+    let mut bn = BayesianNetwork {
+        network: String::from("hill_climb"),
+        variables: variables.to_vec(),
+        cpts: HashMap::new(),
+        states: HashMap::new(),
+        parents: HashMap::new(),
+        metadata: HashMap::new(),
+        topological_order: OnceLock::new(),
+    };
+    for var in variables.iter() {
+        let var_states = states
+            .get(var)
+            .ok_or_else(|| BnError::UnknownVariable(var.clone()))?;
+        bn.states.insert(var.clone(), var_states.clone());
+        bn.parents.insert(var.clone(), Vec::new());
+        let num_states = var_states.len();
+        bn.cpts
+            .insert(var.clone(), vec![vec![1.0 / num_states as f64]; num_states]);
+    }
+    bn.check_complete_rows(data)?;
+
+    let mut scores = FamilyScores::new(&bn, data);
+    let mut score: f64 = variables.iter().map(|v| scores.score(v, &[])).sum();
+    let mut best_score = score;
+    let mut best = bn.clone();
+    let mut non_improving = 0;
+    let mut tabu = VecDeque::from([graph_of(&bn)]);
+    loop {
+        let mut moves: Vec<(f64, Move)> = Vec::new();
+        for child in variables.iter() {
+            let parents = bn.parents(child).clone();
+            let current = scores.score(child, &parents);
+            for parent in variables.iter().filter(|p| *p != child) {
+                if parents.contains(parent) {
+                    let without: Vec<String> =
+                        parents.iter().filter(|p| *p != parent).cloned().collect();
+                    let removed = scores.score(child, &without) - current;
+                    moves.push((removed, Move::Remove(parent, child)));
+
+                    let mut reversed = bn.parents(parent).clone();
+                    let before = scores.score(parent, &reversed);
+                    reversed.push(child.clone());
+                    let gain = removed + scores.score(parent, &reversed) - before;
+                    moves.push((gain, Move::Reverse(parent, child)));
+                } else if !bn.parents(parent).contains(child) {
+                    let mut with = parents.clone();
+                    with.push(parent.clone());
+                    let added = scores.score(child, &with) - current;
+                    moves.push((added, Move::Add(parent, child)));
+                }
+            }
+        }
+        moves.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+        // apply the best move that keeps the graph acyclic and leads somewhere
+        // not visited recently
+        let applied = moves.into_iter().find_map(|(gain, m)| {
+            let mut next = bn.clone();
+            let result = match m {
+                Move::Add(parent, child) => next.add_edge(parent, child),
+                Move::Remove(parent, child) => next.remove_edge(parent, child),
+                Move::Reverse(parent, child) => next
+                    .remove_edge(parent, child)
+                    .and_then(|_| next.add_edge(child, parent)),
+            };
+            let graph = graph_of(&next);
+            (result.is_ok() && !tabu.contains(&graph)).then_some((gain, next, graph))
+        });
+        let Some((gain, next, graph)) = applied else {
+            break;
+        };
+        bn = next;
+        score += gain;
+        tabu.push_back(graph);
+        if tabu.len() > TABU_LENGTH {
+            tabu.pop_front();
+        }
+        if score > best_score + 1e-9 {
+            best_score = score;
+            best = bn.clone();
+            non_improving = 0;
+        } else {
+            non_improving += 1;
+            if non_improving == MAX_NON_IMPROVING_MOVES {
+                break;
+            }
+        }
+    }
+    BayesianNetwork::fit_cpts_mle(&best, data, 0.0)
+}
+
+/// The parents of each variable, sorted, as a key for the tabu list
+fn graph_of(bn: &BayesianNetwork) -> BTreeMap<String, Vec<String>> {
+    bn.variables()
+        .iter()
+        .map(|v| {
+            let mut parents = bn.parents(v).clone();
+            parents.sort();
+            (v.clone(), parents)
+        })
+        .collect()
+}
+
+/// Computes and caches the BIC score of each variable given a set of
+/// parents
+struct FamilyScores<'a> {
+    /// the number of states of each variable
+    cardinalities: HashMap<String, usize>,
+    data: &'a [HashMap<String, String>],
+    /// maps (variable, sorted parents) -> score
+    cache: HashMap<(String, Vec<String>), f64>,
+}
+
+impl<'a> FamilyScores<'a> {
+    fn new(network: &BayesianNetwork, data: &'a [HashMap<String, String>]) -> Self {
+        FamilyScores {
+            cardinalities: network
+                .variables()
+                .iter()
+                .map(|v| (v.clone(), network.cardinality(v)))
+                .collect(),
+            data,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// the log-likelihood of `variable`'s column of the data given its
+    /// `parents`' columns under maximum-likelihood estimates, minus the BIC
+    /// penalty for the family's free parameters
+    fn score(&mut self, variable: &str, parents: &[String]) -> f64 {
+        let mut parents = parents.to_vec();
+        parents.sort();
+        let key = (variable.to_string(), parents);
+        if let Some(score) = self.cache.get(&key) {
+            return *score;
+        }
+        let parents = &key.1;
+        let mut counts: BTreeMap<Vec<&String>, HashMap<&String, usize>> = BTreeMap::new();
+        for row in self.data.iter() {
+            let config: Vec<&String> = parents.iter().map(|p| &row[p]).collect();
+            *counts
+                .entry(config)
+                .or_default()
+                .entry(&row[variable])
+                .or_default() += 1;
+        }
+        let log_likelihood: f64 = counts
+            .values()
+            .map(|column| {
+                let total: usize = column.values().sum();
+                column
+                    .values()
+                    .map(|n| *n as f64 * (*n as f64 / total as f64).ln())
+                    .sum::<f64>()
+            })
+            .sum();
+        let num_columns: usize = parents.iter().map(|p| self.cardinalities[p]).product();
+        let num_parameters = (self.cardinalities[variable] - 1) * num_columns;
+        let penalty = (self.data.len() as f64).ln() / 2.0 * num_parameters as f64;
+
+        let score = log_likelihood - penalty;
+        self.cache.insert(key, score);
+        score
+    }
+}
+
+#[test]
+fn test_hill_climb() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    // the chain A -> B -> C plus the independent D
+    static NETWORK: &str = r#"{
+        "network": "chain",
+        "variables": ["A", "B", "C", "D"],
+        "cpts": {
+            "A": [[0.3], [0.7]],
+            "B": [[0.9, 0.2], [0.1, 0.8]],
+            "C": [[0.85, 0.1], [0.15, 0.9]],
+            "D": [[0.4], [0.6]]
+        },
+        "states": {
+            "A": ["F", "T"],
+            "B": ["F", "T"],
+            "C": ["F", "T"],
+            "D": ["F", "T"]
+        },
+        "parents" :{
+            "A": [],
+            "B": ["A"],
+            "C": ["B"],
+            "D": []
+        }
+    }"#;
+
+    let truth = BayesianNetwork::from_json(NETWORK);
+    let data = truth.sample_n(5000, &mut StdRng::seed_from_u64(1));
+    let learned = hill_climb(&data, truth.variables(), &truth.states).unwrap();
+
+    // a chain is only identifiable up to edge direction, but its skeleton is
+    let adjacent = |x: &str, y: &str| {
+        learned.parents(x).iter().any(|p| p == y) || learned.parents(y).iter().any(|p| p == x)
+    };
+    assert!(adjacent("A", "B") && adjacent("B", "C"));
+    assert!(!adjacent("A", "C"));
+    assert!(["A", "B", "C"].iter().all(|v| !adjacent(v, "D")));
+    assert!(learned.check_normalized(1e-9).is_ok());
+    // the learned network explains the data about as well as the truth
+    let ll = learned.log_likelihood(&data).unwrap();
+    assert!(ll >= truth.log_likelihood(&data).unwrap() - 5.0);
+
+    let mut missing = truth.states.clone();
+    missing.remove("D");
+    assert!(matches!(
+        hill_climb(&data, truth.variables(), &missing),
+        Err(BnError::UnknownVariable(v)) if v == "D"
+    ));
+}
+
+#[test]
+fn test_hill_climb_collider() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    // the collider A -> C <- B, with C a noisy OR of A and B
+    static NETWORK: &str = r#"{
+        "network": "collider",
+        "variables": ["A", "B", "C"],
+        "cpts": {
+            "A": [[0.6], [0.4]],
+            "B": [[0.5], [0.5]],
+            "C": [[0.9, 0.15, 0.25, 0.05], [0.1, 0.85, 0.75, 0.95]]
+        },
+        "states": {
+            "A": ["F", "T"],
+            "B": ["F", "T"],
+            "C": ["F", "T"]
+        },
+        "parents" :{
+            "A": [],
+            "B": [],
+            "C": ["A", "B"]
+        }
+    }"#;
+
+    let truth = BayesianNetwork::from_json(NETWORK);
+    let data = truth.sample_n(5000, &mut StdRng::seed_from_u64(2));
+    let learned = hill_climb(&data, truth.variables(), &truth.states).unwrap();
+
+    // unlike a chain, the v-structure fixes the direction of both edges
+    let mut parents = learned.parents("C").clone();
+    parents.sort();
+    assert_eq!(parents, vec!["A", "B"]);
+    assert!(learned.parents("A").is_empty());
+    assert!(learned.parents("B").is_empty());
+}
@@ -0,0 +1,101 @@
+//! Notes about data that an operation quietly corrected instead of rejecting,
+//! or about results it could compute but not vouch for
+
+use std::fmt;
+
+/// One correction made to a network, or one caveat about a result. `column`
+/// names a CPT column as `C|A=T,B=F` (or just `C` for a variable without
+/// parents).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    /// the column summed to `sum` and was rescaled to sum to 1
+    ColumnRescaled { column: String, sum: f64 },
+    /// the column summed to 0, so it could not be rescaled and was left as is
+    ZeroColumn { column: String },
+    /// the entry for `state` was `value`, outside [0, 1], and was clamped
+    ProbabilityClamped {
+        column: String,
+        state: String,
+        value: f64,
+    },
+    /// the entry for `state` was NaN and was set to 0
+    NanProbability { column: String, state: String },
+    /// only `accepted` of `drawn` samples agreed with the evidence, too few
+    /// for a sampled estimate to be reliable
+    LowAcceptance { accepted: usize, drawn: usize },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::ColumnRescaled { column, sum } => {
+                write!(f, "column for {column} summed to {sum}, rescaled")
+            }
+            Warning::ZeroColumn { column } => {
+                write!(f, "column for {column} summed to 0, left unchanged")
+            }
+            Warning::ProbabilityClamped {
+                column,
+                state,
+                value,
+            } => write!(
+                f,
+                "entry {state} in column for {column} was {value}, clamped to [0, 1]"
+            ),
+            Warning::NanProbability { column, state } => {
+                write!(f, "entry {state} in column for {column} was NaN, set to 0")
+            }
+            Warning::LowAcceptance { accepted, drawn } => write!(
+                f,
+                "only {accepted} of {drawn} samples agreed with the evidence"
+            ),
+        }
+    }
+}
+
+/// Accumulates the [`Warning`]s raised by loading and repair methods, which
+/// take a `&mut Warnings` and push onto it as they go
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Warnings {
+    warnings: Vec<Warning>,
+}
+
+impl Warnings {
+    pub fn new() -> Warnings {
+        Warnings::default()
+    }
+
+    pub fn push(&mut self, warning: Warning) {
+        self.warnings.push(warning);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.warnings.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.warnings.len()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Warning> {
+        self.warnings.iter()
+    }
+}
+
+impl IntoIterator for Warnings {
+    type Item = Warning;
+    type IntoIter = std::vec::IntoIter<Warning>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.warnings.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Warnings {
+    type Item = &'a Warning;
+    type IntoIter = std::slice::Iter<'a, Warning>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.warnings.iter()
+    }
+}
@@ -0,0 +1,416 @@
+//! Import of GeNIe/SMILE `.xdsl` networks
+//!
+//! Only the subset of XML that `.xdsl` files use is understood: elements,
+//! attributes, text, the predefined entities and character references,
+//! comments, processing instructions, and CDATA.
+
+use std::{collections::HashMap, sync::OnceLock};
+
+use crate::{BayesianNetwork, BnError};
+
+fn syntax_error(message: impl Into<String>) -> BnError {
+    BnError::Syntax {
+        format: "XDSL",
+        message: message.into(),
+    }
+}
+
+/// an XML element with its attributes, child elements, and concatenated text
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|c| c.name == name)
+    }
+}
+
+/// Decodes the predefined entities (`&lt;`, `&gt;`, `&amp;`, `&quot;`,
+/// `&apos;`) and character references (`&#60;`, `&#x3C;`) in `text`, in a
+/// single pass so that a decoded `&` never starts another reference
+fn unescape(text: &str) -> Result<String, BnError> {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp + 1..];
+        let end = rest
+            .find(';')
+            .ok_or_else(|| syntax_error("unterminated entity reference"))?;
+        let entity = &rest[..end];
+        let c = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        decoded.push(c.ok_or_else(|| syntax_error(format!("unknown entity &{entity};")))?);
+        rest = &rest[end + 1..];
+    }
+    decoded.push_str(rest);
+    Ok(decoded)
+}
+
+/// the index of the `>` that closes the tag starting `rest`, skipping any
+/// `>` inside a quoted attribute value
+fn tag_end(rest: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in rest.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '>' => return Some(i),
+            None => {}
+        }
+    }
+    None
+}
+
+/// Parses an XML document into its root element
+fn parse_xml(text: &str) -> Result<Element, BnError> {
+    // the stack of open elements; the bottom one collects the root
+    let mut stack: Vec<Element> = vec![Element::default()];
+    let mut rest = text;
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            stack.last_mut().unwrap().text.push_str(&unescape(rest)?);
+            break;
+        };
+        stack
+            .last_mut()
+            .unwrap()
+            .text
+            .push_str(&unescape(&rest[..start])?);
+        rest = &rest[start..];
+
+        let skip_to = |rest: &str, end: &str| -> Result<usize, BnError> {
+            rest.find(end)
+                .map(|i| i + end.len())
+                .ok_or_else(|| syntax_error(format!("unterminated {}", &rest[..2])))
+        };
+        if rest.starts_with("<!--") {
+            rest = &rest[skip_to(rest, "-->")?..];
+        } else if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+            let end = cdata
+                .find("]]>")
+                .ok_or_else(|| syntax_error("unterminated CDATA section"))?;
+            stack.last_mut().unwrap().text.push_str(&cdata[..end]);
+            rest = &cdata[end + 3..];
+        } else if rest.starts_with("<?") || rest.starts_with("<!") {
+            rest = &rest[skip_to(rest, ">")?..];
+        } else if let Some(close) = rest.strip_prefix("</") {
+            let end = close
+                .find('>')
+                .ok_or_else(|| syntax_error("unterminated closing tag"))?;
+            let name = close[..end].trim();
+            let element = stack.pop().unwrap();
+            if element.name != name || stack.is_empty() {
+                return Err(syntax_error(format!("unexpected closing tag </{name}>")));
+            }
+            stack.last_mut().unwrap().children.push(element);
+            rest = &close[end + 1..];
+        } else {
+            let end = tag_end(rest).ok_or_else(|| syntax_error("unterminated tag"))?;
+            let tag = &rest[1..end];
+            let (tag, self_closing) = match tag.strip_suffix('/') {
+                Some(tag) => (tag, true),
+                None => (tag, false),
+            };
+            let element = parse_tag(tag)?;
+            if self_closing {
+                stack.last_mut().unwrap().children.push(element);
+            } else {
+                stack.push(element);
+            }
+            rest = &rest[end + 1..];
+        }
+    }
+    if stack.len() != 1 {
+        return Err(syntax_error(format!(
+            "unclosed element <{}>",
+            stack.last().unwrap().name
+        )));
+    }
+    stack
+        .pop()
+        .unwrap()
+        .children
+        .pop()
+        .ok_or_else(|| syntax_error("document has no root element"))
+}
+
+/// Parses the inside of an opening tag, e.g. `cpt id="A"`
+fn parse_tag(tag: &str) -> Result<Element, BnError> {
+    let tag = tag.trim();
+    let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+    let mut element = Element {
+        name: tag[..name_end].to_string(),
+        ..Default::default()
+    };
+    let mut rest = tag[name_end..].trim_start();
+    while !rest.is_empty() {
+        let eq = rest
+            .find('=')
+            .ok_or_else(|| syntax_error(format!("malformed attribute in <{tag}>")))?;
+        let name = rest[..eq].trim().to_string();
+        let value = rest[eq + 1..].trim_start();
+        let quote = value
+            .chars()
+            .next()
+            .filter(|c| *c == '"' || *c == '\'')
+            .ok_or_else(|| syntax_error(format!("unquoted attribute {name} in <{tag}>")))?;
+        let close = value[1..]
+            .find(quote)
+            .ok_or_else(|| syntax_error(format!("unterminated attribute {name} in <{tag}>")))?;
+        element
+            .attributes
+            .push((name, unescape(&value[1..close + 1])?));
+        rest = value[close + 2..].trim_start();
+    }
+    Ok(element)
+}
+
+impl BayesianNetwork {
+    /// Reads a network in GeNIe/SMILE's `.xdsl` format.
+    ///
+    /// Every node must be a `<cpt>` node. Its `<state>` elements become the
+    /// variable's states, `<parents>` lists its parents, and `<probabilities>`
+    /// lists its CPT with the variable's state varying fastest and then the
+    /// parents' configurations with the last parent varying fastest, i.e.
+    /// one CPT column after another in the order used by this crate. The
+    /// network is named by the `id` of the root `<smile>` element.
+    /// ```
+    /// use rsgm::BayesianNetwork;
+    /// use std::collections::HashMap;
+    ///
+    /// // models the collider A, B -> C
+    /// static NETWORK: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+    /// <smile version="1.0" id="toy_network" numsamples="10000">
+    ///     <nodes>
+    ///         <cpt id="A">
+    ///             <state id="F" />
+    ///             <state id="T" />
+    ///             <probabilities>0.5 0.5</probabilities>
+    ///         </cpt>
+    ///         <cpt id="B">
+    ///             <state id="F" />
+    ///             <state id="T" />
+    ///             <probabilities>0.25 0.75</probabilities>
+    ///         </cpt>
+    ///         <cpt id="C">
+    ///             <state id="F" />
+    ///             <state id="T" />
+    ///             <parents>A B</parents>
+    ///             <probabilities>0.9 0.1 0.8 0.2 0.3 0.7 0.4 0.6</probabilities>
+    ///         </cpt>
+    ///     </nodes>
+    /// </smile>"#;
+    ///
+    /// let bn = BayesianNetwork::from_xdsl(NETWORK).unwrap();
+    ///
+    /// assert_eq!(bn.variables(), &vec!["A", "B", "C"]);
+    /// assert_eq!(bn.conditional_probability("C", "T", &HashMap::from([
+    ///     (String::from("A"), String::from("T")),
+    ///     (String::from("B"), String::from("F"))
+    /// ])), 0.7);
+    /// ```
+    pub fn from_xdsl(text: &str) -> Result<BayesianNetwork, BnError> {
+        let root = parse_xml(text)?;
+        if root.name != "smile" {
+            return Err(syntax_error(format!(
+                "expected root element <smile>, found <{}>",
+                root.name
+            )));
+        }
+        let nodes = root
+            .child("nodes")
+            .ok_or_else(|| syntax_error("missing <nodes> element"))?;
+
+        let mut bn = BayesianNetwork {
+            network: root.attribute("id").unwrap_or_default().to_string(),
+            variables: Vec::new(),
+            cpts: HashMap::new(),
+            states: HashMap::new(),
+            parents: HashMap::new(),
+            metadata: HashMap::new(),
+            topological_order: OnceLock::new(),
+        };
+        let mut probabilities: HashMap<String, Vec<f64>> = HashMap::new();
+        for node in nodes.children.iter() {
+            if node.name != "cpt" {
+                return Err(syntax_error(format!(
+                    "unsupported node type <{}>",
+                    node.name
+                )));
+            }
+            let id = node
+                .attribute("id")
+                .ok_or_else(|| syntax_error("<cpt> without an id"))?
+                .to_string();
+            if bn.states.contains_key(&id) {
+                return Err(BnError::VariableCollision(id));
+            }
+            let mut states: Vec<String> = Vec::new();
+            for state in node.children.iter().filter(|c| c.name == "state") {
+                let state = state
+                    .attribute("id")
+                    .ok_or_else(|| syntax_error(format!("<state> of {id} without an id")))?
+                    .to_string();
+                if states.contains(&state) {
+                    return Err(BnError::DuplicateState(id, state));
+                }
+                states.push(state);
+            }
+            let parents: Vec<String> = node
+                .child("parents")
+                .map(|p| p.text.split_whitespace().map(String::from).collect())
+                .unwrap_or_default();
+            let values = node
+                .child("probabilities")
+                .ok_or_else(|| syntax_error(format!("{id} has no <probabilities>")))?
+                .text
+                .split_whitespace()
+                .map(|p| {
+                    p.parse::<f64>()
+                        .map_err(|_| syntax_error(format!("{id} has non-numeric probability {p}")))
+                })
+                .collect::<Result<Vec<f64>, BnError>>()?;
+
+            bn.variables.push(id.clone());
+            bn.states.insert(id.clone(), states);
+            bn.parents.insert(id.clone(), parents);
+            probabilities.insert(id, values);
+        }
+
+        for variable in bn.variables.iter() {
+            for parent in bn.parents[variable].iter() {
+                if !bn.states.contains_key(parent) {
+                    return Err(BnError::UnknownVariable(parent.clone()));
+                }
+            }
+            let rows = bn.num_states(variable);
+            let columns: usize = bn.parents[variable]
+                .iter()
+                .map(|p| bn.num_states(p))
+                .product();
+            let values = &probabilities[variable];
+            if rows == 0 || values.len() != rows * columns {
+                return Err(BnError::CptShape {
+                    variable: variable.clone(),
+                    expected: (rows, columns),
+                });
+            }
+            let cpt = (0..rows)
+                .map(|s| (0..columns).map(|c| values[c * rows + s]).collect())
+                .collect();
+            bn.cpts.insert(variable.clone(), cpt);
+        }
+        bn.try_topological_sort()?;
+        Ok(bn)
+    }
+}
+
+#[test]
+fn test_from_xdsl_errors() {
+    fn network(c_node: &str) -> String {
+        format!(
+            r#"<?xml version="1.0"?>
+            <!-- a comment -->
+            <smile version="1.0" id="broken">
+                <nodes>
+                    <cpt id="A">
+                        <state id="F" /><state id="T" />
+                        <probabilities>0.5 0.5</probabilities>
+                    </cpt>
+                    {c_node}
+                </nodes>
+            </smile>"#
+        )
+    }
+
+    let ok = BayesianNetwork::from_xdsl(&network(
+        r#"<cpt id="C"><state id="x"/><state id="y"/><state id="z"/>
+            <parents>A</parents><probabilities>0.2 0.3 0.5 1 0 0</probabilities></cpt>"#,
+    ))
+    .unwrap();
+    assert_eq!(
+        ok.cpts["C"],
+        vec![vec![0.2, 1.0], vec![0.3, 0.0], vec![0.5, 0.0]]
+    );
+
+    assert!(matches!(
+        BayesianNetwork::from_xdsl(&network(
+            r#"<cpt id="C"><state id="x"/><parents>A</parents>
+                <probabilities>1 1 1</probabilities></cpt>"#
+        )),
+        Err(BnError::CptShape {
+            expected: (1, 2),
+            ..
+        })
+    ));
+    assert!(matches!(
+        BayesianNetwork::from_xdsl(&network(
+            r#"<cpt id="C"><state id="x"/><parents>Q</parents>
+                <probabilities>1</probabilities></cpt>"#
+        )),
+        Err(BnError::UnknownVariable(v)) if v == "Q"
+    ));
+    assert!(matches!(
+        BayesianNetwork::from_xdsl(&network(r#"<noisymax id="C"><state id="x"/></noisymax>"#)),
+        Err(BnError::Syntax { .. })
+    ));
+    assert!(matches!(
+        BayesianNetwork::from_xdsl(&network("<cpt id=\"C\">")),
+        Err(BnError::Syntax { .. })
+    ));
+    assert!(matches!(
+        BayesianNetwork::from_xdsl(&network(r#"<cpt id="C&nbsp;"/>"#)),
+        Err(BnError::Syntax { .. })
+    ));
+}
+
+#[test]
+fn test_from_xdsl_escapes() {
+    // ids with escaped characters, and a `>` that needs no escaping inside
+    // an attribute value
+    let bn = BayesianNetwork::from_xdsl(
+        r#"<smile version="1.0" id="a&amp;b">
+            <nodes>
+                <cpt id="x>0">
+                    <state id="&lt;0" /><state id='&quot;&#48;&#x22;' />
+                    <probabilities>0.5 0.5</probabilities>
+                </cpt>
+                <cpt id="&amp;lt;">
+                    <state id="F" /><state id="T" />
+                    <parents>x&gt;0</parents>
+                    <probabilities>0.9 0.1 0.2 0.8</probabilities>
+                </cpt>
+            </nodes>
+        </smile>"#,
+    )
+    .unwrap();
+    assert_eq!(bn.network, "a&b");
+    assert_eq!(bn.variables(), &vec!["x>0", "&lt;"]);
+    assert_eq!(bn.all_possible_assignments("x>0"), &vec!["<0", "\"0\""]);
+    assert_eq!(bn.parents("&lt;"), &vec!["x>0"]);
+}